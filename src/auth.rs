@@ -0,0 +1,21 @@
+pub mod credentials;
+pub mod md5;
+pub mod scram;
+pub mod secrets;
+
+// Takes the same time wherever the secrets differ, so a client can't guess them byte by byte.
+pub(crate) fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0, |diff, (l, r)| diff | (l ^ r)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::constant_time_eq;
+
+    #[test]
+    fn compared_in_constant_time() {
+        assert!(constant_time_eq(b"md5abc", b"md5abc"));
+        assert!(!constant_time_eq(b"md5abc", b"md5abd"));
+        assert!(!constant_time_eq(b"md5abc", b"md5ab"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::auth::secrets::{Secret, expect_end, parse_lines, take_word};
+
+use ::std::collections::HashMap;
+use ::std::fmt::{self, Debug, Formatter};
+use ::std::fs;
+use ::std::io;
+
+// Lines like "username" "database" "secret" where the secret is written as in Secrets and "*" stands for any database.
+// Backend is answered with the secret on behalf of the frontend connecting as the user to the database.
+pub struct Credentials {
+    secrets: HashMap<Key, Secret>,
+}
+
+type Key = (Vec<u8>, Option<Vec<u8>>);  // user and database which is None for any
+
+impl Credentials {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, err)))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let secrets = parse_lines(text, parse_line)?.into_iter().collect();
+        Ok(Self { secrets })
+    }
+
+    pub fn get(&self, user: &[u8], database: &[u8]) -> Option<&Secret> {
+        self.secrets.get(&(user.to_vec(), Some(database.to_vec())))
+            .or_else(|| self.secrets.get(&(user.to_vec(), None)))
+    }
+}
+
+fn parse_line(line: &str) -> Result<(Key, Secret), String> {
+    let (user, rest) = take_word(line, "user")?;
+    let (database, rest) = take_word(rest, "database")?;
+    let (secret, rest) = take_word(rest, "secret")?;
+    expect_end(rest)?;
+    let database = match database.as_str() {
+        "*" => None,
+        _ => Some(database.into_bytes()),
+    };
+    Ok(((user.into_bytes(), database), Secret::parse(&secret)?))
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // only users and databases are shown like Secrets does
+        let mut keys: Vec<_> = self.secrets.keys()
+            .map(|(user, database)| format!(
+                "{}@{}",
+                String::from_utf8_lossy(user),
+                database.as_ref().map_or("*".into(), |database| String::from_utf8_lossy(database)),
+            ))
+            .collect();
+        keys.sort();
+        f.debug_struct("Credentials")
+            .field("secrets", &keys)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Credentials;
+    use crate::auth::secrets::Secret;
+
+    #[test]
+    fn file() {
+        let credentials = Credentials::parse("\
+            # user database secret\n\
+            alice prod \"prod password\"\n\
+            alice * md53175bce1d3201d16594cebf9d7eb3f9d\n\
+            bob stage qwerty\n\
+        ").unwrap();
+        assert_eq!(Some(&Secret::Password(Vec::from("prod password"))), credentials.get(b"alice", b"prod"));
+        assert_eq!(Some(&Secret::Md5(Vec::from("3175bce1d3201d16594cebf9d7eb3f9d"))), credentials.get(b"alice", b"stage"));
+        assert_eq!(Some(&Secret::Password(Vec::from("qwerty"))), credentials.get(b"bob", b"stage"));
+        assert_eq!(None, credentials.get(b"bob", b"prod"));
+        assert_eq!(r#"Credentials { secrets: ["alice@*", "alice@prod", "bob@stage"] }"#, format!("{:?}", credentials));
+    }
+
+    #[test]
+    fn file_with_errors() {
+        assert_eq!(Err("line 1: database expected".into()), Credentials::parse("alice").map(|_| ()));
+        assert_eq!(Err("line 1: secret expected".into()), Credentials::parse("alice prod").map(|_| ()));
+        assert_eq!(Err("line 1: unexpected \"extra\" after the secret".into()), Credentials::parse("alice prod pwd extra").map(|_| ()));
+    }
+}
@@ -0,0 +1,33 @@
+use ::md5::{Digest, Md5};
+
+// https://www.postgresql.org/docs/current/protocol-flow.html#id-1.10.6.7.2
+// Frontend answers AuthenticationMD5Password with "md5" followed by hex of md5(password hash + salt)
+// where the password hash is hex of md5(password + user), the one pg_authid keeps after "md5" too.
+
+pub fn password_hash(password: &[u8], user: &[u8]) -> Vec<u8> {
+    hex_md5(&[password, user].concat())
+}
+
+pub fn salted_password(password_hash: &[u8], salt: [u8; 4]) -> Vec<u8> {
+    [b"md5", &hex_md5(&[password_hash, &salt].concat())[..]].concat()
+}
+
+pub fn random_salt() -> [u8; 4] {
+    rand::random()
+}
+
+fn hex_md5(data: &[u8]) -> Vec<u8> {
+    hex::encode(Md5::digest(data)).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{password_hash, salted_password};
+
+    #[test]
+    fn password() {
+        let hash = password_hash(b"postgres", b"postgres");
+        assert_eq!(b"3175bce1d3201d16594cebf9d7eb3f9d", &hash[..]);
+        assert_eq!(b"md568be9ed08db75f318087ab337aaea044", &salted_password(&hash, [1, 2, 3, 4])[..]);
+    }
+}
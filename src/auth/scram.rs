@@ -0,0 +1,420 @@
+use crate::auth::constant_time_eq;
+use crate::auth::secrets::ScramSecret;
+use crate::msg::body::SaslInitialResponse;
+use crate::msg::scram::{ChannelBinding, ClientFinal, ClientFirst, Mechanism, ServerFinal, ServerFirst};
+
+use ::hmac::{Hmac, Mac};
+use ::rand::RngCore;
+use ::sha2::{Digest, Sha256};
+
+// https://tools.ietf.org/html/rfc5802#section-3
+// postgread may know StoredKey and ServerKey of a user but not the password. As server to frontend
+// it recovers ClientKey from the frontend's proof, and as client to backend it proves with that ClientKey.
+// So backend must have the same secret (salt and iterations too) as postgread has.
+// If postgread knows the password it proves with whatever secret backend has.
+
+const NONCE_LEN: usize = 18;  // random bytes, the same as PostgreSQL generates
+const TLS_SERVER_END_POINT: &[u8] = b"tls-server-end-point";
+
+pub type ScramResult<T> = Result<T, String>;
+
+// postgread as server to frontend.
+pub struct ServerExchange {
+    secret: ScramSecret,
+    gs2_header: Vec<u8>,
+    channel_binding_data: Vec<u8>,  // empty if frontend doesn't bind
+    nonce: Vec<u8>,
+    auth_message: Vec<u8>,
+}
+
+// postgread as client to backend.
+pub struct ClientExchange {
+    mechanism: Mechanism,
+    gs2_header: &'static [u8],
+    channel_binding_data: Vec<u8>,  // empty if postgread doesn't bind
+    nonce: Vec<u8>,
+    auth_message: Vec<u8>,
+    server_key: Vec<u8>,  // known after server-first-message
+}
+
+pub enum ClientCredential {
+    Password(Vec<u8>),
+    ClientKey(ScramSecret, Vec<u8>),  // recovered by ServerExchange with the secret
+}
+
+impl ServerExchange {
+    // Mechanisms for AuthenticationSASL; -PLUS only over TLS which is required for channel binding.
+    pub fn mechanisms(end_point: &Option<Vec<u8>>) -> &'static [&'static str] {
+        const WITH_PLUS: &[&str] = &[Mechanism::ScramSha256Plus.name(), Mechanism::ScramSha256.name()];
+        const WITHOUT_PLUS: &[&str] = &[Mechanism::ScramSha256.name()];
+        match end_point {
+            Some(_) => WITH_PLUS,
+            None => WITHOUT_PLUS,
+        }
+    }
+
+    // Returns server-first-message to send.
+    pub fn start(
+        secret: ScramSecret,
+        initial_response: &SaslInitialResponse,
+        end_point: Option<Vec<u8>>,
+    ) -> ScramResult<(Self, Vec<u8>)> {
+        Self::start_with_nonce(secret, initial_response, end_point, random_nonce())
+    }
+
+    fn start_with_nonce(
+        secret: ScramSecret,
+        initial_response: &SaslInitialResponse,
+        end_point: Option<Vec<u8>>,
+        server_nonce: Vec<u8>,
+    ) -> ScramResult<(Self, Vec<u8>)> {
+        let client_first = match ClientFirst::parse(initial_response) {
+            Some(client_first) => client_first.map_err(|problem| format!("client-first-message is malformed: {:?}", problem))?,
+            None => return Err(format!(
+                "SASL mechanism {} is not supported",
+                String::from_utf8_lossy(&initial_response.selected_mechanism),
+            )),
+        };
+        let channel_binding_data = match (client_first.mechanism, &client_first.gs2_header.channel_binding, end_point) {
+            (Mechanism::ScramSha256Plus, ChannelBinding::Required(name), Some(end_point)) if name.0 == TLS_SERVER_END_POINT =>
+                end_point,
+            (Mechanism::ScramSha256Plus, _, _) =>
+                return Err("SCRAM-SHA-256-PLUS is selected without tls-server-end-point channel binding over TLS".into()),
+            (Mechanism::ScramSha256, ChannelBinding::Required(_), _) =>
+                return Err("channel binding is required without selecting SCRAM-SHA-256-PLUS".into()),
+            (Mechanism::ScramSha256, ChannelBinding::NotSupportedByServer, Some(_)) =>
+                // frontend could bind but didn't see -PLUS offered, so someone removed it on the way
+                return Err("channel binding is supported by both sides but not used".into()),
+            (Mechanism::ScramSha256, _, _) =>
+                vec![],
+        };
+        let data = initial_response.mechanism_data.as_deref().unwrap_or_default();
+        let (gs2_header, client_first_bare) = split_gs2_header(data)?;
+        let mut nonce = client_first.nonce.0;
+        nonce.extend(server_nonce);
+        let server_first = [
+            b"r=", &nonce[..],
+            b",s=", base64::encode(&secret.salt).as_bytes(),
+            b",i=", secret.iterations.to_string().as_bytes(),
+        ].concat();
+        let auth_message = [client_first_bare, b",", &server_first].concat();
+        Ok((Self { secret, gs2_header: gs2_header.to_vec(), channel_binding_data, nonce, auth_message }, server_first))
+    }
+
+    // Returns ClientKey of the user and server-final-message to send.
+    pub fn finish(self, mechanism_data: &[u8]) -> ScramResult<(Vec<u8>, Vec<u8>)> {
+        let Self { secret, gs2_header, channel_binding_data, nonce, mut auth_message } = self;
+        let client_final = ClientFinal::parse(mechanism_data)
+            .map_err(|problem| format!("client-final-message is malformed: {:?}", problem))?;
+        if client_final.channel_binding.0 != [gs2_header, channel_binding_data].concat() {
+            return Err("channel binding doesn't match".into())
+        }
+        if client_final.nonce.0 != nonce {
+            return Err("nonce doesn't match".into())
+        }
+        auth_message.push(b',');
+        auth_message.extend(without_proof(mechanism_data));
+        let client_signature = hmac(&secret.stored_key, &auth_message);
+        let client_key = xor(&client_final.proof.0, &client_signature)?;
+        if !constant_time_eq(Sha256::digest(&client_key).as_slice(), &secret.stored_key) {
+            return Err("proof is wrong".into())
+        }
+        let server_signature = hmac(&secret.server_key, &auth_message);
+        let server_final = [b"v=", base64::encode(&server_signature).as_bytes()].concat();
+        Ok((client_key, server_final))
+    }
+}
+
+impl ClientExchange {
+    // None if backend doesn't offer SCRAM-SHA-256. -PLUS is preferred if backend is connected with TLS.
+    pub fn new(backend_mechanisms: &[Vec<u8>], end_point: Option<Vec<u8>>) -> Option<Self> {
+        use Mechanism::*;
+        let offers = |mechanism: Mechanism| backend_mechanisms.iter().any(|offered| offered == mechanism.name().as_bytes());
+        let (mechanism, gs2_header, channel_binding_data): (_, &[u8], _) = match end_point {
+            Some(end_point) if offers(ScramSha256Plus) => (ScramSha256Plus, b"p=tls-server-end-point,,", end_point),
+            Some(_) if offers(ScramSha256) => (ScramSha256, b"y,,", vec![]),
+            None if offers(ScramSha256) => (ScramSha256, b"n,,", vec![]),
+            _ => return None,
+        };
+        Some(Self {
+            mechanism,
+            gs2_header,
+            channel_binding_data,
+            nonce: vec![],
+            auth_message: vec![],
+            server_key: vec![],
+        })
+    }
+
+    // Returns the selected mechanism and client-first-message to send.
+    pub fn start(&mut self) -> (Mechanism, Vec<u8>) {
+        self.start_with_nonce(random_nonce())
+    }
+
+    fn start_with_nonce(&mut self, nonce: Vec<u8>) -> (Mechanism, Vec<u8>) {
+        // username is empty as PostgreSQL takes it from Startup
+        let client_first_bare = [b"n=,r=", &nonce[..]].concat();
+        self.nonce = nonce;
+        self.auth_message = client_first_bare.clone();
+        (self.mechanism, [self.gs2_header, &client_first_bare].concat())
+    }
+
+    // Returns client-final-message to send.
+    pub fn proceed(&mut self, challenge_data: &[u8], credential: &ClientCredential) -> ScramResult<Vec<u8>> {
+        let server_first = ServerFirst::parse(challenge_data)
+            .map_err(|problem| format!("server-first-message is malformed: {:?}", problem))?;
+        if server_first.nonce.0.len() <= self.nonce.len() || !server_first.nonce.0.starts_with(&self.nonce) {
+            return Err("nonce doesn't match".into())
+        }
+        let (client_key, secret) = match credential {
+            ClientCredential::Password(password) =>
+                password_keys(password, server_first.salt.0, server_first.iterations),
+            ClientCredential::ClientKey(secret, _) if server_first.salt.0 != secret.salt || server_first.iterations != secret.iterations =>
+                return Err("backend has another secret of the user".into()),
+            ClientCredential::ClientKey(secret, client_key) =>
+                (client_key.clone(), secret.clone()),
+        };
+        let channel_binding = [self.gs2_header, &self.channel_binding_data].concat();
+        let without_proof = [
+            b"c=", base64::encode(&channel_binding).as_bytes(),
+            b",r=", &server_first.nonce.0,
+        ].concat();
+        self.auth_message.push(b',');
+        self.auth_message.extend(challenge_data);
+        self.auth_message.push(b',');
+        self.auth_message.extend(&without_proof);
+        let client_signature = hmac(&secret.stored_key, &self.auth_message);
+        let proof = xor(&client_key, &client_signature)?;
+        self.server_key = secret.server_key;
+        Ok([&without_proof[..], b",p=", base64::encode(&proof).as_bytes()].concat())
+    }
+
+    pub fn finish(self, additional_data: &[u8]) -> ScramResult<()> {
+        let server_final = ServerFinal::parse(additional_data)
+            .map_err(|problem| format!("server-final-message is malformed: {:?}", problem))?;
+        match server_final {
+            ServerFinal::Error(error) =>
+                Err(format!("backend answered with error {}", String::from_utf8_lossy(&error.0))),
+            ServerFinal::Verifier(verifier) if self.server_key.is_empty() || verifier.0 != hmac(&self.server_key, &self.auth_message) =>
+                Err("backend's signature is wrong".into()),
+            ServerFinal::Verifier(_) =>
+                Ok(()),
+        }
+    }
+}
+
+// Returns ClientKey and the secret as PostgreSQL derives them in scram_build_secret.
+// The password is taken as is without SASLprep which changes only non-ASCII passwords.
+fn password_keys(password: &[u8], salt: Vec<u8>, iterations: u32) -> (Vec<u8>, ScramSecret) {
+    let mut salted_password = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &salt, iterations, &mut salted_password);
+    let client_key = hmac(&salted_password, b"Client Key");
+    let secret = ScramSecret {
+        iterations,
+        salt,
+        stored_key: Sha256::digest(&client_key).to_vec(),
+        server_key: hmac(&salted_password, b"Server Key"),
+    };
+    (client_key, secret)
+}
+
+fn split_gs2_header(client_first: &[u8]) -> ScramResult<(&[u8], &[u8])> {
+    // gs2-header is "<flag>,[authzid],"
+    let mut commas = client_first.iter().enumerate().filter(|(_, &byte)| byte == b',').map(|(pos, _)| pos);
+    match (commas.next(), commas.next()) {
+        (Some(_), Some(second)) => Ok(client_first.split_at(second + 1)),
+        _ => Err("GS2 header is malformed".into()),
+    }
+}
+
+fn without_proof(client_final: &[u8]) -> &[u8] {
+    // proof is the last attribute which is already checked by parsing
+    let last_comma = client_final.iter().rposition(|&byte| byte == b',').unwrap_or(0);
+    &client_final[.. last_comma]
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes a key of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn xor(left: &[u8], right: &[u8]) -> ScramResult<Vec<u8>> {
+    if left.len() != right.len() {
+        return Err(format!("proof should have {} bytes but has {}", right.len(), left.len()))
+    }
+    Ok(left.iter().zip(right).map(|(l, r)| l ^ r).collect())
+}
+
+fn random_nonce() -> Vec<u8> {
+    let mut random = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut random);
+    base64::encode(random).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientCredential, ClientExchange, ServerExchange, password_keys};
+    use crate::auth::secrets::ScramSecret;
+    use crate::msg::body::SaslInitialResponse;
+    use crate::msg::scram::Mechanism::{self, *};
+
+    // https://tools.ietf.org/html/rfc7677#section-3 where the password is "pencil"
+    const SECRET: &str = "SCRAM-SHA-256$4096:W22ZaJ0SNY7soEsUEjb6gQ==$\
+        WG5d8oPm3OtcPnkdi4Uo7BkeZkBFzpcXkuLmtbsT4qY=:wfPLwcE6nTWhTAmQ7tl2KeoiWGPlZqQxSrmfPwDl2dU=";
+    const CLIENT_KEY: &str = "a60fc923d67e8644a92d16b96eda5ef4656b0c725c484374be25535576996e8b";
+    const CLIENT_NONCE: &[u8] = b"rOprNGfwEbeRWgbNEkqO";
+    const SERVER_NONCE: &[u8] = b"%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0";
+    const CLIENT_FIRST: &[u8] = b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO";
+    const SERVER_FIRST: &[u8] = b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096";
+    const CLIENT_FINAL: &[u8] = b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+        p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=";
+    const SERVER_FINAL: &[u8] = b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=";
+
+    fn secret() -> ScramSecret {
+        ScramSecret::parse(SECRET).unwrap()
+    }
+
+    fn client_key() -> ClientCredential {
+        ClientCredential::ClientKey(secret(), hex::decode(CLIENT_KEY).unwrap())
+    }
+
+    fn initial_response(mechanism: Mechanism, client_first: &[u8]) -> SaslInitialResponse {
+        SaslInitialResponse {
+            selected_mechanism: Vec::from(mechanism.name()),
+            mechanism_data: Some(client_first.to_vec()),
+        }
+    }
+
+    #[test]
+    fn server() {
+        let initial_response = initial_response(ScramSha256, CLIENT_FIRST);
+        let (server, server_first) = ServerExchange::start_with_nonce(secret(), &initial_response, None, SERVER_NONCE.to_vec()).unwrap();
+        assert_eq!(SERVER_FIRST, server_first.as_slice());
+        let (client_key, server_final) = server.finish(CLIENT_FINAL).unwrap();
+        assert_eq!(CLIENT_KEY, hex::encode(client_key));
+        assert_eq!(SERVER_FINAL, server_final.as_slice());
+    }
+
+    #[test]
+    fn server_with_wrong_proof() {
+        let initial_response = initial_response(ScramSha256, CLIENT_FIRST);
+        let (server, _) = ServerExchange::start_with_nonce(secret(), &initial_response, None, SERVER_NONCE.to_vec()).unwrap();
+        let client_final = b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+            p=AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+        assert_eq!(Err("proof is wrong".into()), server.finish(client_final).map(|_| ()));
+    }
+
+    #[test]
+    fn server_with_wrong_nonce() {
+        let initial_response = initial_response(ScramSha256, CLIENT_FIRST);
+        let (server, _) = ServerExchange::start_with_nonce(secret(), &initial_response, None, b"other".to_vec()).unwrap();
+        assert_eq!(Err("nonce doesn't match".into()), server.finish(CLIENT_FINAL).map(|_| ()));
+    }
+
+    #[test]
+    fn server_with_channel_binding() {
+        let initial_response = initial_response(ScramSha256Plus, b"p=tls-server-end-point,,n=,r=abc");
+        let end_point = Some(b"hash".to_vec());
+        let (server, _) = ServerExchange::start_with_nonce(secret(), &initial_response, end_point, b"def".to_vec()).unwrap();
+        let wrong_binding = [b"c=", base64::encode(b"p=tls-server-end-point,,other").as_bytes(), b",r=abcdef,p=AAAA"].concat();
+        assert_eq!(Err("channel binding doesn't match".into()), server.finish(&wrong_binding).map(|_| ()));
+    }
+
+    #[test]
+    fn server_rejects_plus_without_tls() {
+        let initial_response = initial_response(ScramSha256Plus, b"p=tls-server-end-point,,n=,r=abc");
+        assert!(ServerExchange::start(secret(), &initial_response, None).is_err());
+    }
+
+    #[test]
+    fn server_rejects_downgrade() {
+        let initial_response = initial_response(ScramSha256, b"y,,n=,r=abc");
+        assert!(ServerExchange::start(secret(), &initial_response, Some(b"hash".to_vec())).is_err());
+        assert!(ServerExchange::start(secret(), &initial_response, None).is_ok());
+    }
+
+    #[test]
+    fn server_mechanisms() {
+        assert_eq!(&["SCRAM-SHA-256"], ServerExchange::mechanisms(&None));
+        assert_eq!(&["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"], ServerExchange::mechanisms(&Some(vec![])));
+    }
+
+    #[test]
+    fn client() {
+        let mechanisms = vec![Vec::from("SCRAM-SHA-256")];
+        let mut client = ClientExchange::new(&mechanisms, None).unwrap();
+        let (mechanism, client_first) = client.start_with_nonce(CLIENT_NONCE.to_vec());
+        assert_eq!(ScramSha256, mechanism);
+        assert_eq!(b"n,,n=,r=rOprNGfwEbeRWgbNEkqO".to_vec(), client_first);
+        // the proof differs from RFC's one as the username is empty in client-first-message
+        let client_final = client.proceed(SERVER_FIRST, &client_key()).unwrap();
+        assert!(client_final.starts_with(b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p="));
+        assert_eq!(Err("backend's signature is wrong".into()), client.finish(SERVER_FINAL));
+    }
+
+    #[test]
+    fn client_against_server() {
+        let mechanisms = vec![Vec::from("SCRAM-SHA-256-PLUS"), Vec::from("SCRAM-SHA-256")];
+        let end_point = Some(b"hash".to_vec());
+        let mut client = ClientExchange::new(&mechanisms, end_point.clone()).unwrap();
+        let (mechanism, client_first) = client.start();
+        assert_eq!(ScramSha256Plus, mechanism);
+        let initial_response = initial_response(mechanism, &client_first);
+        let (server, server_first) = ServerExchange::start(secret(), &initial_response, end_point).unwrap();
+        let client_final = client.proceed(&server_first, &client_key()).unwrap();
+        let (client_key, server_final) = server.finish(&client_final).unwrap();
+        assert_eq!(CLIENT_KEY, hex::encode(client_key));
+        assert_eq!(Ok(()), client.finish(&server_final));
+    }
+
+    #[test]
+    fn client_with_password_against_server() {
+        let mechanisms = vec![Vec::from("SCRAM-SHA-256")];
+        let mut client = ClientExchange::new(&mechanisms, None).unwrap();
+        let (mechanism, client_first) = client.start();
+        let initial_response = initial_response(mechanism, &client_first);
+        let (server, server_first) = ServerExchange::start(secret(), &initial_response, None).unwrap();
+        let client_final = client.proceed(&server_first, &ClientCredential::Password(Vec::from("pencil"))).unwrap();
+        let (client_key, server_final) = server.finish(&client_final).unwrap();
+        assert_eq!(CLIENT_KEY, hex::encode(client_key));
+        assert_eq!(Ok(()), client.finish(&server_final));
+    }
+
+    #[test]
+    fn keys_of_password() {
+        let (client_key, secret) = password_keys(b"pencil", self::secret().salt, 4096);
+        assert_eq!(CLIENT_KEY, hex::encode(client_key));
+        assert_eq!(self::secret(), secret);
+    }
+
+    #[test]
+    fn client_without_channel_binding_over_tls() {
+        let mechanisms = vec![Vec::from("SCRAM-SHA-256")];
+        let mut client = ClientExchange::new(&mechanisms, Some(b"hash".to_vec())).unwrap();
+        let (_, client_first) = client.start();
+        assert!(client_first.starts_with(b"y,,n=,r="));
+    }
+
+    #[test]
+    fn client_without_scram() {
+        let mechanisms = vec![Vec::from("OTHER")];
+        assert!(ClientExchange::new(&mechanisms, None).is_none());
+    }
+
+    #[test]
+    fn client_with_another_secret() {
+        let mechanisms = vec![Vec::from("SCRAM-SHA-256")];
+        let mut client = ClientExchange::new(&mechanisms, None).unwrap();
+        client.start_with_nonce(CLIENT_NONCE.to_vec());
+        let server_first = b"r=rOprNGfwEbeRWgbNEkqOsuffix,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=10000";
+        assert_eq!(Err("backend has another secret of the user".into()), client.proceed(server_first, &client_key()));
+    }
+
+    #[test]
+    fn client_with_server_error() {
+        let mechanisms = vec![Vec::from("SCRAM-SHA-256")];
+        let client = ClientExchange::new(&mechanisms, None).unwrap();
+        assert_eq!(Err("backend answered with error invalid-proof".into()), client.finish(b"e=invalid-proof"));
+    }
+}
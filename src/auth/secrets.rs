@@ -0,0 +1,268 @@
+use ::sha2::{Digest, Sha256};
+use ::std::collections::HashMap;
+use ::std::fmt::{self, Debug, Formatter};
+use ::std::fs;
+use ::std::io;
+
+// Lines like pgbouncer's auth_file has: "username" "secret" where the secret is either rolpassword
+// from pg_authid (md5<hash> or SCRAM-SHA-256$<iterations>:<salt>$<StoredKey>:<ServerKey>) or the password itself.
+// Quotes are optional unless the name has spaces, a quote inside quotes is doubled.
+pub struct Secrets {
+    users: HashMap<Vec<u8>, Secret>,
+    mock_key: [u8; 32],  // random, salts mock secrets
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Secret {
+    Password(Vec<u8>),
+    Md5(Vec<u8>),  // hex of md5(password + user), see auth::md5
+    Scram(ScramSecret),
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ScramSecret {
+    pub iterations: u32,
+    pub salt: Vec<u8>,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+
+impl Secrets {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, err)))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let users = parse_lines(text, parse_line)?.into_iter().collect();
+        Ok(Self { users, mock_key: rand::random() })
+    }
+
+    pub fn get(&self, user: &[u8]) -> Option<&Secret> {
+        self.users.get(user)
+    }
+
+    // A secret nobody can answer for a user who has none, so the user is challenged like a known one
+    // and fails only after answering, as PostgreSQL does. It's SCRAM if any user has a SCRAM secret,
+    // and its salt is the same each time for the same user like a real secret's salt.
+    pub fn mock(&self, user: &[u8]) -> Secret {
+        let scram = self.users.values().find_map(|secret| match secret {
+            Secret::Scram(secret) => Some((secret.iterations, secret.salt.len())),
+            Secret::Password(_) | Secret::Md5(_) => None,
+        });
+        match scram {
+            Some((iterations, salt_len)) => Secret::Scram(ScramSecret {
+                iterations,
+                salt: Sha256::digest([&self.mock_key[..], user].concat())[.. salt_len.min(32)].to_vec(),
+                stored_key: rand::random::<[u8; 32]>().to_vec(),
+                server_key: rand::random::<[u8; 32]>().to_vec(),
+            }),
+            None => Secret::Md5(hex::encode(rand::random::<[u8; 16]>()).into_bytes()),
+        }
+    }
+}
+
+impl Secret {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.starts_with("SCRAM-SHA-256$") {
+            return ScramSecret::parse(text).map(Self::Scram)
+        }
+        match text.strip_prefix("md5") {
+            Some(hash) if hash.len() == 32 && hash.bytes().all(|byte| byte.is_ascii_hexdigit()) =>
+                Ok(Self::Md5(hash.to_ascii_lowercase().into_bytes())),
+            _ =>
+                Ok(Self::Password(text.into())),
+        }
+    }
+}
+
+impl ScramSecret {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let incorrect = || "SCRAM secret should look like SCRAM-SHA-256$<iterations>:<salt>$<StoredKey>:<ServerKey>".to_string();
+        let rest = text.strip_prefix("SCRAM-SHA-256$").ok_or_else(incorrect)?;
+        let (iterations_and_salt, keys) = split_once(rest, '$').ok_or_else(incorrect)?;
+        let (iterations, salt) = split_once(iterations_and_salt, ':').ok_or_else(incorrect)?;
+        let (stored_key, server_key) = split_once(keys, ':').ok_or_else(incorrect)?;
+        Ok(Self {
+            iterations: iterations.parse().map_err(|_| incorrect())?,
+            salt: decode_base64("salt", salt)?,
+            stored_key: decode_base64("StoredKey", stored_key)?,
+            server_key: decode_base64("ServerKey", server_key)?,
+        })
+    }
+}
+
+pub(crate) fn parse_lines<T>(text: &str, parse_line: fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    let mut parsed = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue
+        }
+        parsed.push(parse_line(line).map_err(|err| format!("line {}: {}", index + 1, err))?);
+    }
+    Ok(parsed)
+}
+
+fn parse_line(line: &str) -> Result<(Vec<u8>, Secret), String> {
+    let (user, rest) = take_word(line, "user and secret")?;
+    let (secret, rest) = take_word(rest, "user and secret")?;
+    expect_end(rest)?;
+    Ok((user.into_bytes(), Secret::parse(&secret)?))
+}
+
+pub(super) fn expect_end(rest: &str) -> Result<(), String> {
+    match rest.trim() {
+        "" => Ok(()),
+        rest => Err(format!("unexpected {:?} after the secret", rest)),
+    }
+}
+
+// `expected` names what's missing in the error for an empty text.
+pub(crate) fn take_word<'a>(text: &'a str, expected: &str) -> Result<(String, &'a str), String> {
+    let text = text.trim_start();
+    match text.strip_prefix('"') {
+        Some(mut rest) => {
+            let mut word = String::new();
+            loop {
+                let quote = rest.find('"').ok_or_else(|| "quote is not closed".to_string())?;
+                word.push_str(&rest[.. quote]);
+                rest = &rest[quote + 1 ..];
+                match rest.strip_prefix('"') {
+                    Some(after_doubled) => {
+                        word.push('"');
+                        rest = after_doubled;
+                    },
+                    None => return Ok((word, rest)),
+                }
+            }
+        },
+        None if text.is_empty() => Err(format!("{} expected", expected)),
+        None => {
+            let end = text.find(char::is_whitespace).unwrap_or(text.len());
+            Ok((text[.. end].to_string(), &text[end ..]))
+        },
+    }
+}
+
+fn split_once(text: &str, delimiter: char) -> Option<(&str, &str)> {
+    let pos = text.find(delimiter)?;
+    Some((&text[.. pos], &text[pos + 1 ..]))
+}
+
+fn decode_base64(name: &str, value: &str) -> Result<Vec<u8>, String> {
+    base64::decode(value).map_err(|err| format!("{} is not base64: {}", name, err))
+}
+
+impl Debug for Secrets {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // keys are as sensitive as passwords so only users are shown
+        let mut users: Vec<_> = self.users.keys().map(|user| String::from_utf8_lossy(user)).collect();
+        users.sort();
+        f.debug_struct("Secrets")
+            .field("users", &users)
+            .finish()
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Password(_) => f.write_str("Password"),
+            Self::Md5(_) => f.write_str("Md5"),
+            Self::Scram(secret) => f.debug_tuple("Scram").field(secret).finish(),
+        }
+    }
+}
+
+impl Debug for ScramSecret {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ScramSecret")
+            .field("iterations", &self.iterations)
+            .field("salt", &base64::encode(&self.salt))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScramSecret, Secret, Secrets};
+
+    const SECRET: &str = "SCRAM-SHA-256$4096:W22ZaJ0SNY7soEsUEjb6gQ==$\
+        WG5d8oPm3OtcPnkdi4Uo7BkeZkBFzpcXkuLmtbsT4qY=:wfPLwcE6nTWhTAmQ7tl2KeoiWGPlZqQxSrmfPwDl2dU=";
+
+    #[test]
+    fn scram_secret() {
+        let secret = ScramSecret::parse(SECRET).unwrap();
+        assert_eq!(4096, secret.iterations);
+        assert_eq!(base64::decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap(), secret.salt);
+        assert_eq!(32, secret.stored_key.len());
+        assert_eq!(32, secret.server_key.len());
+    }
+
+    #[test]
+    fn not_scram_secret() {
+        assert!(ScramSecret::parse("md5a3556571e93b0d20722ba62be61e8c2d").is_err());
+        assert!(ScramSecret::parse("SCRAM-SHA-256$4096:salt").is_err());
+        assert!(ScramSecret::parse("SCRAM-SHA-256$many:W22Z$WG5d:wfPL").is_err());
+    }
+
+    #[test]
+    fn mock() {
+        let secrets = Secrets::parse(&format!("alice md53175bce1d3201d16594cebf9d7eb3f9d\nerin {}", SECRET)).unwrap();
+        let salt = |user: &[u8]| match secrets.mock(user) {
+            Secret::Scram(secret) => {
+                assert_eq!(4096, secret.iterations);
+                secret.salt
+            },
+            secret => panic!("{:?} instead of a SCRAM secret", secret),
+        };
+        assert_eq!(16, salt(b"bob").len());
+        assert_eq!(salt(b"bob"), salt(b"bob"));
+        assert_ne!(salt(b"bob"), salt(b"carol"));
+        let secrets = Secrets::parse("alice md53175bce1d3201d16594cebf9d7eb3f9d").unwrap();
+        assert!(matches!(secrets.mock(b"bob"), Secret::Md5(hash) if hash.len() == 32));
+    }
+
+    #[test]
+    fn secret() {
+        assert_eq!(Secret::Scram(ScramSecret::parse(SECRET).unwrap()), Secret::parse(SECRET).unwrap());
+        assert_eq!(
+            Secret::Md5(Vec::from("3175bce1d3201d16594cebf9d7eb3f9d")),
+            Secret::parse("md53175BCE1D3201D16594CEBF9D7EB3F9D").unwrap(),
+        );
+        assert_eq!(Secret::Password(Vec::from("md5short")), Secret::parse("md5short").unwrap());
+        assert_eq!(Secret::Password(Vec::from("qwerty")), Secret::parse("qwerty").unwrap());
+        assert!(Secret::parse("SCRAM-SHA-256$broken").is_err());
+        assert_eq!("Password", format!("{:?}", Secret::parse("qwerty").unwrap()));
+    }
+
+    #[test]
+    fn file() {
+        let text = format!("\
+            # comment\n\
+            \n\
+            \"user\" \"{secret}\"\n\
+            \"with \"\"quotes\"\" and spaces\" {secret}\n\
+            bare {secret}\n\
+            plain qwerty\n\
+        ", secret = SECRET);
+        let secrets = Secrets::parse(&text).unwrap();
+        assert!(secrets.get(b"user").is_some());
+        assert!(secrets.get(b"with \"quotes\" and spaces").is_some());
+        assert!(secrets.get(b"bare").is_some());
+        assert_eq!(Some(&Secret::Password(Vec::from("qwerty"))), secrets.get(b"plain"));
+        assert!(secrets.get(b"other").is_none());
+        assert_eq!(r#"Secrets { users: ["bare", "plain", "user", "with \"quotes\" and spaces"] }"#, format!("{:?}", secrets));
+    }
+
+    #[test]
+    fn file_with_errors() {
+        assert_eq!(Err("line 2: quote is not closed".into()), Secrets::parse("\n\"user SCRAM").map(|_| ()));
+        assert_eq!(Err("line 1: user and secret expected".into()), Secrets::parse("user").map(|_| ()));
+        assert_eq!(
+            Err("line 1: unexpected \"extra\" after the secret".into()),
+            Secrets::parse(&format!("user {} extra", SECRET)).map(|_| ()),
+        );
+    }
+}
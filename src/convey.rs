@@ -1,31 +1,49 @@
+pub mod intercept;
 pub mod util;
 
-#[cfg(test)] mod tests;
+#[cfg(test)] pub(crate) mod tests;
 
+use crate::auth::{self, md5};
+use crate::auth::credentials::Credentials;
+use crate::auth::scram::{ClientCredential, ClientExchange, ScramResult, ServerExchange};
+use crate::auth::secrets::{ScramSecret, Secret, Secrets};
+use crate::convey::intercept::{ForwardAll, Intercepted, Interceptor};
+use crate::firewall::{Action, Rules};
 use crate::msg::body::*;
-use crate::msg::type_byte::TypeByte;
+use crate::msg::body::error_and_notice_responses::ErrorOrNoticeFields;
+use crate::msg::body::initial::{Startup, StartupParam, Version};
+use crate::msg::body::ready_for_query::Status;
+use crate::msg::parts::Text;
+use crate::msg::replication::{PgOutputWalSenderMsg, ReplicationMode, WalReceiverMsg, WalSenderMsg, pgoutput};
+use crate::msg::scram::{self, ClientFinal, ClientFirst, ServerFinal, ServerFirst};
+use crate::msg::type_byte::{BackendTypeByte, FrontendTypeByte, TypeByte};
 use crate::msg::util::async_io;
-use crate::msg::util::decode::{MsgDecode, Problem as DecodeProblem};
-use crate::msg::util::encode::{Problem as EncodeProblem};
+use crate::msg::util::decode::{DecodeResult, MsgDecode, Problem as DecodeProblem};
+use crate::msg::util::encode::{MsgEncode, Problem as EncodeProblem, encode_msg};
 use crate::msg::util::read::*;
-use crate::tls::interface::{TlsClient, TlsServer};
+use crate::pcapng::PcapngStream;
+use crate::record::{Direction, Recording};
+use crate::tls::interface::{TlsChannelBinding, TlsClient, TlsServer};
 
 use ::async_trait::async_trait;
 use ::core::hint::unreachable_unchecked;
 use ::futures::future::{self, Either, Future, FutureExt};
-use ::futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use ::futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use ::std::collections::VecDeque;
 use ::std::convert::TryFrom;
 use ::std::io::{Error as IoError, Result as IoResult};
+use ::std::sync::Arc;
 
 #[derive(Debug)]
 pub enum ConveyError {
+    AuthFailed(String),
     DecodeError(DecodeProblem),
     EncodeError(EncodeProblem),
     IoError(IoError),
     TlsError(TlsError),
     LeftUndecoded(usize),
     Todo(String),
-    UnexpectedType(State, Side, TypeByte),
+    UnexpectedType(State, Option<Pending>, TypeByte),
     UnknownType(Side, u8),
     Unsupported(&'static str),
 }
@@ -38,45 +56,157 @@ pub enum Side {
     Frontend,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Side {
+    fn opposite(self) -> Self {
+        match self {
+            Self::Backend => Self::Frontend,
+            Self::Frontend => Self::Backend,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum State {
     // From backend point of view ("AskedX" means "backend asked X", "GotX" means "backend got X from frontend").
-    AbortedExtendedQuery,
-    AbortedParsingOrBinding,
-    AbortedSimpleQuery,
-    AnsweringToExtendedQuery,
-    AnsweringToSimpleQuery,
     AskedCleartextPassword,
     AskedGssResponse,
     AskedMd5Password,
     AskedSaslInitialResponse,
     AskedSaslResponse,
     Authenticated,
-    Bound,
-    CompletedExtendedQuery,
-    CompletedSimpleCommand,
-    ExecutingExtendedQuery,
     FinishedSasl,
     GotAnySaslResponse,
-    GotBinding,
     GotCleartextPassword,
     GotGssResponse,
     GotMd5Password,
+    GotStartup,
+    ReadyForQuery,
+    SentAllBackendParams,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pending {
+    // A request sent by frontend which backend has not fully answered yet, named like State.
+    // Frontend may send many requests without waiting (pipelining), so they are queued.
+    AbortedExtendedQuery,  // backend skips everything till Sync
+    AbortedFunctionCall,
+    AbortedSimpleQuery,
+    AnsweringToExtendedQuery,
+    AnsweringToSimpleQuery,
+    AnsweredToFunctionCall,
+    CompletedSimpleCommand,
+    CopyingBothExtendedQuery,
+    CopyingBothReplication,
+    CopyingBothSimpleQuery,
+    CopyingInExtendedQuery,
+    CopyingInReplication,
+    CopyingInSimpleQuery,
+    CopyingOutExtendedQuery,
+    CopyingOutReplication,
+    CopyingOutSimpleQuery,
+    DeniedStatement,  // answered by postgread once backend answers the requests before it
+    DescribedParameters,
+    ExecutingExtendedQuery,
+    GotBinding,
+    GotClose,
+    GotDescribePortal,
+    GotDescribeStatement,
+    GotFunctionCall,
     GotPreparedStatement,
     GotSimpleQuery,
-    GotStartup,
     GotSync,
-    ReadyForQuery,
-    SeenEmptyExtendedQuery,
     SeenEmptySimpleQuery,
-    SentAllBackendParams,
-    SuspendedExtendedQuery,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConveyOptions {
+    // Pass unknown, unexpected and undecodable messages through as Opaque instead of stopping.
+    pub lenient: bool,
+    // Copy the rest of the connection as is instead of stopping when it can't be modeled.
+    pub relay_fallback: bool,
+    // Run SCRAM as server to frontend and as client to backend for users having SCRAM secrets here,
+    // so channel binding works on both legs despite TLS is terminated by postgread.
+    pub scram_secrets: Option<Arc<Secrets>>,
+    // Authenticate frontends with postgread's own challenges against these secrets like pgbouncer does
+    // with auth_file, and answer backend's challenge with the same secret. Takes precedence over scram_secrets.
+    pub local_auth: Option<Arc<Secrets>>,
+    // Answer backend's challenge with the secret mapped to the user and database from Startup,
+    // so frontend only sees AuthenticationOk or is authenticated locally. Takes precedence over local_auth secrets.
+    pub backend_credentials: Option<Arc<Credentials>>,
+    // Check each simple query and prepared statement against these rules and answer a denied one
+    // with ErrorResponse on behalf of backend which never gets it.
+    pub firewall: Option<Arc<Rules>>,
+    // Keep sessions read-only: backend is started with default_transaction_read_only=on, and statements
+    // denied by these rules, normally Rules::read_only(), are answered with ErrorResponse like the firewall does.
+    // FunctionCall is always denied as the function may write.
+    pub read_only: Option<Arc<Rules>>,
+    // Write every message read from either side to a capture file as is.
+    // A failing recording stops for the connection with CaptureEvent::RecordingStopped while conveying goes on.
+    pub recording: Option<Recording>,
+    // Write the plaintext written to either side, relayed bytes too, to a pcapng file as a TCP stream.
+    // A failing capture stops for the connection with CaptureEvent::PcapngStopped like recording does.
+    pub pcapng: Option<PcapngStream>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opaque {
+    pub bytes: Vec<u8>,  // type byte, length and body as they are conveyed
+    pub error: OpaqueError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpaqueError {
+    DecodeError(DecodeProblem),
+    LeftUndecoded(usize),
+    UnexpectedType(State, Option<Pending>, TypeByte),
+    UnknownType(u8),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Message<'a> {
     Backend(BackendMsg<'a>),
     Frontend(FrontendMsg<'a>),
+    FrontendIgnored(FrontendMsg<'a>),  // backend skips it till Sync after an error in extended query
+    Firewall(&'a FirewallEvent),
+    Relay(&'a RelayEvent),
+    Capture(&'a CaptureEvent),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FirewallEvent {
+    Allowed {
+        rule: String,
+        statement: String,
+    },
+    Denied {
+        rule: String,
+        statement: String,
+    },
+    DeniedInReadOnly {
+        rule: String,
+        statement: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaptureEvent {
+    RecordingStopped {
+        error: String,
+    },
+    PcapngStopped {
+        error: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RelayEvent {
+    Entered {
+        reason: String,
+    },
+    Finished {
+        backend_bytes: u64,  // copied from backend to frontend
+        frontend_bytes: u64,  // copied from frontend to backend
+    },
 }
 
 #[derive(Debug, PartialEq)]
@@ -84,32 +214,58 @@ pub enum BackendMsg<'a> {
     Authentication(&'a Authentication),
     BackendKeyData(&'a BackendKeyData),
     BindComplete(&'a BindComplete),
+    CloseComplete(&'a CloseComplete),
     CommandComplete(&'a CommandComplete),
+    CopyBothResponse(&'a CopyBothResponse),
+    CopyData(&'a CopyData),
+    CopyDone(&'a CopyDone),
+    CopyInResponse(&'a CopyInResponse),
+    CopyOutResponse(&'a CopyOutResponse),
     DataRow(&'a DataRow),
     EmptyQueryResponse(&'a EmptyQueryResponse),
     ErrorResponse(&'a ErrorResponse),
+    FunctionCallResponse(&'a FunctionCallResponse),
     NegotiateProtocolVersion(&'a NegotiateProtocolVersion),
+    NoData(&'a NoData),
     NoticeResponse(&'a NoticeResponse),
+    NotificationResponse(&'a NotificationResponse),
+    Opaque(&'a Opaque),
+    ParameterDescription(&'a ParameterDescription),
     ParameterStatus(&'a ParameterStatus),
     ParseComplete(&'a ParseComplete),
+    PgOutputWalSenderMsg(&'a PgOutputWalSenderMsg),
     PortalSuspended(&'a PortalSuspended),
     ReadyForQuery(&'a ReadyForQuery),
     RowDescription(&'a RowDescription),
+    ScramServerFinal(&'a ServerFinal),
+    ScramServerFirst(&'a ServerFirst),
+    WalSenderMsg(&'a WalSenderMsg),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum FrontendMsg<'a> {
     Bind(&'a Bind),
+    Close(&'a Close),
+    CopyData(&'a CopyData),
+    CopyDone(&'a CopyDone),
+    CopyFail(&'a CopyFail),
+    Describe(&'a Describe),
     Execute(&'a Execute),
+    Flush(&'a Flush),
+    FunctionCall(&'a FunctionCall),
     GssResponse(&'a GssResponse),
     Initial(&'a Initial),
+    Opaque(&'a Opaque),
     Parse(&'a Parse),
     Password(&'a Password),
     Query(&'a Query),
     SaslInitialResponse(&'a SaslInitialResponse),
     SaslResponse(&'a SaslResponse),
+    ScramClientFinal(&'a ClientFinal),
+    ScramClientFirst(&'a ClientFirst),
     Sync(&'a Sync),
     Terminate(&'a Terminate),
+    WalReceiverMsg(&'a WalReceiverMsg),
 }
 
 #[derive(Debug)]
@@ -123,6 +279,7 @@ pub async fn convey<FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Callba
     backend: BackPlain,
     frontend_tls_server: FrontTlsServer,
     backend_tls_client: BackTlsClient,
+    options: ConveyOptions,
     callback: Callback,
 ) -> ConveyResult<()>
 where
@@ -130,8 +287,39 @@ where
     BackPlain: AsyncRead + AsyncWrite + Send + Unpin,
     FrontTlsServer: TlsServer<FrontPlain> + Send,
     BackTlsClient: TlsClient<BackPlain> + Send,
-    FrontTlsServer::Tls: AsyncRead + AsyncWrite,
-    BackTlsClient::Tls: AsyncRead + AsyncWrite,
+    FrontTlsServer::Tls: AsyncRead + AsyncWrite + TlsChannelBinding,
+    BackTlsClient::Tls: AsyncRead + AsyncWrite + TlsChannelBinding,
+    Callback: Fn(Message) + Send,
+{
+    convey_intercepting(
+        frontend,
+        backend,
+        frontend_tls_server,
+        backend_tls_client,
+        options,
+        ForwardAll,
+        callback,
+    ).await
+}
+
+// Like convey but lets the interceptor forward, replace, drop or answer each message.
+pub async fn convey_intercepting<FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Intercept, Callback>(
+    frontend: FrontPlain,
+    backend: BackPlain,
+    frontend_tls_server: FrontTlsServer,
+    backend_tls_client: BackTlsClient,
+    options: ConveyOptions,
+    interceptor: Intercept,
+    callback: Callback,
+) -> ConveyResult<()>
+where
+    FrontPlain: AsyncRead + AsyncWrite + Send + Unpin,
+    BackPlain: AsyncRead + AsyncWrite + Send + Unpin,
+    FrontTlsServer: TlsServer<FrontPlain> + Send,
+    BackTlsClient: TlsClient<BackPlain> + Send,
+    FrontTlsServer::Tls: AsyncRead + AsyncWrite + TlsChannelBinding,
+    BackTlsClient::Tls: AsyncRead + AsyncWrite + TlsChannelBinding,
+    Intercept: Interceptor,
     Callback: Fn(Message) + Send,
 {
     Conveyor::new(
@@ -139,11 +327,13 @@ where
         backend,
         frontend_tls_server,
         backend_tls_client,
+        options,
+        interceptor,
         callback,
     ).go().await
 }
 
-struct Conveyor<FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Callback>
+struct Conveyor<FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Intercept, Callback>
 where
     FrontPlain: Send + Unpin,
     BackPlain: Send + Unpin,
@@ -154,41 +344,77 @@ where
     backend: StreamWrap<BackPlain, BackTlsClient::Tls>,
     frontend_tls_server: FrontTlsServer,
     backend_tls_client: BackTlsClient,
+    options: ConveyOptions,
+    interceptor: Intercept,
     callback: Callback,
+    undecoded: Option<Vec<u8>>,
+    pending: VecDeque<Pending>,
+    replication: Option<ReplicationMode>,
+    decoding_pgoutput: bool,
+    protocol_version: Version,
+    scram: Option<scram::Mechanism>,
+    user: Vec<u8>,
+    database: Vec<u8>,
+    transaction_status: Status,
+    denied_till_sync: bool,  // frontend messages are dropped till Sync after a denied Parse
+    denials: VecDeque<Denial>,  // answers to Pending::DeniedStatement in the same order
+}
+
+struct Denial {
+    error: Vec<u8>,
+    ready: bool,  // ReadyForQuery follows the error, it's encoded when sent to keep the transaction status actual
 }
 
 use ConveyError::*;
 
+// The message is conveyed unless the interceptor holds it back, then Ok($held) is returned at once.
 macro_rules! read_through {
     (
         <$msg_type:ident>,
         $self:ident,
         $read:ident,
         $callback:ident($cb_wrap:ident),
-        $write:ident
+        $intercept:ident,
+        $side:expr,
+        $held:expr
     ) => {{
         let (bytes, msg) = $self.$read::<$msg_type>().await?;
         $self.$callback($cb_wrap::$msg_type(&msg));
-        $self.$write(&bytes).await?;
-        msg
+        let intercepted = $self.$intercept($cb_wrap::$msg_type(&msg))?;
+        match $self.convey_intercepted($side, bytes, msg, intercepted).await? {
+            Some(msg) => msg,
+            None => return Ok($held),
+        }
     }};
 }
 
 macro_rules! read_backend_through {
     (
         <$msg_type:ident>,
-        $self:ident
+        $self:ident,
+        $held:expr
     ) => {
-        read_through!(<$msg_type>, $self, read_backend, callback_backend(BackendMsg), write_frontend)
+        read_through!(<$msg_type>, $self, read_backend, callback_backend(BackendMsg), intercept_backend, Side::Backend, $held)
     }
 }
 
 macro_rules! read_frontend_through {
     (
         <$msg_type:ident>,
-        $self:ident
+        $self:ident,
+        $held:expr
+    ) => {
+        read_through!(<$msg_type>, $self, read_frontend, callback_frontend(FrontendMsg), intercept_frontend, Side::Frontend, $held)
+    }
+}
+
+macro_rules! read_frontend_ignored {
+    (
+        <$msg_type:ident>,
+        $self:ident,
+        $held:expr
     ) => {
-        read_through!(<$msg_type>, $self, read_frontend, callback_frontend(FrontendMsg), write_backend)
+        read_through!(<$msg_type>, $self, read_frontend, callback_frontend_ignored(FrontendMsg), intercept_frontend, Side::Frontend, $held)
     }
 }
 
@@ -196,15 +422,16 @@ macro_rules! unwrap_stream {
     ($wrap:expr, $func:expr) => { unwrap_stream($wrap, $func, $func) }
 }
 
-impl<'a, FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Callback>
-Conveyor<FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Callback>
+impl<'a, FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Intercept, Callback>
+Conveyor<FrontPlain, BackPlain, FrontTlsServer, BackTlsClient, Intercept, Callback>
 where
     FrontPlain: ConveyReader + ConveyWriter,
     BackPlain: ConveyReader + ConveyWriter,
     FrontTlsServer: TlsServer<FrontPlain> + Send,
     BackTlsClient: TlsClient<BackPlain> + Send,
-    FrontTlsServer::Tls: ConveyReader + ConveyWriter,
-    BackTlsClient::Tls: ConveyReader + ConveyWriter,
+    FrontTlsServer::Tls: ConveyReader + ConveyWriter + TlsChannelBinding,
+    BackTlsClient::Tls: ConveyReader + ConveyWriter + TlsChannelBinding,
+    Intercept: Interceptor,
     Callback: FnMut(Message) + Send,
 {
     fn new(
@@ -212,6 +439,8 @@ where
         backend: BackPlain,
         frontend_tls_server: FrontTlsServer,
         backend_tls_client: BackTlsClient,
+        options: ConveyOptions,
+        interceptor: Intercept,
         callback: Callback,
     ) -> Self {
         Conveyor {
@@ -219,14 +448,27 @@ where
             backend: StreamWrap::Plain(backend),
             frontend_tls_server,
             backend_tls_client,
+            options,
+            interceptor,
             callback,
+            undecoded: None,
+            pending: VecDeque::new(),
+            replication: None,
+            decoding_pgoutput: false,
+            protocol_version: Version::V3_0,
+            scram: None,
+            user: vec![],
+            database: vec![],
+            transaction_status: Status::Idle,
+            denied_till_sync: false,
+            denials: VecDeque::new(),
         }
     }
-    #[allow(clippy::cognitive_complexity)]
     async fn go(&mut self) -> ConveyResult<()> {
-        let mut state = match read_frontend_through!(<Initial>, self) {
-            Initial::Startup(_) => State::GotStartup,
+        let mut state = match self.read_frontend_initial().await? {
+            Initial::Startup(startup) => self.start(&startup),
             Initial::Cancel(_) => return Ok(()),
+            Initial::GssEnc => return self.relay("GSSAPI encryption".into()).await,
             Initial::TLS => {
                 let tls_response = self.read_backend_type_byte().await?;
                 match tls_response {
@@ -236,17 +478,17 @@ where
                     },
                     ErrorResponse::TYPE_BYTE => {
                         // "This would only occur if the server predates the addition of SSL support to PostgreSQL"
-                        read_backend_through!(<ErrorResponse>, self);
+                        read_backend_through!(<ErrorResponse>, self, ());
                         return Ok(())
                     }
                     _ => {
                         return Err(UnknownType(Side::Backend, tls_response))
                     },
                 }
-                self.write_frontend(&[TLS_SUPPORTED]).await?;
+                self.answer_encryption_request(TLS_SUPPORTED).await?;
                 switch_server_to_tls(&mut self.frontend, &self.frontend_tls_server).await?;
-                match read_frontend_through!(<Initial>, self) {
-                    Initial::Startup(_) => State::GotStartup,
+                match read_frontend_through!(<Initial>, self, ()) {
+                    Initial::Startup(startup) => self.start(&startup),
                     Initial::Cancel(_) => return Ok(()),
                     _ => return Err(TlsError(TlsError::TlsRequestedInsideTls))
                 }
@@ -254,167 +496,545 @@ where
         };
         loop {
             let (side, type_byte) = self.read_type_byte_from_both().await?;
-            use Side::*;
-            use TypeByte as T;
-            if cfg!(test) {
-                eprintln!("conveyor got {:?} from {:?} on state={:?}", type_byte, side, state);
-            }
-            state = match (side, type_byte, state) {
-                (Backend, T::Authentication, _) => {
-                    self.process_backend_authentication(type_byte, state).await
+            let type_byte = match type_byte {
+                Ok(type_byte) => type_byte,
+                Err(byte) if self.options.lenient => {
+                    let bytes = self.read_raw(side, byte).await?;
+                    self.convey_opaque(side, Opaque { bytes, error: OpaqueError::UnknownType(byte) }).await?;
+                    continue
                 },
-                (Backend, T::BackendKeyData, State::Authenticated) => {
-                    read_backend_through!(<BackendKeyData>, self);
-                    Ok(State::SentAllBackendParams)
+                Err(byte) => return Err(UnknownType(side, byte)),
+            };
+            let result = self.convey_msg(side, type_byte, state).await;
+            match self.convey_undecoded_if_lenient(side, state, result).await {
+                Ok(Some(next_state)) => {
+                    self.answer_denied().await?;
+                    state = next_state
                 },
-                (Frontend, T::Bind, State::ReadyForQuery) => {
-                    read_frontend_through!(<Bind>, self);
-                    Ok(State::GotBinding)
+                Ok(None) => return Ok(()),
+                // the message causing these errors is already conveyed so relaying goes on right after it
+                Err(Unsupported(reason)) if self.options.relay_fallback => return self.relay(reason.into()).await,
+                Err(Todo(reason)) if self.options.relay_fallback => return self.relay(reason).await,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    // Returns the next state or None if conveying is finished.
+    #[allow(clippy::cognitive_complexity)]
+    async fn convey_msg(&mut self, side: Side, type_byte: TypeByte, state: State) -> ConveyResult<Option<State>> {
+        use TypeByte::*;
+        use BackendTypeByte as B;
+        use FrontendTypeByte as F;
+        use Pending as P;
+        let expected = self.pending.front().copied();
+        let skipping = self.is_skipping_until_sync();
+        let replicating = self.replication.is_some();
+        let decoding_pgoutput = self.decoding_pgoutput;
+        if cfg!(test) {
+            eprintln!("conveyor got {:?} from {:?} on state={:?} pending={:?}", type_byte, side, state, self.pending);
+        }
+        match (type_byte, state, expected) {
+            (Frontend(frontend_type_byte), State::ReadyForQuery, _)
+            if skipping && !matches!(frontend_type_byte, F::Sync | F::Terminate) => {
+                self.convey_ignored_frontend(frontend_type_byte).await?;
+                Ok(state)
+            },
+            (Backend(B::Authentication), _, _) => {
+                return self.process_backend_authentication(type_byte, state).await
+            },
+            (Backend(B::BackendKeyData), State::Authenticated, _) => {
+                // the key is checked before it's conveyed since frontend can't cancel with a key not fitting its protocol
+                let (bytes, key_data) = self.read_backend::<BackendKeyData>().await?;
+                if !self.protocol_version.secret_key_fits(&key_data.secret_key) {
+                    let problem = DecodeProblem::Incorrect(format!(
+                        "secret key of {} bytes in protocol {}.{}",
+                        key_data.secret_key.len(), self.protocol_version.major, self.protocol_version.minor,
+                    ));
+                    if !self.options.lenient {
+                        return Err(DecodeError(problem))
+                    }
+                    self.convey_opaque(side, Opaque { bytes, error: OpaqueError::DecodeError(problem) }).await?;
+                    return Ok(Some(State::SentAllBackendParams))
                 }
-                (Backend, T::BindComplete, State::GotBinding) => {
-                    read_backend_through!(<BindComplete>, self);
-                    Ok(State::ReadyForQuery)
+                self.callback_backend(BackendMsg::BackendKeyData(&key_data));
+                let intercepted = self.intercept_backend(BackendMsg::BackendKeyData(&key_data))?;
+                if self.convey_intercepted(side, bytes, key_data, intercepted).await?.is_none() {
+                    return Ok(Some(state))
                 }
-                (Backend, T::CommandComplete, State::AnsweringToSimpleQuery) |
-                (Backend, T::CommandComplete, State::CompletedSimpleCommand) |
-                (Backend, T::CommandComplete, State::GotSimpleQuery) => {
-                    read_backend_through!(<CommandComplete>, self);
-                    Ok(State::CompletedSimpleCommand)
-                },
-                (Backend, T::CommandComplete, State::AnsweringToExtendedQuery) |
-                (Backend, T::CommandComplete, State::ExecutingExtendedQuery) => {
-                    read_backend_through!(<CommandComplete>, self);
-                    Ok(State::CompletedExtendedQuery)
-                },
-                (Backend, T::DataRow, State::AnsweringToSimpleQuery) => {
-                    read_backend_through!(<DataRow>, self);
-                    Ok(State::AnsweringToSimpleQuery)
-                },
-                (Backend, T::DataRow, State::AnsweringToExtendedQuery) |
-                (Backend, T::DataRow, State::ExecutingExtendedQuery) => {
-                    read_backend_through!(<DataRow>, self);
-                    Ok(State::AnsweringToExtendedQuery)
-                },
-                (Backend, T::EmptyQueryResponse, State::ExecutingExtendedQuery) => {
-                    read_backend_through!(<EmptyQueryResponse>, self);
-                    Ok(State::SeenEmptyExtendedQuery)
-                },
-                (Backend, T::EmptyQueryResponse, State::GotSimpleQuery) => {
-                    read_backend_through!(<EmptyQueryResponse>, self);
-                    Ok(State::SeenEmptySimpleQuery)
-                },
-                (Backend, T::Execute_or_ErrorResponse, State::AbortedSimpleQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::AnsweringToSimpleQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::CompletedSimpleCommand) |
-                (Backend, T::Execute_or_ErrorResponse, State::GotSimpleQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::SeenEmptySimpleQuery) => {
-                    read_backend_through!(<ErrorResponse>, self);
-                    Ok(State::AbortedSimpleQuery)
-                },
-                (Backend, T::Execute_or_ErrorResponse, State::GotBinding) |
-                (Backend, T::Execute_or_ErrorResponse, State::GotPreparedStatement) => {
-                    read_backend_through!(<ErrorResponse>, self);
-                    Ok(State::AbortedParsingOrBinding)
-                },
-                (Backend, T::Execute_or_ErrorResponse, State::AbortedExtendedQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::AnsweringToExtendedQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::CompletedExtendedQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::ExecutingExtendedQuery) |
-                (Backend, T::Execute_or_ErrorResponse, State::SeenEmptyExtendedQuery) => {
-                    read_backend_through!(<ErrorResponse>, self);
-                    Ok(State::AbortedExtendedQuery)
-                },
-                (Backend, T::Execute_or_ErrorResponse, _) => {
-                    read_backend_through!(<ErrorResponse>, self);
-                    return Ok(())
-                },
-                (Frontend, T::Execute_or_ErrorResponse, State::ReadyForQuery) |
-                (Frontend, T::Execute_or_ErrorResponse, State::SuspendedExtendedQuery) => {
-                    read_frontend_through!(<Execute>, self);
-                    Ok(State::ExecutingExtendedQuery)
-                },
-                (Frontend, T::GssResponse_or_Password_or_SaslResponses, State::AskedCleartextPassword) => {
-                    read_frontend_through!(<Password>, self);
-                    Ok(State::GotCleartextPassword)
-                },
-                (Frontend, T::GssResponse_or_Password_or_SaslResponses, State::AskedGssResponse) => {
-                    read_frontend_through!(<GssResponse>, self);
-                    Ok(State::GotGssResponse)
-                },
-                (Frontend, T::GssResponse_or_Password_or_SaslResponses, State::AskedMd5Password) => {
-                    read_frontend_through!(<Password>, self);
-                    Ok(State::GotMd5Password)
-                },
-                (Frontend, T::GssResponse_or_Password_or_SaslResponses, State::AskedSaslInitialResponse) => {
-                    read_frontend_through!(<SaslInitialResponse>, self);
-                    Ok(State::GotAnySaslResponse)
-                },
-                (Frontend, T::GssResponse_or_Password_or_SaslResponses, State::AskedSaslResponse) => {
-                    read_frontend_through!(<SaslResponse>, self);
-                    Ok(State::GotAnySaslResponse)
-                },
-                (Backend, T::NegotiateProtocolVersion, State::GotStartup) |
-                (Backend, T::NegotiateProtocolVersion, State::Authenticated) => {
-                    read_backend_through!(<NegotiateProtocolVersion>, self);
-                    Ok(state)
-                },
-                (Backend, T::NoticeResponse, _) => {
-                    read_backend_through!(<NoticeResponse>, self);
-                    Ok(state)
-                },
-                (Backend, T::ParameterStatus_or_Sync, State::Authenticated) => {
-                    read_backend_through!(<ParameterStatus>, self);
-                    Ok(State::Authenticated)
-                },
-                (Frontend, T::ParameterStatus_or_Sync, State::AbortedExtendedQuery) |
-                (Frontend, T::ParameterStatus_or_Sync, State::CompletedExtendedQuery) |
-                (Frontend, T::ParameterStatus_or_Sync, State::SeenEmptyExtendedQuery) |
-                (Frontend, T::ParameterStatus_or_Sync, State::SuspendedExtendedQuery) => {
-                    read_frontend_through!(<Sync>, self);
-                    Ok(State::GotSync)
-                },
-                (Frontend, T::Parse, State::ReadyForQuery) => {
-                    read_frontend_through!(<Parse>, self);
-                    Ok(State::GotPreparedStatement)
-                },
-                (Backend, T::ParseComplete, State::GotPreparedStatement) => {
-                    read_backend_through!(<ParseComplete>, self);
-                    Ok(State::ReadyForQuery)
-                },
-                (Backend, T::PortalSuspended, State::AnsweringToExtendedQuery) => {
-                    read_backend_through!(<PortalSuspended>, self);
-                    Ok(State::SuspendedExtendedQuery)
-                },
-                (Frontend, T::Query, State::ReadyForQuery) => {
-                    read_frontend_through!(<Query>, self);
-                    Ok(State::GotSimpleQuery)
-                },
-                (Backend, T::ReadyForQuery, State::AbortedParsingOrBinding) |
-                (Backend, T::ReadyForQuery, State::AbortedSimpleQuery) |
-                (Backend, T::ReadyForQuery, State::CompletedSimpleCommand) |
-                (Backend, T::ReadyForQuery, State::SentAllBackendParams) |
-                (Backend, T::ReadyForQuery, State::GotSync) |
-                (Backend, T::ReadyForQuery, State::SeenEmptySimpleQuery) => {
-                    read_backend_through!(<ReadyForQuery>, self);
-                    Ok(State::ReadyForQuery)
-                },
-                (Backend, T::RowDescription, State::GotSimpleQuery) |
-                (Backend, T::RowDescription, State::CompletedSimpleCommand) => {
-                    read_backend_through!(<RowDescription>, self);
-                    Ok(State::AnsweringToSimpleQuery)
+                Ok(State::SentAllBackendParams)
+            },
+            (Frontend(F::Bind), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Bind>, self, Some(state));
+                self.expect(P::GotBinding)
+            }
+            (Backend(B::BindComplete), State::ReadyForQuery, Some(P::GotBinding)) => {
+                read_backend_through!(<BindComplete>, self, Some(state));
+                self.complete_expected()
+            }
+            (Frontend(F::Close), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Close>, self, Some(state));
+                self.expect(P::GotClose)
+            },
+            (Backend(B::CloseComplete), State::ReadyForQuery, Some(P::GotClose)) => {
+                read_backend_through!(<CloseComplete>, self, Some(state));
+                self.complete_expected()
+            },
+            (Backend(B::CommandComplete), State::ReadyForQuery, Some(P::AnsweringToSimpleQuery)) |
+            (Backend(B::CommandComplete), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::CommandComplete), State::ReadyForQuery, Some(P::GotSimpleQuery)) => {
+                read_backend_through!(<CommandComplete>, self, Some(state));
+                self.replace_expected(P::CompletedSimpleCommand)
+            },
+            (Backend(B::CommandComplete), State::ReadyForQuery, Some(P::AnsweringToExtendedQuery)) |
+            (Backend(B::CommandComplete), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) => {
+                read_backend_through!(<CommandComplete>, self, Some(state));
+                self.complete_expected()
+            },
+            (Backend(B::CopyBothResponse), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::CopyBothResponse), State::ReadyForQuery, Some(P::GotSimpleQuery)) if replicating => {
+                read_backend_through!(<CopyBothResponse>, self, Some(state));
+                self.replace_expected(P::CopyingBothReplication)
+            },
+            (Backend(B::CopyBothResponse), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::CopyBothResponse), State::ReadyForQuery, Some(P::GotSimpleQuery)) => {
+                read_backend_through!(<CopyBothResponse>, self, Some(state));
+                self.replace_expected(P::CopyingBothSimpleQuery)
+            },
+            (Backend(B::CopyBothResponse), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) => {
+                read_backend_through!(<CopyBothResponse>, self, Some(state));
+                self.start_copying_in_extended_query(P::CopyingBothExtendedQuery)
+            },
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingBothReplication)) |
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingOutReplication)) if decoding_pgoutput => {
+                read_backend_through!(<PgOutputWalSenderMsg>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingBothReplication)) |
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingOutReplication)) => {
+                read_backend_through!(<WalSenderMsg>, self, Some(state));
+                Ok(state)
+            },
+            (Frontend(F::CopyData), State::ReadyForQuery, Some(P::CopyingBothReplication)) |
+            (Frontend(F::CopyData), State::ReadyForQuery, Some(P::CopyingInReplication)) => {
+                read_frontend_through!(<WalReceiverMsg>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) |
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) |
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingOutExtendedQuery)) |
+            (Backend(B::CopyData), State::ReadyForQuery, Some(P::CopyingOutSimpleQuery)) => {
+                read_backend_through!(<CopyData>, self, Some(state));
+                Ok(state)
+            },
+            (Frontend(F::CopyData), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) |
+            (Frontend(F::CopyData), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) |
+            (Frontend(F::CopyData), State::ReadyForQuery, Some(P::CopyingInExtendedQuery)) |
+            (Frontend(F::CopyData), State::ReadyForQuery, Some(P::CopyingInSimpleQuery)) => {
+                read_frontend_through!(<CopyData>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::CopyDone), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) => {
+                read_backend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::CopyingInExtendedQuery)
+            },
+            (Backend(B::CopyDone), State::ReadyForQuery, Some(P::CopyingBothReplication)) => {
+                read_backend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::CopyingInReplication)
+            },
+            (Backend(B::CopyDone), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) => {
+                read_backend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::CopyingInSimpleQuery)
+            },
+            (Backend(B::CopyDone), State::ReadyForQuery, Some(P::CopyingOutExtendedQuery)) => {
+                read_backend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::ExecutingExtendedQuery)
+            },
+            (Backend(B::CopyDone), State::ReadyForQuery, Some(P::CopyingOutReplication)) |
+            (Backend(B::CopyDone), State::ReadyForQuery, Some(P::CopyingOutSimpleQuery)) => {
+                read_backend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::GotSimpleQuery)
+            },
+            (Frontend(F::CopyDone), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) => {
+                read_frontend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::CopyingOutExtendedQuery)
+            },
+            (Frontend(F::CopyDone), State::ReadyForQuery, Some(P::CopyingBothReplication)) => {
+                read_frontend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::CopyingOutReplication)
+            },
+            (Frontend(F::CopyDone), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) => {
+                read_frontend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::CopyingOutSimpleQuery)
+            },
+            (Frontend(F::CopyDone), State::ReadyForQuery, Some(P::CopyingInExtendedQuery)) => {
+                read_frontend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::ExecutingExtendedQuery)
+            },
+            (Frontend(F::CopyDone), State::ReadyForQuery, Some(P::CopyingInReplication)) |
+            (Frontend(F::CopyDone), State::ReadyForQuery, Some(P::CopyingInSimpleQuery)) => {
+                read_frontend_through!(<CopyDone>, self, Some(state));
+                self.replace_expected(P::GotSimpleQuery)
+            },
+            (Frontend(F::CopyFail), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) |
+            (Frontend(F::CopyFail), State::ReadyForQuery, Some(P::CopyingInExtendedQuery)) => {
+                read_frontend_through!(<CopyFail>, self, Some(state));
+                self.replace_expected(P::ExecutingExtendedQuery)
+            },
+            (Frontend(F::CopyFail), State::ReadyForQuery, Some(P::CopyingBothReplication)) |
+            (Frontend(F::CopyFail), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) |
+            (Frontend(F::CopyFail), State::ReadyForQuery, Some(P::CopyingInReplication)) |
+            (Frontend(F::CopyFail), State::ReadyForQuery, Some(P::CopyingInSimpleQuery)) => {
+                read_frontend_through!(<CopyFail>, self, Some(state));
+                self.replace_expected(P::GotSimpleQuery)
+            },
+            (Backend(B::CopyInResponse), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::CopyInResponse), State::ReadyForQuery, Some(P::GotSimpleQuery)) => {
+                read_backend_through!(<CopyInResponse>, self, Some(state));
+                self.replace_expected(P::CopyingInSimpleQuery)
+            },
+            (Backend(B::CopyInResponse), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) => {
+                read_backend_through!(<CopyInResponse>, self, Some(state));
+                self.start_copying_in_extended_query(P::CopyingInExtendedQuery)
+            },
+            (Backend(B::CopyOutResponse), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::CopyOutResponse), State::ReadyForQuery, Some(P::GotSimpleQuery)) => {
+                read_backend_through!(<CopyOutResponse>, self, Some(state));
+                self.replace_expected(P::CopyingOutSimpleQuery)
+            },
+            (Backend(B::CopyOutResponse), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) => {
+                read_backend_through!(<CopyOutResponse>, self, Some(state));
+                self.replace_expected(P::CopyingOutExtendedQuery)
+            },
+            (Frontend(F::Flush), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Flush>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::DataRow), State::ReadyForQuery, Some(P::AnsweringToSimpleQuery)) => {
+                read_backend_through!(<DataRow>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::DataRow), State::ReadyForQuery, Some(P::AnsweringToExtendedQuery)) |
+            (Backend(B::DataRow), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) => {
+                read_backend_through!(<DataRow>, self, Some(state));
+                self.replace_expected(P::AnsweringToExtendedQuery)
+            },
+            (Frontend(F::Describe), State::ReadyForQuery, _) => {
+                match read_frontend_through!(<Describe>, self, Some(state)) {
+                    Describe::Portal(_) => self.expect(P::GotDescribePortal),
+                    Describe::PreparedStatement(_) => self.expect(P::GotDescribeStatement),
+                }
+            },
+            (Backend(B::EmptyQueryResponse), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) => {
+                read_backend_through!(<EmptyQueryResponse>, self, Some(state));
+                self.complete_expected()
+            },
+            (Backend(B::EmptyQueryResponse), State::ReadyForQuery, Some(P::GotSimpleQuery)) => {
+                read_backend_through!(<EmptyQueryResponse>, self, Some(state));
+                self.replace_expected(P::SeenEmptySimpleQuery)
+            },
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::AbortedSimpleQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::AnsweringToSimpleQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingBothReplication)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingInReplication)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingInSimpleQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingOutReplication)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingOutSimpleQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotSimpleQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::SeenEmptySimpleQuery)) => {
+                read_backend_through!(<ErrorResponse>, self, Some(state));
+                self.replace_expected(P::AbortedSimpleQuery)
+            },
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::AnsweringToExtendedQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingInExtendedQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::CopyingOutExtendedQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::DescribedParameters)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::ExecutingExtendedQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotBinding)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotClose)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotDescribePortal)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotDescribeStatement)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotPreparedStatement)) => {
+                read_backend_through!(<ErrorResponse>, self, Some(state));
+                self.abort_extended_query()
+            },
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotFunctionCall)) => {
+                read_backend_through!(<ErrorResponse>, self, Some(state));
+                self.replace_expected(P::AbortedFunctionCall)
+            },
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::AbortedExtendedQuery)) |
+            (Backend(B::ErrorResponse), State::ReadyForQuery, Some(P::GotSync)) => {
+                read_backend_through!(<ErrorResponse>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::ErrorResponse), _, None) => {
+                read_backend_through!(<ErrorResponse>, self, Some(state));
+                return Ok(None)
+            },
+            (Frontend(F::Execute), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Execute>, self, Some(state));
+                self.expect(P::ExecutingExtendedQuery)
+            },
+            (Frontend(F::FunctionCall), State::ReadyForQuery, _) => {
+                read_frontend_through!(<FunctionCall>, self, Some(state));
+                self.expect(P::GotFunctionCall)
+            },
+            (Backend(B::FunctionCallResponse), State::ReadyForQuery, Some(P::GotFunctionCall)) => {
+                read_backend_through!(<FunctionCallResponse>, self, Some(state));
+                self.replace_expected(P::AnsweredToFunctionCall)
+            },
+            (Frontend(F::GssResponse_or_Password_or_SaslResponses), State::AskedCleartextPassword, _) => {
+                read_frontend_through!(<Password>, self, Some(state));
+                Ok(State::GotCleartextPassword)
+            },
+            (Frontend(F::GssResponse_or_Password_or_SaslResponses), State::AskedGssResponse, _) => {
+                read_frontend_through!(<GssResponse>, self, Some(state));
+                Ok(State::GotGssResponse)
+            },
+            (Frontend(F::GssResponse_or_Password_or_SaslResponses), State::AskedMd5Password, _) => {
+                read_frontend_through!(<Password>, self, Some(state));
+                Ok(State::GotMd5Password)
+            },
+            (Frontend(F::GssResponse_or_Password_or_SaslResponses), State::AskedSaslInitialResponse, _) => {
+                let (bytes, initial_response) = self.read_frontend::<SaslInitialResponse>().await?;
+                self.callback_sasl_initial_response(&bytes, &initial_response)?;
+                self.write_backend(&bytes).await?;
+                Ok(State::GotAnySaslResponse)
+            },
+            (Frontend(F::GssResponse_or_Password_or_SaslResponses), State::AskedSaslResponse, _) => {
+                let (bytes, response) = self.read_frontend::<SaslResponse>().await?;
+                self.callback_sasl_response(&bytes, &response)?;
+                self.write_backend(&bytes).await?;
+                Ok(State::GotAnySaslResponse)
+            },
+            (Backend(B::NegotiateProtocolVersion), State::GotStartup, _) |
+            (Backend(B::NegotiateProtocolVersion), State::Authenticated, _) => {
+                let negotiation = read_backend_through!(<NegotiateProtocolVersion>, self, Some(state));
+                self.negotiate(&negotiation);
+                Ok(state)
+            },
+            (Backend(B::NoData), State::ReadyForQuery, Some(P::DescribedParameters)) |
+            (Backend(B::NoData), State::ReadyForQuery, Some(P::GotDescribePortal)) => {
+                read_backend_through!(<NoData>, self, Some(state));
+                self.complete_expected()
+            },
+            (Backend(B::NoticeResponse), _, _) => {
+                read_backend_through!(<NoticeResponse>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::NotificationResponse), State::Authenticated, _) |
+            (Backend(B::NotificationResponse), State::ReadyForQuery, _) |
+            (Backend(B::NotificationResponse), State::SentAllBackendParams, _) => {
+                read_backend_through!(<NotificationResponse>, self, Some(state));
+                Ok(state)
+            },
+            (Backend(B::ParameterDescription), State::ReadyForQuery, Some(P::GotDescribeStatement)) => {
+                read_backend_through!(<ParameterDescription>, self, Some(state));
+                self.replace_expected(P::DescribedParameters)
+            },
+            (Backend(B::ParameterStatus), State::Authenticated, _) |
+            (Backend(B::ParameterStatus), State::ReadyForQuery, _) |
+            (Backend(B::ParameterStatus), State::SentAllBackendParams, _) => {
+                read_backend_through!(<ParameterStatus>, self, Some(state));
+                Ok(state)
+            },
+            (Frontend(F::Sync), State::ReadyForQuery, Some(P::CopyingBothExtendedQuery)) |
+            (Frontend(F::Sync), State::ReadyForQuery, Some(P::CopyingBothSimpleQuery)) |
+            (Frontend(F::Sync), State::ReadyForQuery, Some(P::CopyingInExtendedQuery)) |
+            (Frontend(F::Sync), State::ReadyForQuery, Some(P::CopyingInSimpleQuery)) => {
+                // backend ignores Sync while copying in
+                read_frontend_through!(<Sync>, self, Some(state));
+                Ok(state)
+            },
+            (Frontend(F::Sync), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Sync>, self, Some(state));
+                if skipping {
+                    self.pending.pop_back();
+                }
+                self.expect(P::GotSync)
+            },
+            (Frontend(F::Parse), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Parse>, self, Some(state));
+                self.expect(P::GotPreparedStatement)
+            },
+            (Backend(B::ParseComplete), State::ReadyForQuery, Some(P::GotPreparedStatement)) => {
+                read_backend_through!(<ParseComplete>, self, Some(state));
+                self.complete_expected()
+            },
+            (Backend(B::PortalSuspended), State::ReadyForQuery, Some(P::AnsweringToExtendedQuery)) => {
+                read_backend_through!(<PortalSuspended>, self, Some(state));
+                self.complete_expected()
+            },
+            (Frontend(F::Query), State::ReadyForQuery, _) => {
+                let query = read_frontend_through!(<Query>, self, Some(state));
+                self.decoding_pgoutput = self.replication == Some(ReplicationMode::Logical)
+                    && pgoutput::is_started_by(&query);
+                self.expect(P::GotSimpleQuery)
+            },
+            (Backend(B::ReadyForQuery), State::SentAllBackendParams, None) => {
+                let ready = read_backend_through!(<ReadyForQuery>, self, Some(state));
+                self.transaction_status = ready.status;
+                Ok(State::ReadyForQuery)
+            },
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::AbortedExtendedQuery)) |
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::AbortedFunctionCall)) |
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::AnsweredToFunctionCall)) |
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::AbortedSimpleQuery)) |
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) |
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::GotSync)) |
+            (Backend(B::ReadyForQuery), State::ReadyForQuery, Some(P::SeenEmptySimpleQuery)) => {
+                let ready = read_backend_through!(<ReadyForQuery>, self, Some(state));
+                self.transaction_status = ready.status;
+                self.complete_expected()
+            },
+            (Backend(B::RowDescription), State::ReadyForQuery, Some(P::DescribedParameters)) |
+            (Backend(B::RowDescription), State::ReadyForQuery, Some(P::GotDescribePortal)) => {
+                read_backend_through!(<RowDescription>, self, Some(state));
+                self.complete_expected()
+            },
+            (Backend(B::RowDescription), State::ReadyForQuery, Some(P::GotSimpleQuery)) |
+            (Backend(B::RowDescription), State::ReadyForQuery, Some(P::CompletedSimpleCommand)) => {
+                read_backend_through!(<RowDescription>, self, Some(state));
+                self.replace_expected(P::AnsweringToSimpleQuery)
+            },
+            (Frontend(F::Terminate), State::ReadyForQuery, _) => {
+                read_frontend_through!(<Terminate>, self, Some(state));
+                return Ok(None)
+            },
+            _ if self.options.lenient => {
+                let bytes = self.read_raw(side, type_byte.into()).await?;
+                let error = OpaqueError::UnexpectedType(state, expected, type_byte);
+                self.convey_opaque(side, Opaque { bytes, error }).await?;
+                Ok(state)
+            },
+            _ => Err(UnexpectedType(state, expected, type_byte)),
+        }.map(Some)
+    }
+
+    async fn relay(&mut self, reason: String) -> ConveyResult<()> {
+        (self.callback)(Message::Relay(&RelayEvent::Entered { reason }));
+        let mut backend_bytes = 0;
+        let mut frontend_bytes = 0;
+        loop {
+            let (side, bytes) = self.read_some_from_both().await?;
+            if bytes.is_empty() {
+                break
+            }
+            match side {
+                Side::Backend => {
+                    self.write_frontend(&bytes).await?;
+                    backend_bytes += bytes.len() as u64;
                 },
-                (Frontend, T::Terminate, State::ReadyForQuery) => {
-                    read_frontend_through!(<Terminate>, self);
-                    return Ok(())
+                Side::Frontend => {
+                    self.write_backend(&bytes).await?;
+                    frontend_bytes += bytes.len() as u64;
                 },
-                _ => Err(UnexpectedType(state, side, type_byte)),
-            }?
+            }
+        }
+        (self.callback)(Message::Relay(&RelayEvent::Finished { backend_bytes, frontend_bytes }));
+        Ok(())
+    }
+
+    async fn convey_undecoded_if_lenient(
+        &mut self,
+        side: Side,
+        state: State,
+        result: ConveyResult<Option<State>>,
+    ) -> ConveyResult<Option<State>> {
+        // Only a message failed to be decoded is left in self.undecoded, so it's not conveyed yet.
+        match (result, self.undecoded.take()) {
+            (Err(DecodeError(problem)), Some(bytes)) if self.options.lenient => {
+                self.convey_opaque(side, Opaque { bytes, error: OpaqueError::DecodeError(problem) }).await?;
+                Ok(Some(state))
+            },
+            (Err(LeftUndecoded(left)), Some(bytes)) if self.options.lenient => {
+                self.convey_opaque(side, Opaque { bytes, error: OpaqueError::LeftUndecoded(left) }).await?;
+                Ok(Some(state))
+            },
+            (result, _) => result,
         }
     }
 
-    async fn process_backend_authentication(&mut self, type_byte: TypeByte, state: State) -> ConveyResult<State> {
+    async fn convey_opaque(&mut self, side: Side, opaque: Opaque) -> ConveyResult<()> {
+        match side {
+            Side::Backend => {
+                self.callback_backend(BackendMsg::Opaque(&opaque));
+                self.write_frontend(&opaque.bytes).await
+            },
+            Side::Frontend => {
+                self.callback_frontend(FrontendMsg::Opaque(&opaque));
+                self.write_backend(&opaque.bytes).await
+            },
+        }
+    }
+
+    async fn read_frontend_initial(&mut self) -> ConveyResult<Initial> {
+        loop {
+            let (bytes, initial) = self.read_frontend::<Initial>().await?;
+            self.callback_frontend(FrontendMsg::Initial(&initial));
+            if matches!(initial, Initial::GssEnc) && !self.options.relay_fallback {
+                // postgread can't look into GSSAPI-encrypted traffic, so it declines the encryption
+                // without asking backend, and frontend goes on with TLS or plain startup.
+                // With relay fallback the request goes to backend and the rest is copied as is.
+                self.answer_encryption_request(GSS_ENC_NOT_SUPPORTED).await?;
+            } else {
+                let intercepted = self.intercept_frontend(FrontendMsg::Initial(&initial))?;
+                return self.convey_intercepted(Side::Frontend, bytes, initial, intercepted).await?
+                    .ok_or(Unsupported("holding back the initial message"))
+            }
+        }
+    }
+
+    fn start(&mut self, startup: &Startup) -> State {
+        self.replication = ReplicationMode::of(startup);
+        let param = |name: &[u8]| startup.params.iter().find(|param| param.name == name).map(|param| param.value.clone());
+        self.user = param(b"user").unwrap_or_default();
+        self.database = param(b"database").unwrap_or_else(|| self.user.clone());
+        self.protocol_version = startup.version;
+        State::GotStartup
+    }
+
+    fn negotiate(&mut self, negotiation: &NegotiateProtocolVersion) {
+        // backend answers so only if it doesn't support the requested minor version
+        let newest_minor = u16::try_from(negotiation.newest_backend_minor).unwrap_or(u16::MAX);
+        self.protocol_version.minor = self.protocol_version.minor.min(newest_minor);
+    }
+
+    async fn process_backend_authentication(&mut self, type_byte: TypeByte, state: State) -> ConveyResult<Option<State>> {
+        use Authentication as Auth;
+        let (bytes, authentication) = self.read_backend::<Authentication>().await?;
+        let next_state = Self::next_authentication_state(&authentication, state, type_byte);
+        if let (Err(UnexpectedType(..)), true) = (&next_state, self.options.lenient) {
+            let error = OpaqueError::UnexpectedType(state, None, type_byte);
+            self.convey_opaque(Side::Backend, Opaque { bytes, error }).await?;
+            return Ok(Some(state))
+        }
+        self.callback_backend_authentication(&bytes, &authentication)?;
+        if state == State::GotStartup {
+            let backend_secret = self.options.backend_credentials.as_ref()
+                .and_then(|credentials| credentials.get(&self.user, &self.database))
+                .cloned();
+            if let Some(secrets) = self.options.local_auth.clone() {
+                return self.authenticate_locally(&secrets, backend_secret, bytes, authentication).await
+            }
+            if let Some(backend_secret) = backend_secret {
+                return self.answer_backend(bytes, authentication, &backend_secret, None).await
+            }
+        }
+        if let (Auth::Sasl { auth_mechanisms }, State::GotStartup) = (&authentication, &state) {
+            if let Some((secret, client)) = self.scram_termination(auth_mechanisms) {
+                return self.terminate_scram(secret, client).await
+            }
+        }
+        self.write_frontend(&bytes).await?;
+        next_state.map(Some)
+    }
+
+    fn next_authentication_state(authentication: &Authentication, state: State, type_byte: TypeByte) -> ConveyResult<State> {
         use Authentication as Auth;
-        let authentication = read_backend_through!(<Authentication>, self);
         match (authentication, &state) {
             (Auth::CleartextPassword, State::GotStartup) =>
                 Ok(State::AskedCleartextPassword),
@@ -450,8 +1070,472 @@ where
             (_, State::GotStartup) =>
                 Err(Todo("Authentication::* is not fully implemented yet".into())),
             _ =>
-                Err(UnexpectedType(state, Side::Backend, type_byte)),
+                Err(UnexpectedType(state, None, type_byte)),
+        }
+    }
+
+    fn scram_termination(&self, backend_mechanisms: &[Vec<u8>]) -> Option<(ScramSecret, ClientExchange)> {
+        let secret = match self.options.scram_secrets.as_ref()?.get(&self.user)? {
+            Secret::Scram(secret) => secret,
+            Secret::Password(_) | Secret::Md5(_) => return None,
+        };
+        let client = ClientExchange::new(backend_mechanisms, self.backend_end_point())?;
+        Some((secret.clone(), client))
+    }
+
+    // Backend has asked for SASL and postgread answers for frontend after authenticating it itself.
+    async fn terminate_scram(&mut self, secret: ScramSecret, client: ClientExchange) -> ConveyResult<Option<State>> {
+        let (client_key, server_final) = self.authenticate_frontend_with_scram(secret.clone()).await?;
+        if !self.authenticate_backend_with_scram(client, ClientCredential::ClientKey(secret, client_key)).await? {
+            return Ok(None)
+        }
+        // backend goes on with AuthenticationOk which is conveyed as usual
+        self.write_frontend(&encode(&Authentication::SaslFinal { additional_data: server_final })?).await?;
+        Ok(Some(State::FinishedSasl))
+    }
+
+    // Frontend isn't asked what backend has asked but what the user's secret allows to check.
+    async fn authenticate_locally(
+        &mut self,
+        secrets: &Secrets,
+        backend_secret: Option<Secret>,
+        bytes: Vec<u8>,
+        authentication: Authentication,
+    ) -> ConveyResult<Option<State>> {
+        let (secret, known) = match secrets.get(&self.user) {
+            Some(secret) => (secret.clone(), true),
+            None => (secrets.mock(&self.user), false),
+        };
+        let client_key = match &secret {
+            Secret::Password(password) => {
+                self.authenticate_frontend_with_md5(&md5::password_hash(password, &self.user)).await?;
+                None
+            },
+            Secret::Md5(password_hash) => {
+                self.authenticate_frontend_with_md5(password_hash).await?;
+                None
+            },
+            Secret::Scram(scram_secret) => {
+                let (client_key, server_final) = self.authenticate_frontend_with_scram(scram_secret.clone()).await?;
+                self.write_frontend(&encode(&Authentication::SaslFinal { additional_data: server_final })?).await?;
+                Some(client_key)
+            },
+        };
+        if !known {
+            return self.reject_frontend_unless_ok(Err("user has no secret".into())).await
+        }
+        match backend_secret {
+            Some(backend_secret) => self.answer_backend(bytes, authentication, &backend_secret, None).await,
+            None => self.answer_backend(bytes, authentication, &secret, client_key).await,
+        }
+    }
+
+    // Frontend is already authenticated or trusted, backend's AuthenticationOk or ErrorResponse is conveyed as usual.
+    async fn answer_backend(
+        &mut self,
+        bytes: Vec<u8>,
+        authentication: Authentication,
+        secret: &Secret,
+        client_key: Option<Vec<u8>>,
+    ) -> ConveyResult<Option<State>> {
+        use Authentication as Auth;
+        match (authentication, secret, client_key) {
+            (Auth::Ok, _, _) => {
+                self.write_frontend(&bytes).await?;
+                Ok(Some(State::Authenticated))
+            },
+            (Auth::CleartextPassword, Secret::Password(password), _) => {
+                self.write_backend(&encode(&Password(password.clone()))?).await?;
+                Ok(Some(State::GotCleartextPassword))
+            },
+            (Auth::Md5Password { salt }, Secret::Password(password), _) => {
+                let password_hash = md5::password_hash(password, &self.user);
+                self.write_backend(&encode(&Password(md5::salted_password(&password_hash, salt)))?).await?;
+                Ok(Some(State::GotMd5Password))
+            },
+            (Auth::Md5Password { salt }, Secret::Md5(password_hash), _) => {
+                self.write_backend(&encode(&Password(md5::salted_password(password_hash, salt)))?).await?;
+                Ok(Some(State::GotMd5Password))
+            },
+            (Auth::Sasl { auth_mechanisms }, Secret::Password(password), _) => {
+                let credential = ClientCredential::Password(password.clone());
+                self.answer_backend_with_scram(&auth_mechanisms, credential).await
+            },
+            (Auth::Sasl { auth_mechanisms }, Secret::Scram(scram_secret), Some(client_key)) => {
+                let credential = ClientCredential::ClientKey(scram_secret.clone(), client_key);
+                self.answer_backend_with_scram(&auth_mechanisms, credential).await
+            },
+            (authentication, secret, _) => {
+                let reason = format!("backend asks for {:?} which can't be answered with {:?}", authentication, secret);
+                self.reject_frontend_unless_ok(Err(reason)).await
+            },
+        }
+    }
+
+    async fn answer_backend_with_scram(
+        &mut self,
+        backend_mechanisms: &[Vec<u8>],
+        credential: ClientCredential,
+    ) -> ConveyResult<Option<State>> {
+        let client = ClientExchange::new(backend_mechanisms, self.backend_end_point())
+            .ok_or_else(|| "backend doesn't offer SCRAM-SHA-256".to_string());
+        let client = self.reject_frontend_unless_ok(client).await?;
+        if !self.authenticate_backend_with_scram(client, credential).await? {
+            return Ok(None)
+        }
+        Ok(Some(State::FinishedSasl))
+    }
+
+    async fn authenticate_frontend_with_md5(&mut self, password_hash: &[u8]) -> ConveyResult<()> {
+        let salt = md5::random_salt();
+        self.write_frontend(&encode(&Authentication::Md5Password { salt })?).await?;
+        let (_, password) = self.read_frontend_during_auth::<Password>(State::AskedMd5Password).await?;
+        self.callback_frontend(FrontendMsg::Password(&password));
+        if !auth::constant_time_eq(&password.0, &md5::salted_password(password_hash, salt)) {
+            return self.reject_frontend_unless_ok(Err("password is wrong".into())).await
+        }
+        Ok(())
+    }
+
+    // Returns ClientKey of the user and server-final-message which is up to the caller to send.
+    async fn authenticate_frontend_with_scram(&mut self, secret: ScramSecret) -> ConveyResult<(Vec<u8>, Vec<u8>)> {
+        let frontend_end_point = self.frontend_end_point();
+        let auth_mechanisms = ServerExchange::mechanisms(&frontend_end_point).iter()
+            .map(|mechanism| mechanism.as_bytes().to_vec())
+            .collect();
+        self.write_frontend(&encode(&Authentication::Sasl { auth_mechanisms })?).await?;
+        let (bytes, initial_response) = self.read_frontend_during_auth::<SaslInitialResponse>(State::AskedSaslInitialResponse).await?;
+        self.callback_sasl_initial_response(&bytes, &initial_response)?;
+        let server_start = ServerExchange::start(secret, &initial_response, frontend_end_point);
+        let (server, server_first) = self.reject_frontend_unless_ok(server_start).await?;
+        self.write_frontend(&encode(&Authentication::SaslContinue { challenge_data: server_first })?).await?;
+        let (bytes, response) = self.read_frontend_during_auth::<SaslResponse>(State::AskedSaslResponse).await?;
+        self.callback_sasl_response(&bytes, &response)?;
+        self.reject_frontend_unless_ok(server.finish(&response.mechanism_data)).await
+    }
+
+    // Returns false if backend has refused with ErrorResponse which is conveyed to frontend.
+    async fn authenticate_backend_with_scram(&mut self, mut client: ClientExchange, credential: ClientCredential) -> ConveyResult<bool> {
+        let (mechanism, client_first) = client.start();
+        self.scram = Some(mechanism);
+        let initial_response = SaslInitialResponse {
+            selected_mechanism: mechanism.name().as_bytes().to_vec(),
+            mechanism_data: Some(client_first),
+        };
+        self.write_backend(&encode(&initial_response)?).await?;
+        let challenge_data = match self.read_backend_during_auth(State::GotAnySaslResponse).await? {
+            Some(Authentication::SaslContinue { challenge_data }) => challenge_data,
+            Some(_) => return self.reject_frontend_unless_ok(Err("backend didn't continue SASL".into())).await,
+            None => return Ok(false),
+        };
+        let client_final = self.reject_frontend_unless_ok(client.proceed(&challenge_data, &credential)).await?;
+        self.write_backend(&encode(&SaslResponse { mechanism_data: client_final })?).await?;
+        let additional_data = match self.read_backend_during_auth(State::GotAnySaslResponse).await? {
+            Some(Authentication::SaslFinal { additional_data }) => additional_data,
+            Some(_) => return self.reject_frontend_unless_ok(Err("backend didn't finish SASL".into())).await,
+            None => return Ok(false),
+        };
+        self.reject_frontend_unless_ok(client.finish(&additional_data)).await?;
+        Ok(true)
+    }
+
+    async fn read_frontend_during_auth<Msg>(&mut self, state: State) -> ConveyResult<(Vec<u8>, Msg)>
+    where Msg: 'static + MsgDecode {
+        let byte = self.read_frontend_type_byte().await?;
+        match FrontendTypeByte::try_from(byte) {
+            Ok(FrontendTypeByte::GssResponse_or_Password_or_SaslResponses) => self.read_frontend::<Msg>().await,
+            Ok(type_byte) => Err(UnexpectedType(state, None, TypeByte::Frontend(type_byte))),
+            Err(_) => Err(UnknownType(Side::Frontend, byte)),
+        }
+    }
+
+    // Returns None if backend has refused with ErrorResponse which is conveyed to frontend.
+    async fn read_backend_during_auth(&mut self, state: State) -> ConveyResult<Option<Authentication>> {
+        let byte = self.read_backend_type_byte().await?;
+        match BackendTypeByte::try_from(byte) {
+            Ok(BackendTypeByte::Authentication) => {
+                let (bytes, authentication) = self.read_backend::<Authentication>().await?;
+                self.callback_backend_authentication(&bytes, &authentication)?;
+                Ok(Some(authentication))
+            },
+            Ok(BackendTypeByte::ErrorResponse) => {
+                read_backend_through!(<ErrorResponse>, self, None);
+                Ok(None)
+            },
+            Ok(type_byte) => Err(UnexpectedType(state, None, TypeByte::Backend(type_byte))),
+            Err(_) => Err(UnknownType(Side::Backend, byte)),
+        }
+    }
+
+    // Frontend gets the same error whether it or backend has failed, the details are in AuthFailed.
+    async fn reject_frontend_unless_ok<T>(&mut self, result: ScramResult<T>) -> ConveyResult<T> {
+        match result {
+            Ok(ok) => Ok(ok),
+            Err(reason) => {
+                let text = format!("password authentication failed for user \"{}\"", String::from_utf8_lossy(&self.user));
+                let error = ErrorResponse(ErrorOrNoticeFields {
+                    localized_severity: Some(b"FATAL".to_vec()),
+                    severity: Some(b"FATAL".to_vec()),
+                    code: Some(INVALID_PASSWORD.into()),
+                    message: Some(text.into_bytes()),
+                    ..Default::default()
+                });
+                self.write_frontend(&encode(&error)?).await?;
+                Err(AuthFailed(reason))
+            },
+        }
+    }
+
+    fn frontend_end_point(&self) -> Option<Vec<u8>> {
+        match &self.frontend {
+            StreamWrap::Tls(tls) => tls.tls_server_end_point(),
+            _ => None,
+        }
+    }
+
+    fn backend_end_point(&self) -> Option<Vec<u8>> {
+        match &self.backend {
+            StreamWrap::Tls(tls) => tls.tls_server_end_point(),
+            _ => None,
+        }
+    }
+
+    fn callback_sasl_initial_response(&mut self, bytes: &[u8], initial_response: &SaslInitialResponse) -> ConveyResult<()> {
+        self.scram = scram::Mechanism::of(initial_response);
+        match ClientFirst::parse(initial_response) {
+            Some(client_first) => {
+                let client_first = self.unwrap_scram(bytes, client_first)?;
+                self.callback_frontend(FrontendMsg::ScramClientFirst(&client_first));
+            },
+            None => self.callback_frontend(FrontendMsg::SaslInitialResponse(initial_response)),
         }
+        Ok(())
+    }
+
+    fn callback_sasl_response(&mut self, bytes: &[u8], response: &SaslResponse) -> ConveyResult<()> {
+        if self.scram.is_some() {
+            let client_final = self.unwrap_scram(bytes, ClientFinal::parse(&response.mechanism_data))?;
+            self.callback_frontend(FrontendMsg::ScramClientFinal(&client_final));
+        } else {
+            self.callback_frontend(FrontendMsg::SaslResponse(response));
+        }
+        Ok(())
+    }
+
+    fn callback_backend_authentication(&mut self, bytes: &[u8], authentication: &Authentication) -> ConveyResult<()> {
+        match (authentication, self.scram) {
+            (Authentication::SaslContinue { challenge_data }, Some(_)) => {
+                let server_first = self.unwrap_scram(bytes, ServerFirst::parse(challenge_data))?;
+                self.callback_backend(BackendMsg::ScramServerFirst(&server_first));
+            },
+            (Authentication::SaslFinal { additional_data }, Some(_)) => {
+                let server_final = self.unwrap_scram(bytes, ServerFinal::parse(additional_data))?;
+                self.callback_backend(BackendMsg::ScramServerFinal(&server_final));
+            },
+            _ => self.callback_backend(BackendMsg::Authentication(authentication)),
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, pending: Pending) -> ConveyResult<State> {
+        self.pending.push_back(pending);
+        Ok(State::ReadyForQuery)
+    }
+
+    fn replace_expected(&mut self, pending: Pending) -> ConveyResult<State> {
+        if let Some(front) = self.pending.front_mut() {
+            *front = pending;
+        }
+        Ok(State::ReadyForQuery)
+    }
+
+    fn complete_expected(&mut self) -> ConveyResult<State> {
+        self.pending.pop_front();
+        Ok(State::ReadyForQuery)
+    }
+
+    fn abort_extended_query(&mut self) -> ConveyResult<State> {
+        // Backend discards the rest of the current pipeline up to the nearest Sync.
+        while let Some(pending) = self.pending.front() {
+            if *pending == Pending::GotSync {
+                return Ok(State::ReadyForQuery)
+            }
+            if *pending == Pending::DeniedStatement {
+                // backend wouldn't get to the denied statement either
+                self.denials.pop_front();
+            }
+            self.pending.pop_front();
+        }
+        self.expect(Pending::AbortedExtendedQuery)
+    }
+
+    fn start_copying_in_extended_query(&mut self, pending: Pending) -> ConveyResult<State> {
+        // Backend ignores Sync got while copying in, but libpq sends it right after Execute.
+        while self.pending.get(1) == Some(&Pending::GotSync) {
+            self.pending.remove(1);
+        }
+        self.replace_expected(pending)
+    }
+
+    fn is_skipping_until_sync(&self) -> bool {
+        self.pending.back() == Some(&Pending::AbortedExtendedQuery)
+    }
+
+    async fn convey_ignored_frontend(&mut self, type_byte: FrontendTypeByte) -> ConveyResult<()> {
+        use FrontendTypeByte as F;
+        match type_byte {
+            F::Bind => { read_frontend_ignored!(<Bind>, self, ()); },
+            F::Close => { read_frontend_ignored!(<Close>, self, ()); },
+            F::CopyData => { read_frontend_ignored!(<CopyData>, self, ()); },
+            F::CopyDone => { read_frontend_ignored!(<CopyDone>, self, ()); },
+            F::CopyFail => { read_frontend_ignored!(<CopyFail>, self, ()); },
+            F::Describe => { read_frontend_ignored!(<Describe>, self, ()); },
+            F::Execute => { read_frontend_ignored!(<Execute>, self, ()); },
+            F::Flush => { read_frontend_ignored!(<Flush>, self, ()); },
+            F::FunctionCall => { read_frontend_ignored!(<FunctionCall>, self, ()); },
+            F::Parse => { read_frontend_ignored!(<Parse>, self, ()); },
+            F::Query => { read_frontend_ignored!(<Query>, self, ()); },
+            _ => return Err(UnexpectedType(
+                State::ReadyForQuery, Some(Pending::AbortedExtendedQuery), TypeByte::Frontend(type_byte)
+            )),
+        }
+        Ok(())
+    }
+
+    // Returns the message to go on with or None if it's held back.
+    async fn convey_intercepted<Msg>(&mut self, from: Side, bytes: Vec<u8>, msg: Msg, intercepted: Intercepted) -> ConveyResult<Option<Msg>>
+    where Msg: MsgDecode {
+        match intercepted {
+            Intercepted::Forward => {
+                self.write_to(from.opposite(), &bytes).await?;
+                Ok(Some(msg))
+            },
+            Intercepted::Replace(bytes) => {
+                let msg = intercept::decode_replacement(&bytes)?;
+                self.write_to(from.opposite(), &bytes).await?;
+                Ok(Some(msg))
+            },
+            Intercepted::Drop => Ok(None),
+            Intercepted::Respond(responses) => {
+                for response in responses {
+                    self.write_to(from, &response).await?;
+                }
+                Ok(None)
+            },
+        }
+    }
+
+    fn intercept_frontend(&mut self, msg: FrontendMsg) -> ConveyResult<Intercepted> {
+        if let Some(intercepted) = self.check_frontend(&msg)? {
+            return Ok(intercepted)
+        }
+        Ok(self.interceptor.intercept_frontend(msg))
+    }
+
+    fn intercept_backend(&mut self, msg: BackendMsg) -> ConveyResult<Intercepted> {
+        Ok(self.interceptor.intercept_backend(msg))
+    }
+
+    // Read-only mode and the firewall go before the interceptor which doesn't see messages they replace or hold back.
+    fn check_frontend(&mut self, msg: &FrontendMsg) -> ConveyResult<Option<Intercepted>> {
+        if self.denied_till_sync {
+            return match msg {
+                FrontendMsg::Terminate(_) => Ok(None),
+                FrontendMsg::Sync(_) => {
+                    self.denied_till_sync = false;
+                    if self.pending.is_empty() {
+                        Ok(Some(Intercepted::Respond(vec![self.encode_ready_for_query()?])))
+                    } else {
+                        Ok(None)  // backend answers to the messages before the denied one
+                    }
+                },
+                _ => Ok(Some(Intercepted::Drop)),
+            }
+        }
+        match msg {
+            FrontendMsg::Initial(Initial::Startup(startup)) if self.options.read_only.is_some() => {
+                let startup = Initial::Startup(read_only_startup(startup));
+                Ok(Some(Intercepted::Replace(encode_msg(&startup).map_err(EncodeError)?)))
+            },
+            FrontendMsg::Query(Query(statement)) |
+            FrontendMsg::Parse(Parse { query: Text(statement), .. }) => self.check_statement(msg, statement),
+            FrontendMsg::FunctionCall(call) if self.options.read_only.is_some() => {
+                // the function is only known by OID and may write, so none is called in read-only mode
+                let denied = FirewallEvent::DeniedInReadOnly {
+                    rule: READ_ONLY_FUNCTION_CALL_RULE.into(),
+                    statement: format!("function call of OID {}", call.function_oid),
+                };
+                (self.callback)(Message::Firewall(&denied));
+                self.deny(msg, READ_ONLY_SQL_TRANSACTION, "function call is denied in read-only mode of postgread", READ_ONLY_FUNCTION_CALL_RULE).map(Some)
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn check_statement(&mut self, msg: &FrontendMsg, statement: &[u8]) -> ConveyResult<Option<Intercepted>> {
+        let statement_text = || String::from_utf8_lossy(statement).into_owned();
+        if let Some(rules) = self.options.read_only.clone() {
+            if let Some(rule) = rules.check(&self.user, &self.database, statement).filter(|rule| rule.action == Action::Deny) {
+                let rule = rule.to_string();
+                (self.callback)(Message::Firewall(&FirewallEvent::DeniedInReadOnly { rule: rule.clone(), statement: statement_text() }));
+                return self.deny(msg, READ_ONLY_SQL_TRANSACTION, "statement is denied in read-only mode of postgread", &rule).map(Some)
+            }
+        }
+        if let Some(rules) = self.options.firewall.clone() {
+            if let Some(rule) = rules.check(&self.user, &self.database, statement) {
+                let (action, rule) = (rule.action, rule.to_string());
+                if action == Action::Allow {
+                    (self.callback)(Message::Firewall(&FirewallEvent::Allowed { rule, statement: statement_text() }));
+                    return Ok(None)
+                }
+                (self.callback)(Message::Firewall(&FirewallEvent::Denied { rule: rule.clone(), statement: statement_text() }));
+                return self.deny(msg, INSUFFICIENT_PRIVILEGE, "statement is denied by postgread firewall", &rule).map(Some)
+            }
+        }
+        Ok(None)
+    }
+
+    fn deny(&mut self, msg: &FrontendMsg, code: &str, message: &str, rule: &str) -> ConveyResult<Intercepted> {
+        let error = ErrorResponse(ErrorOrNoticeFields {
+            localized_severity: Some(b"ERROR".to_vec()),
+            severity: Some(b"ERROR".to_vec()),
+            code: Some(code.into()),
+            message: Some(message.into()),
+            detail: Some(format!("Rule: {}", rule).into_bytes()),
+            ..Default::default()
+        });
+        let error = encode_msg(&error).map_err(EncodeError)?;
+        if self.is_skipping_until_sync() {
+            return Ok(Intercepted::Drop)  // backend would ignore the statement and not answer it
+        }
+        let ready = !matches!(msg, FrontendMsg::Parse(_));
+        if !ready {
+            // like backend does after an error in extended query
+            self.denied_till_sync = true;
+        }
+        // the answer waits in the queue so frontend gets it after the answers to the requests sent before
+        self.pending.push_back(Pending::DeniedStatement);
+        self.denials.push_back(Denial { error, ready });
+        Ok(Intercepted::Drop)
+    }
+
+    async fn answer_denied(&mut self) -> ConveyResult<()> {
+        while self.pending.front() == Some(&Pending::DeniedStatement) {
+            self.pending.pop_front();
+            if let Some(denial) = self.denials.pop_front() {
+                self.write_frontend(&denial.error).await?;
+                if denial.ready {
+                    let ready = self.encode_ready_for_query()?;
+                    self.write_frontend(&ready).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_ready_for_query(&self) -> ConveyResult<Vec<u8>> {
+        // backend hasn't got the denied statement so its transaction goes on as is
+        encode_msg(&ReadyForQuery { status: self.transaction_status.clone() }).map_err(EncodeError)
     }
 
     // util:
@@ -464,37 +1548,89 @@ where
         (self.callback)(Message::Frontend(wrap));
     }
 
+    fn callback_frontend_ignored(&mut self, wrap: FrontendMsg<'a>) {
+        (self.callback)(Message::FrontendIgnored(wrap));
+    }
+
     async fn read_backend<Msg>(&mut self) -> ConveyResult<(Vec<u8>, Msg)>
     where Msg: 'static + MsgDecode {
-        unwrap_stream!(&mut self.backend, Self::read_msg_mapping_err).await
+        let read_data = unwrap_stream!(&mut self.backend, Self::read_msg_mapping_err).await?;
+        self.record(Direction::FromBackend, &read_data.bytes);
+        self.unwrap_read_data(read_data)
     }
 
     async fn read_frontend<Msg>(&mut self) -> ConveyResult<(Vec<u8>, Msg)>
     where Msg: 'static + MsgDecode {
-        unwrap_stream!(&mut self.frontend, Self::read_msg_mapping_err).await
+        let read_data = unwrap_stream!(&mut self.frontend, Self::read_msg_mapping_err).await?;
+        let direction = if Msg::TYPE_BYTE_OPT.is_some() { Direction::FromFrontend } else { Direction::FromFrontendInitial };
+        self.record(direction, &read_data.bytes);
+        self.unwrap_read_data(read_data)
+    }
+
+    async fn read_raw(&mut self, side: Side, type_byte: u8) -> ConveyResult<Vec<u8>> {
+        let (bytes, direction) = match side {
+            Side::Backend => (
+                unwrap_stream!(&mut self.backend, |rd| Self::read_raw_mapping_err(rd, type_byte)).await?,
+                Direction::FromBackend,
+            ),
+            Side::Frontend => (
+                unwrap_stream!(&mut self.frontend, |rd| Self::read_raw_mapping_err(rd, type_byte)).await?,
+                Direction::FromFrontend,
+            ),
+        };
+        self.record(direction, &bytes);
+        Ok(bytes)
+    }
+
+    // A capture must not break the session, so the recording is dropped once it fails.
+    fn record(&mut self, direction: Direction, bytes: &[u8]) {
+        let result = match &self.options.recording {
+            Some(recording) => recording.record(direction, bytes),
+            None => return,
+        };
+        if let Err(err) = result {
+            self.options.recording = None;
+            (self.callback)(Message::Capture(&CaptureEvent::RecordingStopped { error: err.to_string() }));
+        }
+    }
+
+    // SCRAM payload is parsed after the message itself so it's undecoded as a whole if the payload is broken.
+    fn unwrap_scram<Scram>(&mut self, bytes: &[u8], scram_result: DecodeResult<Scram>) -> ConveyResult<Scram> {
+        scram_result.map_err(|problem| {
+            self.undecoded = Some(bytes.to_vec());
+            DecodeError(problem)
+        })
+    }
+
+    fn unwrap_read_data<Msg>(&mut self, read_data: ReadData<Msg>) -> ConveyResult<(Vec<u8>, Msg)> {
+        let ReadData { bytes, msg_result } = read_data;
+        match msg_result {
+            Ok(message) => Ok((bytes, message)),
+            Err(msg_error) => {
+                self.undecoded = Some(bytes);
+                Err(match msg_error {
+                    MsgError::DecodeError(decode_error) => ConveyError::DecodeError(decode_error),
+                    MsgError::LeftUndecoded(left) => ConveyError::LeftUndecoded(left),
+                })
+            },
+        }
     }
 
-    async fn read_msg_mapping_err<R, Msg>(reader: &mut R) -> ConveyResult<(Vec<u8>, Msg)>
+    async fn read_msg_mapping_err<R, Msg>(reader: &mut R) -> ConveyResult<ReadData<Msg>>
     where
         R: ConveyReader,
         Msg: 'static + MsgDecode,
     {
-        let ReadData { bytes, msg_result } = reader.read_msg().await.map_err(|read_err|
-            match read_err {
-                ReadError::IoError(io_error) => ConveyError::IoError(io_error),
-                ReadError::EncodeError(encode_error) => ConveyError::EncodeError(encode_error),
-            }
-        )?;
-        let message = msg_result.map_err(|msg_error|
-            match msg_error {
-                MsgError::DecodeError(decode_error) => ConveyError::DecodeError(decode_error),
-                MsgError::LeftUndecoded(left) => ConveyError::LeftUndecoded(left),
-            }
-        )?;
-        Ok((bytes, message))
+        reader.read_msg().await.map_err(map_read_err)
+    }
+
+    async fn read_raw_mapping_err<R>(reader: &mut R, type_byte: u8) -> ConveyResult<Vec<u8>>
+    where R: ConveyReader {
+        reader.read_raw(type_byte).await.map_err(map_read_err)
     }
 
-    async fn read_type_byte_from_both(&mut self) -> ConveyResult<(Side, TypeByte)> {
+    // Returns the raw byte instead of the type if it's unknown for the side.
+    async fn read_type_byte_from_both(&mut self) -> ConveyResult<(Side, Result<TypeByte, u8>)> {
         let either = future::select(
             unwrap_stream!(&mut self.backend, Self::read_type_byte).boxed(),
             unwrap_stream!(&mut self.frontend, Self::read_type_byte).boxed(),
@@ -504,24 +1640,77 @@ where
             Either::Left((backend, _frontend)) => backend.map(|byte| (Side::Backend, byte)),
             Either::Right((frontend, _backend)) => frontend.map(|byte| (Side::Frontend, byte)),
         }?;
-        let type_byte = TypeByte::try_from(byte).map_err(|_| UnknownType(side, byte))?;
+        let type_byte = match side {
+            Side::Backend => BackendTypeByte::try_from(byte).map(TypeByte::Backend).map_err(|_| byte),
+            Side::Frontend => FrontendTypeByte::try_from(byte).map(TypeByte::Frontend).map_err(|_| byte),
+        };
         Ok((side, type_byte))
     }
 
+    async fn read_some_from_both(&mut self) -> ConveyResult<(Side, Vec<u8>)> {
+        let either = future::select(
+            unwrap_stream!(&mut self.backend, Self::read_some).boxed(),
+            unwrap_stream!(&mut self.frontend, Self::read_some).boxed(),
+        ).await;
+        match either {
+            Either::Left((backend, _frontend)) => backend.map(|bytes| (Side::Backend, bytes)),
+            Either::Right((frontend, _backend)) => frontend.map(|bytes| (Side::Frontend, bytes)),
+        }
+    }
+
+    async fn read_some(reader: &mut impl ConveyReader) -> ConveyResult<Vec<u8>> {
+        reader.read_some().await.map_err(IoError)
+    }
+
     async fn read_backend_type_byte(&mut self) -> ConveyResult<u8> {
         unwrap_stream!(&mut self.backend, Self::read_type_byte).await
     }
 
+    async fn read_frontend_type_byte(&mut self) -> ConveyResult<u8> {
+        unwrap_stream!(&mut self.frontend, Self::read_type_byte).await
+    }
+
     async fn read_type_byte(reader: &mut impl ConveyReader) -> ConveyResult<u8> {
         reader.read_type_byte().await.map_err(IoError)
     }
 
+    async fn write_to(&mut self, side: Side, bytes: &[u8]) -> ConveyResult<()> {
+        match side {
+            Side::Backend => self.write_backend(bytes).await,
+            Side::Frontend => self.write_frontend(bytes).await,
+        }
+    }
+
+    // Unlike recording, capturing takes what is written, so it shows interception, and relayed bytes too.
     async fn write_backend(&mut self, bytes: &[u8]) -> ConveyResult<()> {
-        unwrap_stream!(&mut self.backend, |wr| Self::write_bytes(wr, bytes)).await
+        unwrap_stream!(&mut self.backend, |wr| Self::write_bytes(wr, bytes)).await?;
+        self.capture(Side::Backend, bytes);
+        Ok(())
     }
 
     async fn write_frontend(&mut self, bytes: &[u8]) -> ConveyResult<()> {
-        unwrap_stream!(&mut self.frontend, |wr| Self::write_bytes(wr, bytes)).await
+        unwrap_stream!(&mut self.frontend, |wr| Self::write_bytes(wr, bytes)).await?;
+        self.capture(Side::Frontend, bytes);
+        Ok(())
+    }
+
+    // The bytes are delivered already, so the capture is dropped once it fails like recording.
+    fn capture(&mut self, to: Side, bytes: &[u8]) {
+        let result = match (&self.options.pcapng, to) {
+            (Some(pcapng), Side::Backend) => pcapng.sent_to_server(bytes),
+            (Some(pcapng), Side::Frontend) => pcapng.sent_to_client(bytes),
+            (None, _) => return,
+        };
+        if let Err(err) = result {
+            self.options.pcapng = None;
+            (self.callback)(Message::Capture(&CaptureEvent::PcapngStopped { error: err.to_string() }));
+        }
+    }
+
+    // Single-byte answers aren't captured like the requests for encryption they answer.
+    async fn answer_encryption_request(&mut self, answer: u8) -> ConveyResult<()> {
+        let bytes = [answer];
+        unwrap_stream!(&mut self.frontend, |wr| Self::write_bytes(wr, &bytes)).await
     }
 
     async fn write_bytes(writer: &mut impl ConveyWriter, bytes: &[u8]) -> ConveyResult<()> {
@@ -529,6 +1718,27 @@ where
     }
 }
 
+fn encode<Msg: MsgDecode + MsgEncode>(msg: &Msg) -> ConveyResult<Vec<u8>> {
+    encode_msg(msg).map_err(EncodeError)
+}
+
+pub(crate) fn map_read_err(read_err: ReadError) -> ConveyError {
+    match read_err {
+        ReadError::IoError(io_error) => ConveyError::IoError(io_error),
+        ReadError::EncodeError(encode_error) => ConveyError::EncodeError(encode_error),
+    }
+}
+
+// Backend applies params after the -c switches in "options" so the injected one wins over them.
+fn read_only_startup(startup: &Startup) -> Startup {
+    let mut params: Vec<_> = startup.params.iter()
+        .filter(|param| param.name != READ_ONLY_PARAM)
+        .cloned()
+        .collect();
+    params.push(StartupParam::new(READ_ONLY_PARAM.into(), b"on".to_vec()));
+    Startup { version: startup.version, params }
+}
+
 async fn unwrap_stream<'w, Plain, Tls, FnPlain, FnTls, Ok>(
     wrap: &'w mut StreamWrap<Plain, Tls>,
     fn_plain: impl Fn(&'w mut Plain) -> FnPlain,
@@ -614,6 +1824,10 @@ trait ConveyReader : Send + Unpin {
     async fn read_msg<Msg>(&mut self) -> ReadResult<Msg>
     where Msg: 'static + MsgDecode;
 
+    async fn read_raw(&mut self, type_byte: u8) -> Result<Vec<u8>, ReadError>;
+
+    async fn read_some(&mut self) -> IoResult<Vec<u8>>;  // empty at the end of stream
+
     async fn read_type_byte(&mut self) -> IoResult<u8>;
 }
 
@@ -625,6 +1839,17 @@ where R: AsyncRead + Send + Unpin {
         read_msg(self).await
     }
 
+    async fn read_raw(&mut self, type_byte: u8) -> Result<Vec<u8>, ReadError> {
+        read_raw(self, type_byte).await
+    }
+
+    async fn read_some(&mut self) -> IoResult<Vec<u8>> {
+        let mut bytes = vec![0u8; RELAY_BUFFER_LEN];
+        let len = self.read(&mut bytes).await?;
+        bytes.truncate(len);
+        Ok(bytes)
+    }
+
     async fn read_type_byte(&mut self) -> IoResult<u8> {
         async_io::read_u8(self).await
     }
@@ -643,5 +1868,12 @@ where W: AsyncWrite + Send + Unpin {
     }
 }
 
-const TLS_SUPPORTED: u8 = b'S';
+pub(crate) const GSS_ENC_NOT_SUPPORTED: u8 = b'N';
+const INSUFFICIENT_PRIVILEGE: &str = "42501";
+const INVALID_PASSWORD: &str = "28P01";
+const READ_ONLY_FUNCTION_CALL_RULE: &str = "deny * * FunctionCall";
+const READ_ONLY_PARAM: &[u8] = b"default_transaction_read_only";
+const READ_ONLY_SQL_TRANSACTION: &str = "25006";
+const RELAY_BUFFER_LEN: usize = 8192;
+pub(crate) const TLS_SUPPORTED: u8 = b'S';
 const TLS_NOT_SUPPORTED: u8 = b'N';
@@ -0,0 +1,77 @@
+use crate::convey::{BackendMsg, ConveyError::*, ConveyResult, FrontendMsg};
+use crate::msg::util::decode::{BytesSource, MsgDecode, Problem as DecodeProblem};
+
+// What to do with a message which is read and reported to the callback but not conveyed yet.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Intercepted {
+    // Convey the message as is.
+    Forward,
+    // Convey another message of the same type instead, the conveyor follows it rather than the original.
+    // The bytes are the type byte, the length and the body as made by encode_msg.
+    Replace(Vec<u8>),
+    // Convey nothing, the conveyor stays in the same state as if the message hasn't come.
+    Drop,
+    // Like Drop but send these messages back to the side the message has come from.
+    Respond(Vec<Vec<u8>>),
+}
+
+// Hooks run on messages modeled by the conveyor, i.e. not on authentication and opaque ones
+// nor on anything relayed as is. The interceptor is responsible for the dialogue to remain correct.
+pub trait Interceptor: Send {
+    fn intercept_backend(&mut self, _msg: BackendMsg) -> Intercepted {
+        Intercepted::Forward
+    }
+
+    fn intercept_frontend(&mut self, _msg: FrontendMsg) -> Intercepted {
+        Intercepted::Forward
+    }
+}
+
+pub struct ForwardAll;
+
+impl Interceptor for ForwardAll {}
+
+pub(super) fn decode_replacement<Msg: MsgDecode>(bytes: &[u8]) -> ConveyResult<Msg> {
+    let mut bytes_source = BytesSource::new(bytes);
+    if let Some(type_byte) = Msg::TYPE_BYTE_OPT {
+        if bytes_source.take_u8().map_err(DecodeError)? != type_byte {
+            return Err(Unsupported("replacing a message with another type"))
+        }
+    }
+    let len = bytes_source.take_u32().map_err(DecodeError)? as usize;
+    if len != 4 + bytes_source.left() {
+        return Err(DecodeError(DecodeProblem::Incorrect(format!("length {} of {} bytes", len, 4 + bytes_source.left()))))
+    }
+    let msg = Msg::decode_body(&mut bytes_source).map_err(DecodeError)?;
+    match bytes_source.left() {
+        0 => Ok(msg),
+        left => Err(LeftUndecoded(left)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_replacement;
+    use crate::convey::ConveyError::*;
+    use crate::msg::body::{Query, Sync};
+    use crate::msg::util::decode::Problem::*;
+    use crate::msg::util::encode::encode_msg;
+
+    #[test]
+    fn replacement_of_same_type() {
+        let bytes = encode_msg(&Query(b"select 1".to_vec())).unwrap();
+        assert_eq!(Query(b"select 1".to_vec()), decode_replacement::<Query>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn replacement_of_another_type() {
+        let bytes = encode_msg(&Sync()).unwrap();
+        assert!(matches!(decode_replacement::<Query>(&bytes), Err(Unsupported(_))));
+    }
+
+    #[test]
+    fn replacement_of_wrong_length() {
+        let bytes = b"Q\0\0\0\x09ab\0";
+        assert!(matches!(decode_replacement::<Query>(bytes), Err(DecodeError(Incorrect(_)))));
+    }
+}
@@ -1,4 +1,4 @@
 mod fake_stream;
-mod fake_tls;
-mod new_msg;
+pub(crate) mod fake_tls;
+pub(crate) mod new_msg;
 mod protocol;
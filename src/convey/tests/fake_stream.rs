@@ -1,5 +1,7 @@
 use super::super::*;
 use crate::convey::tests::fake_tls::*;
+use crate::msg::replication::{WalReceiverMsg, WalSenderMsg};
+use crate::msg::util::encode::MsgEncode;
 
 use ::async_trait::async_trait;
 use ::std::any::{Any, type_name};
@@ -13,6 +15,7 @@ use ::futures::task::{Context, Poll};
 
 pub struct TwoFakeStreams {
     items: Arc<Mutex<VecDeque<TwoFakeStreamsItem>>>,
+    written: FakeWritten,
     backend_tls_started: bool,
     frontend_tls_started: bool,
 }
@@ -20,6 +23,14 @@ pub struct TwoFakeStreams {
 pub struct FakeStream {
     side: FakeStreamSide,
     items: Arc<Mutex<VecDeque<TwoFakeStreamsItem>>>,
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+// Bytes written by the conveyor to each side, TLS doesn't encrypt them in fakes.
+#[derive(Clone, Default)]
+pub struct FakeWritten {
+    backend: Arc<Mutex<Vec<u8>>>,
+    frontend: Arc<Mutex<Vec<u8>>>,
 }
 
 pub struct TwoFakeStreamsItem {
@@ -37,8 +48,14 @@ pub enum FakeData {
     TypeByte(u8),
     Body {
         any: Box<dyn Any + Send>,
+        bytes: Vec<u8>,  // as read by the conveyor: the type byte, the length and the body
         type_name: &'static str,  // for debug output
     },
+    Raw(Vec<u8>),
+    Undecodable {
+        bytes: Vec<u8>,
+        error: MsgError,
+    },
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -47,6 +64,31 @@ pub enum FakeStreamSide {
     Frontend,
 }
 
+// Bytes the conveyor reads along with a fake message.
+pub trait FakeBytes {
+    fn fake_bytes(&self) -> Vec<u8>;
+}
+
+impl<Msg> FakeBytes for Msg
+where Msg: MsgDecode + MsgEncode {
+    fn fake_bytes(&self) -> Vec<u8> {
+        encode_msg(self).unwrap()
+    }
+}
+
+// Replication messages aren't encodable, the conveyor forwards nothing for them in fakes.
+impl<Data> FakeBytes for WalSenderMsg<Data> {
+    fn fake_bytes(&self) -> Vec<u8> {
+        vec![]
+    }
+}
+
+impl FakeBytes for WalReceiverMsg {
+    fn fake_bytes(&self) -> Vec<u8> {
+        vec![]
+    }
+}
+
 use FakeDataForm::*;
 use FakeData::*;
 use FakeStreamSide::*;
@@ -55,6 +97,7 @@ impl TwoFakeStreams {
     pub fn new() -> Self {
         Self {
             items: Arc::new(Mutex::new(VecDeque::new())),
+            written: FakeWritten::default(),
             backend_tls_started: false,
             frontend_tls_started: false,
         }
@@ -74,24 +117,51 @@ impl TwoFakeStreams {
     }
 
     pub fn push_backend<Msg>(&mut self, body: Msg)
-    where Msg: 'static + MsgDecode + Send {
+    where Msg: 'static + MsgDecode + FakeBytes + Send {
         self.push(Backend, body)
     }
 
     pub fn push_frontend<Msg>(&mut self, body: Msg)
-    where Msg: 'static + MsgDecode + Send {
+    where Msg: 'static + MsgDecode + FakeBytes + Send {
         self.push(Frontend, body)
     }
 
+    pub fn push_backend_raw(&mut self, type_byte: u8, bytes: Vec<u8>) {
+        self.push_type_byte(Backend, type_byte);
+        self.push_data(Backend, Raw(bytes));
+    }
+
+    pub fn push_frontend_raw(&mut self, type_byte: u8, bytes: Vec<u8>) {
+        self.push_type_byte(Frontend, type_byte);
+        self.push_data(Frontend, Raw(bytes));
+    }
+
+    pub fn push_backend_relayed(&mut self, bytes: Vec<u8>) {
+        self.push_data(Backend, Raw(bytes));
+    }
+
+    pub fn push_frontend_relayed(&mut self, bytes: Vec<u8>) {
+        self.push_data(Frontend, Raw(bytes));
+    }
+
+    pub fn push_backend_undecodable(&mut self, type_byte: u8, bytes: Vec<u8>, error: MsgError) {
+        self.push_type_byte(Backend, type_byte);
+        self.push_data(Backend, Undecodable { bytes, error });
+    }
+
+    pub fn written(&self) -> FakeWritten {
+        self.written.clone()
+    }
+
     pub fn untaken(&mut self) -> VecDeque<TwoFakeStreamsItem> {
         let mut items = self.items.lock().unwrap();
         items.split_off(0)
     }
 
     fn push<Msg>(&mut self, side: FakeStreamSide, body: Msg)
-    where Msg: 'static + MsgDecode + Send {
+    where Msg: 'static + MsgDecode + FakeBytes + Send {
         if let Some(type_byte) = Msg::TYPE_BYTE_OPT {
-            self.push_type_byte(side, type_byte.into())
+            self.push_type_byte(side, type_byte)
         }
         self.push_body(side, body)
     }
@@ -101,8 +171,9 @@ impl TwoFakeStreams {
     }
 
     fn push_body<Msg>(&mut self, side: FakeStreamSide, body: Msg)
-    where Msg: 'static + MsgDecode + Send {
+    where Msg: 'static + MsgDecode + FakeBytes + Send {
         self.push_data(side, Body {
+            bytes: body.fake_bytes(),
             any: Box::new(body),
             type_name: type_name::<Msg>().split("::").last().unwrap(),
         })
@@ -122,11 +193,21 @@ impl TwoFakeStreams {
     }
 
     pub fn backend_stream(&mut self) -> FakeStream {
-        FakeStream { side: Backend, items: self.items.clone() }
+        FakeStream { side: Backend, items: self.items.clone(), written: self.written.backend.clone() }
     }
 
     pub fn frontend_stream(&mut self) -> FakeStream {
-        FakeStream { side: Frontend, items: self.items.clone() }
+        FakeStream { side: Frontend, items: self.items.clone(), written: self.written.frontend.clone() }
+    }
+}
+
+impl FakeWritten {
+    pub fn backend(&self) -> Vec<u8> {
+        self.backend.lock().unwrap().clone()
+    }
+
+    pub fn frontend(&self) -> Vec<u8> {
+        self.frontend.lock().unwrap().clone()
     }
 }
 
@@ -145,6 +226,14 @@ impl ConveyReader for FakeStream {
         impl_fake_read!(self, unwrap_not_encrypted, unwrap_msg_body)
     }
 
+    async fn read_raw(&mut self, _type_byte: u8) -> Result<Vec<u8>, ReadError> {
+        impl_fake_read!(self, unwrap_not_encrypted, unwrap_raw)
+    }
+
+    async fn read_some(&mut self) -> IoResult<Vec<u8>> {
+        impl_fake_read!(self, unwrap_not_encrypted, unwrap_relayed)
+    }
+
     async fn read_type_byte(&mut self) -> IoResult<u8> {
         impl_fake_read!(self, unwrap_not_encrypted, unwrap_type_byte)
     }
@@ -157,6 +246,14 @@ impl ConveyReader for FakeTlsStream<FakeStream> {
         impl_fake_read!(self.plain, unwrap_encrypted, unwrap_msg_body)
     }
 
+    async fn read_raw(&mut self, _type_byte: u8) -> Result<Vec<u8>, ReadError> {
+        impl_fake_read!(self.plain, unwrap_encrypted, unwrap_raw)
+    }
+
+    async fn read_some(&mut self) -> IoResult<Vec<u8>> {
+        impl_fake_read!(self.plain, unwrap_encrypted, unwrap_relayed)
+    }
+
     async fn read_type_byte(&mut self) -> IoResult<u8> {
         impl_fake_read!(self.plain, unwrap_encrypted, unwrap_type_byte)
     }
@@ -164,26 +261,45 @@ impl ConveyReader for FakeTlsStream<FakeStream> {
 
 #[async_trait]
 impl ConveyWriter for FakeStream {
-    async fn write_bytes(&mut self, _bytes: &[u8]) -> IoResult<()> {
-        Ok(())  // Just ignore the bytes for now
+    async fn write_bytes(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.written.lock().unwrap().extend_from_slice(bytes);
+        Ok(())
     }
 }
 
 #[async_trait]
 impl ConveyWriter for FakeTlsStream<FakeStream> {
-    async fn write_bytes(&mut self, _bytes: &[u8]) -> IoResult<()> {
-        Ok(())  // Just ignore the bytes for now
+    async fn write_bytes(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.plain.write_bytes(bytes).await
     }
 }
 
 fn unwrap_msg_body<Msg>(side: FakeStreamSide, data: IoResult<FakeData>) -> ReadResult<Msg>
 where Msg: 'static + MsgDecode {
     match data.map_err(ReadError::IoError)? {
-        Body { any, type_name } => {
+        Body { any, bytes, type_name } => {
             let msg: Box<Msg> = any.downcast().expect(&format!("fake {:?} gave a message of unexpected type {}", side, type_name));
-            Ok(ReadData { bytes: vec![], msg_result: Ok(*msg) })
+            Ok(ReadData { bytes, msg_result: Ok(*msg) })
         }
+        Undecodable { bytes, error } => Ok(ReadData { bytes, msg_result: Err(error) }),
         TypeByte(_) => panic!("fake {:?} gave a type byte instead of a message", side),
+        Raw(_) => panic!("fake {:?} gave raw bytes instead of a message", side),
+    }
+}
+
+fn unwrap_raw(side: FakeStreamSide, data: IoResult<FakeData>) -> Result<Vec<u8>, ReadError> {
+    match data.map_err(ReadError::IoError)? {
+        Raw(bytes) => Ok(bytes),
+        _ => panic!("fake {:?} gave something instead of raw bytes", side),
+    }
+}
+
+fn unwrap_relayed(side: FakeStreamSide, data: IoResult<FakeData>) -> IoResult<Vec<u8>> {
+    match data {
+        Ok(Raw(bytes)) => Ok(bytes),
+        Ok(_) => panic!("fake {:?} gave something instead of relayed bytes", side),
+        Err(error) if error.kind() == UnexpectedEof => Ok(vec![]),
+        Err(error) => Err(error),
     }
 }
 
@@ -191,6 +307,7 @@ fn unwrap_type_byte(side: FakeStreamSide, data: IoResult<FakeData>) -> IoResult<
     match data? {
         TypeByte(type_byte) => Ok(type_byte),
         Body { type_name, .. } => panic!("fake {:?} gave a {} message instead of a type byte", side, type_name),
+        Raw(_) | Undecodable { .. } => panic!("fake {:?} gave a message body instead of a type byte", side),
     }
 }
 
@@ -248,6 +365,8 @@ impl Debug for FakeData {
         match self {
             TypeByte(type_byte) => write!(f, "TypeByte('{}'={})", *type_byte as char, type_byte),
             Body { type_name, .. } => write!(f, "Body<{}>", type_name),
+            Raw(bytes) => write!(f, "Raw({:?})", bytes),
+            Undecodable { error, .. } => write!(f, "Undecodable({:?})", error),
         }
     }
 }
@@ -55,4 +55,11 @@ impl error::Error for FakeTlsError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         None
     }
-}
\ No newline at end of file
+}
+impl<Plain> TlsChannelBinding for FakeTlsStream<Plain> {
+    fn tls_server_end_point(&self) -> Option<Vec<u8>> {
+        Some(FAKE_TLS_SERVER_END_POINT.to_vec())
+    }
+}
+
+pub const FAKE_TLS_SERVER_END_POINT: &[u8] = b"fake certificate hash";
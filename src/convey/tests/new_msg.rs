@@ -61,7 +61,14 @@ pub mod backend_key_data {
     pub fn new(process_id: u32, secret_key: u32) -> BackendKeyData {
         BackendKeyData {
             process_id,
-            secret_key
+            secret_key: secret_key.to_be_bytes().to_vec(),
+        }
+    }
+
+    pub fn long_key(process_id: u32, secret_key_len: usize) -> BackendKeyData {
+        BackendKeyData {
+            process_id,
+            secret_key: vec![0x5a; secret_key_len],
         }
     }
 }
@@ -90,6 +97,28 @@ pub mod bind_complete {
     }
 }
 
+pub mod close {
+    use crate::msg::body::close::*;
+    export_wrapper!(FrontendMsg::Close);
+
+    pub fn portal(name: &'static str) -> Close {
+        Close::Portal(name.into())
+    }
+
+    pub fn prepared_statement(name: &'static str) -> Close {
+        Close::PreparedStatement(name.into())
+    }
+}
+
+pub mod close_complete {
+    use crate::msg::body::close_complete::*;
+    export_wrapper!(BackendMsg::CloseComplete);
+
+    pub fn new(_: ()) -> CloseComplete {
+        CloseComplete()
+    }
+}
+
 pub mod command_complete {
     use crate::msg::body::command_complete::*;
     export_wrapper!(BackendMsg::CommandComplete);
@@ -101,6 +130,75 @@ pub mod command_complete {
     }
 }
 
+pub mod copy_both_response {
+    use crate::msg::body::copy_responses::*;
+    use crate::msg::parts::Format;
+    export_wrapper!(BackendMsg::CopyBothResponse);
+
+    pub fn new(_: ()) -> CopyBothResponse {
+        CopyBothResponse(CopyFormats {
+            overall_format: Format::Binary,
+            columns_formats: vec![],
+        })
+    }
+}
+
+pub mod copy_data {
+    use crate::msg::body::copy_data::*;
+    use crate::msg::parts::Bytes;
+    export_wrapper!(BackendMsg::CopyData);
+    export_wrapper!(FrontendMsg::CopyData);
+
+    pub fn new(data: &'static str) -> CopyData {
+        CopyData(Bytes(data.into()))
+    }
+}
+
+pub mod copy_done {
+    use crate::msg::body::copy_done::*;
+    export_wrapper!(BackendMsg::CopyDone);
+    export_wrapper!(FrontendMsg::CopyDone);
+
+    pub fn new(_: ()) -> CopyDone {
+        CopyDone()
+    }
+}
+
+pub mod copy_fail {
+    use crate::msg::body::copy_fail::*;
+    export_wrapper!(FrontendMsg::CopyFail);
+
+    pub fn new(error_message: &'static str) -> CopyFail {
+        CopyFail { error_message: error_message.into() }
+    }
+}
+
+pub mod copy_in_response {
+    use crate::msg::body::copy_responses::*;
+    use crate::msg::parts::Format;
+    export_wrapper!(BackendMsg::CopyInResponse);
+
+    pub fn columns(count: usize) -> CopyInResponse {
+        CopyInResponse(CopyFormats {
+            overall_format: Format::Text,
+            columns_formats: vec![Format::Text; count],
+        })
+    }
+}
+
+pub mod copy_out_response {
+    use crate::msg::body::copy_responses::*;
+    use crate::msg::parts::Format;
+    export_wrapper!(BackendMsg::CopyOutResponse);
+
+    pub fn columns(count: usize) -> CopyOutResponse {
+        CopyOutResponse(CopyFormats {
+            overall_format: Format::Text,
+            columns_formats: vec![Format::Text; count],
+        })
+    }
+}
+
 pub mod data_row {
     use crate::msg::body::data_row::*;
     use crate::msg::parts::{Bytes, Value};
@@ -114,6 +212,19 @@ pub mod data_row {
     }
 }
 
+pub mod describe {
+    use crate::msg::body::describe::*;
+    export_wrapper!(FrontendMsg::Describe);
+
+    pub fn portal(name: &'static str) -> Describe {
+        Describe::Portal(name.into())
+    }
+
+    pub fn prepared_statement(name: &'static str) -> Describe {
+        Describe::PreparedStatement(name.into())
+    }
+}
+
 pub mod empty_query_response {
     use crate::msg::body::empty_query_response::*;
     export_wrapper!(BackendMsg::EmptyQueryResponse);
@@ -148,6 +259,42 @@ pub mod execute {
     }
 }
 
+pub mod flush {
+    use crate::msg::body::flush::*;
+    export_wrapper!(FrontendMsg::Flush);
+
+    pub fn new(_: ()) -> Flush {
+        Flush()
+    }
+}
+
+pub mod function_call {
+    use crate::msg::body::function_call::*;
+    use crate::msg::parts::{Bytes, Format, Value};
+    export_wrapper!(FrontendMsg::FunctionCall);
+
+    pub fn new(function_oid: u32, arguments: &[&'static [u8]]) -> FunctionCall {
+        FunctionCall {
+            function_oid,
+            arguments_formats: vec![Format::Binary],
+            arguments_values: arguments.iter().map(|arg| Value::Bytes(Bytes(arg.to_vec()))).collect(),
+            result_format: Format::Binary,
+        }
+    }
+}
+
+pub mod function_call_response {
+    use crate::msg::body::function_call_response::*;
+    use crate::msg::parts::{Bytes, Value};
+    export_wrapper!(BackendMsg::FunctionCallResponse);
+
+    pub fn new(result: &'static [u8]) -> FunctionCallResponse {
+        FunctionCallResponse {
+            result: Value::Bytes(Bytes(result.to_vec())),
+        }
+    }
+}
+
 pub mod gss_response {
     use crate::msg::body::gss_response::*;
     export_wrapper!(FrontendMsg::GssResponse);
@@ -157,6 +304,15 @@ pub mod gss_response {
     }
 }
 
+pub mod no_data {
+    use crate::msg::body::no_data::*;
+    export_wrapper!(BackendMsg::NoData);
+
+    pub fn new(_: ()) -> NoData {
+        NoData()
+    }
+}
+
 pub mod notice_response {
     use crate::msg::body::error_and_notice_responses::*;
     export_wrapper!(BackendMsg::NoticeResponse);
@@ -196,13 +352,24 @@ pub mod initial {
     pub fn cancel(process_id: u32, secret_key: u32) -> Initial {
         Initial::Cancel(Cancel {
             process_id,
-            secret_key,
+            secret_key: secret_key.to_be_bytes().to_vec(),
+        })
+    }
+
+    pub fn cancel_long_key(process_id: u32, secret_key_len: usize) -> Initial {
+        Initial::Cancel(Cancel {
+            process_id,
+            secret_key: vec![0x5a; secret_key_len],
         })
     }
 
     pub fn tls(_: ()) -> Initial {
         Initial::TLS
     }
+
+    pub fn gss_enc(_: ()) -> Initial {
+        Initial::GssEnc
+    }
 }
 
 pub mod negotiate_protocol_version {
@@ -218,6 +385,30 @@ pub mod negotiate_protocol_version {
     }
 }
 
+pub mod notification_response {
+    use crate::msg::body::notification_response::*;
+    export_wrapper!(BackendMsg::NotificationResponse);
+
+    pub fn new(channel: &'static str, payload: &'static str) -> NotificationResponse {
+        NotificationResponse {
+            process_id: 21,
+            channel: channel.into(),
+            payload: payload.into(),
+        }
+    }
+}
+
+pub mod parameter_description {
+    use crate::msg::body::parameter_description::*;
+    export_wrapper!(BackendMsg::ParameterDescription);
+
+    pub fn types(parameters_types: &[u32]) -> ParameterDescription {
+        ParameterDescription {
+            parameters_types: parameters_types.into(),
+        }
+    }
+}
+
 pub mod parameter_status {
     use crate::msg::body::parameter_status::*;
     export_wrapper!(BackendMsg::ParameterStatus);
@@ -241,6 +432,14 @@ pub mod parse {
             parameters_types: vec![],
         }
     }
+
+    pub fn query(query: &'static str) -> Parse {
+        Parse {
+            prepared_statement_name: "".into(),
+            query: query.into(),
+            parameters_types: vec![],
+        }
+    }
 }
 
 pub mod parse_complete {
@@ -286,6 +485,10 @@ pub mod ready_for_query {
     pub fn idle(_: ()) -> ReadyForQuery {
         ReadyForQuery { status: Status::Idle }
     }
+
+    pub fn in_transaction(_: ()) -> ReadyForQuery {
+        ReadyForQuery { status: Status::Transaction }
+    }
 }
 
 pub mod row_description {
@@ -317,6 +520,13 @@ pub mod sasl_initial_response {
             mechanism_data: None,
         }
     }
+
+    pub fn with_data(selected_mechanism: &'static str, mechanism_data: &'static str) -> SaslInitialResponse {
+        SaslInitialResponse {
+            selected_mechanism: selected_mechanism.into(),
+            mechanism_data: Some(mechanism_data.into()),
+        }
+    }
 }
 
 pub mod sasl_response {
@@ -346,4 +556,99 @@ pub mod terminate {
     pub fn new(_: ()) -> Terminate {
         Terminate {}
     }
-}
\ No newline at end of file
+}
+
+pub mod wal_receiver_msg {
+    use crate::msg::replication::*;
+    export_wrapper!(FrontendMsg::WalReceiverMsg);
+
+    pub fn standby_status_update(lsn: u64) -> WalReceiverMsg {
+        WalReceiverMsg::StandbyStatusUpdate(StandbyStatusUpdate {
+            written: Lsn(lsn),
+            flushed: Lsn(lsn),
+            applied: Lsn(lsn),
+            clock: 0,
+            reply_requested: false,
+        })
+    }
+
+    pub fn hot_standby_feedback(xmin: u32) -> WalReceiverMsg {
+        WalReceiverMsg::HotStandbyFeedback(HotStandbyFeedback {
+            clock: 0,
+            xmin,
+            xmin_epoch: 0,
+            catalog_xmin: 0,
+            catalog_xmin_epoch: 0,
+        })
+    }
+}
+
+pub mod wal_sender_msg {
+    use crate::msg::parts::Bytes;
+    use crate::msg::replication::*;
+    export_wrapper!(BackendMsg::WalSenderMsg);
+
+    pub fn xlog_data(start: u64, data: &'static str) -> WalSenderMsg {
+        WalSenderMsg::XLogData(XLogData {
+            start: Lsn(start),
+            wal_end: Lsn(start + data.len() as u64),
+            clock: 0,
+            data: Bytes(data.into()),
+        })
+    }
+
+    pub fn primary_keepalive(wal_end: u64) -> WalSenderMsg {
+        WalSenderMsg::PrimaryKeepalive(PrimaryKeepalive {
+            wal_end: Lsn(wal_end),
+            clock: 0,
+            reply_requested: true,
+        })
+    }
+}
+
+pub mod pgoutput_wal_sender_msg {
+    use crate::msg::replication::*;
+    use crate::msg::replication::pgoutput::*;
+    export_wrapper!(BackendMsg::PgOutputWalSenderMsg);
+
+    fn xlog_data(lsn: u64, data: PgOutputMsg) -> PgOutputWalSenderMsg {
+        WalSenderMsg::XLogData(XLogData {
+            start: Lsn(lsn),
+            wal_end: Lsn(lsn),
+            clock: 0,
+            data,
+        })
+    }
+
+    pub fn begin(xid: u32) -> PgOutputWalSenderMsg {
+        xlog_data(0, PgOutputMsg::Begin(Begin {
+            final_lsn: Lsn(0x100),
+            commit_time: 0,
+            xid,
+        }))
+    }
+
+    pub fn insert(relation_oid: u32) -> PgOutputWalSenderMsg {
+        xlog_data(0x80, PgOutputMsg::Insert(Insert {
+            relation_oid,
+            new_tuple: TupleData { columns: vec![] },
+        }))
+    }
+
+    pub fn commit(_: ()) -> PgOutputWalSenderMsg {
+        xlog_data(0x100, PgOutputMsg::Commit(Commit {
+            flags: 0,
+            commit_lsn: Lsn(0x100),
+            end_lsn: Lsn(0x108),
+            commit_time: 0,
+        }))
+    }
+
+    pub fn primary_keepalive(wal_end: u64) -> PgOutputWalSenderMsg {
+        WalSenderMsg::PrimaryKeepalive(PrimaryKeepalive {
+            wal_end: Lsn(wal_end),
+            clock: 0,
+            reply_requested: false,
+        })
+    }
+}
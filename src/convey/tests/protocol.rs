@@ -2,10 +2,30 @@ use super::fake_stream::{TwoFakeStreams};
 use super::fake_tls::*;
 use super::new_msg::*;
 
-use crate::convey::{ConveyError::*, ConveyResult, Conveyor, Message};
+use crate::auth::credentials::Credentials;
+use crate::auth::secrets::Secrets;
+use crate::firewall::Rules;
+use crate::convey::intercept::{decode_replacement, ForwardAll, Intercepted, Interceptor};
+use crate::convey::read_only_startup;
+use crate::convey::{BackendMsg, CaptureEvent, ConveyError::*, ConveyOptions, ConveyResult, Conveyor, FirewallEvent, FrontendMsg, Message, Opaque, OpaqueError, RelayEvent, State};
+use crate::msg::body::{Authentication, ErrorResponse, SaslInitialResponse};
+use crate::msg::body::initial::{Initial, StartupParam};
+use crate::msg::parts::{Bytes, Text};
+use crate::msg::scram;
+use crate::msg::type_byte::{BackendTypeByte, FrontendTypeByte, TypeByte};
+use crate::msg::util::decode::{MsgDecode, Problem as DecodeProblem};
+use crate::msg::util::encode::encode_msg;
+use crate::msg::util::read::MsgError;
+use crate::pcapng::PcapngWriter;
+use crate::record::{CaptureReader, Direction, Recorder};
 
 use ::async_std::task;
+use ::std::collections::HashMap;
+use ::std::convert::TryInto;
+use ::std::io::{self, Write};
 use ::std::iter::Iterator;
+use ::std::sync::Arc;
+use ::std::sync::atomic::{AtomicBool, Ordering};
 
 macro_rules! backend {
     (
@@ -31,6 +51,24 @@ macro_rules! frontend {
     }
 }
 
+macro_rules! encoded {
+    ( $( $msg:expr ),* $(,)? ) => {
+        vec![ $( encode_msg(&$msg).unwrap() ),* ]
+    }
+}
+
+macro_rules! frontend_ignored {
+    (
+        $module:ident::$func:ident( $( $arg:expr ),* ),
+        $conveyed:ident,
+        $fake_streams:ident
+    ) => {
+        $fake_streams.push_frontend($module::$func( $( $arg, )* ));
+        let msg_holder_ = $module::$func( $( $arg, )* );
+        $conveyed.push(Message::FrontendIgnored($module::FrontendMsg(&msg_holder_)));
+    }
+}
+
 #[test]
 fn cancel() {
     let mut streams = TwoFakeStreams::new();
@@ -39,6 +77,14 @@ fn cancel() {
     assert_ok!(test_convey(conveyed, streams));
 }
 
+#[test]
+fn cancel_with_long_key() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::cancel_long_key(11, 32), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
 #[test]
 fn cancel_when_backend_accepts_tls() {
     let mut streams = TwoFakeStreams::new();
@@ -61,6 +107,46 @@ fn cancel_when_backend_rejects_tls() {
     assert_ok!(test_convey(conveyed, streams));
 }
 
+#[test]
+fn gss_enc_declined_then_startup() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::gss_enc(()), conveyed, streams);
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn gss_enc_declined_then_tls() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::gss_enc(()), conveyed, streams);
+    frontend!(initial::tls(()), conveyed, streams);
+    streams.backend_accepts_tls();
+    streams.frontend_starts_tls();
+    frontend!(initial::cancel(11, 12), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn gss_enc_relayed() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::gss_enc(()), conveyed, streams);
+    let entered = RelayEvent::Entered { reason: "GSSAPI encryption".into() };
+    conveyed.push(Message::Relay(&entered));
+    streams.push_backend_relayed(b"G".to_vec());
+    streams.push_frontend_relayed(b"encrypted by GSSAPI".to_vec());
+    streams.push_backend_relayed(b"encrypted too".to_vec());
+    let finished = RelayEvent::Finished { backend_bytes: 14, frontend_bytes: 19 };
+    conveyed.push(Message::Relay(&finished));
+    assert_ok!(test_convey_relaying(conveyed, streams));
+}
+
 #[test]
 fn backend_does_not_know_tls() {
     let mut streams = TwoFakeStreams::new();
@@ -470,6 +556,296 @@ fn auth_sasl_quadriple_error() {
     backend!(error_response::new("something wrong"), conveyed, streams);
     assert_ok!(test_convey(conveyed, streams));
 }
+
+#[test]
+fn auth_scram_ok() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::sasl(&["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"]), conveyed, streams);
+    streams.push_frontend(sasl_initial_response::with_data("SCRAM-SHA-256-PLUS", "p=tls-server-end-point,,n=,r=abc"));
+    let client_first = scram::ClientFirst {
+        mechanism: scram::Mechanism::ScramSha256Plus,
+        gs2_header: scram::Gs2Header {
+            channel_binding: scram::ChannelBinding::Required(Text(Vec::from("tls-server-end-point"))),
+            authzid: None,
+        },
+        username: Text(vec![]),
+        nonce: Text(Vec::from("abc")),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFirst(&client_first)));
+    streams.push_backend(authentication::sasl_continue("r=abcdef,s=EjRW,i=4096"));
+    let server_first = scram::ServerFirst {
+        nonce: Text(Vec::from("abcdef")),
+        salt: Bytes(vec![0x12, 0x34, 0x56]),
+        iterations: 4096,
+    };
+    conveyed.push(Message::Backend(BackendMsg::ScramServerFirst(&server_first)));
+    streams.push_frontend(sasl_response::new("c=cD10bHMtc2VydmVyLWVuZC1wb2ludCws,r=abcdef,p=EjRW"));
+    let client_final = scram::ClientFinal {
+        channel_binding: Bytes(Vec::from("p=tls-server-end-point,,")),
+        nonce: Text(Vec::from("abcdef")),
+        proof: Bytes(vec![0x12, 0x34, 0x56]),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFinal(&client_final)));
+    streams.push_backend(authentication::sasl_final("v=VniR"));
+    let server_final = scram::ServerFinal::Verifier(Bytes(vec![0x56, 0x78, 0x91]));
+    conveyed.push(Message::Backend(BackendMsg::ScramServerFinal(&server_final)));
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(error_response::new("shorten test"), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn auth_scram_broken_server_first() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::sasl(&["SCRAM-SHA-256"]), conveyed, streams);
+    streams.push_frontend(sasl_initial_response::with_data("SCRAM-SHA-256", "n,,n=,r=abc"));
+    let client_first = scram::ClientFirst {
+        mechanism: scram::Mechanism::ScramSha256,
+        gs2_header: scram::Gs2Header {
+            channel_binding: scram::ChannelBinding::NotSupportedByClient,
+            authzid: None,
+        },
+        username: Text(vec![]),
+        nonce: Text(Vec::from("abc")),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFirst(&client_first)));
+    streams.push_backend(authentication::sasl_continue("r=abcdef"));
+    assert_matches!(test_convey(conveyed, streams), Err(DecodeError(DecodeProblem::Incorrect(_))));
+}
+
+#[test]
+fn auth_scram_terminated_plus_without_tls() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::sasl(&["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"]), conveyed, streams);
+    streams.push_frontend(sasl_initial_response::with_data("SCRAM-SHA-256-PLUS", "p=tls-server-end-point,,n=,r=abc"));
+    let client_first = scram::ClientFirst {
+        mechanism: scram::Mechanism::ScramSha256Plus,
+        gs2_header: scram::Gs2Header {
+            channel_binding: scram::ChannelBinding::Required(Text(Vec::from("tls-server-end-point"))),
+            authzid: None,
+        },
+        username: Text(vec![]),
+        nonce: Text(Vec::from("abc")),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFirst(&client_first)));
+    assert_matches!(test_convey_terminating_scram(conveyed, streams), Err(AuthFailed(_)));
+}
+
+#[test]
+fn auth_scram_terminated_wrong_nonce() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::sasl(&["SCRAM-SHA-256"]), conveyed, streams);
+    streams.push_frontend(sasl_initial_response::with_data("SCRAM-SHA-256", "n,,n=,r=abc"));
+    let client_first = scram::ClientFirst {
+        mechanism: scram::Mechanism::ScramSha256,
+        gs2_header: scram::Gs2Header {
+            channel_binding: scram::ChannelBinding::NotSupportedByClient,
+            authzid: None,
+        },
+        username: Text(vec![]),
+        nonce: Text(Vec::from("abc")),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFirst(&client_first)));
+    streams.push_frontend(sasl_response::new("c=biws,r=abcdef,p=EjRW"));
+    let client_final = scram::ClientFinal {
+        channel_binding: Bytes(Vec::from("n,,")),
+        nonce: Text(Vec::from("abcdef")),
+        proof: Bytes(vec![0x12, 0x34, 0x56]),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFinal(&client_final)));
+    assert_matches!(test_convey_terminating_scram(conveyed, streams), Err(AuthFailed(_)));
+}
+
+#[test]
+fn auth_scram_not_terminated_for_unknown_user() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "bob"}), conveyed, streams);
+    backend!(authentication::sasl(&["SCRAM-SHA-256"]), conveyed, streams);
+    frontend!(sasl_initial_response::new("OTP"), conveyed, streams);
+    backend!(error_response::new("shorten test"), conveyed, streams);
+    assert_ok!(test_convey_terminating_scram(conveyed, streams));
+}
+
+#[test]
+fn auth_local_unknown_user() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "bob"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    // bob is challenged like erin is and fails only after answering
+    streams.push_frontend(sasl_initial_response::with_data("SCRAM-SHA-256", "n,,n=,r=abc"));
+    let client_first = scram::ClientFirst {
+        mechanism: scram::Mechanism::ScramSha256,
+        gs2_header: scram::Gs2Header {
+            channel_binding: scram::ChannelBinding::NotSupportedByClient,
+            authzid: None,
+        },
+        username: Text(vec![]),
+        nonce: Text(Vec::from("abc")),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFirst(&client_first)));
+    streams.push_frontend(sasl_response::new("c=biws,r=abcdef,p=EjRW"));
+    let client_final = scram::ClientFinal {
+        channel_binding: Bytes(Vec::from("n,,")),
+        nonce: Text(Vec::from("abcdef")),
+        proof: Bytes(vec![0x12, 0x34, 0x56]),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFinal(&client_final)));
+    let written = streams.written();
+    assert_matches!(test_convey_authenticating_locally(conveyed, streams), Err(AuthFailed(_)));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RRE".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(encode_msg(&authentication::sasl(&["SCRAM-SHA-256"])).unwrap(), to_frontend[0]);
+    assert_matches!(decode_written(&to_frontend[1]), Authentication::SaslContinue { .. });
+    assert_invalid_password(&to_frontend[2]);
+    assert_eq!(encoded![initial::startup(11, 12, hashmap!{"user" => "bob"})], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn auth_local_md5_wrong_password() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::md5_password(&[1, 2, 3, 4]), conveyed, streams);
+    frontend!(password::new("md568be9ed08db75f318087ab337aaea044"), conveyed, streams);
+    let written = streams.written();
+    assert_matches!(test_convey_authenticating_locally(conveyed, streams), Err(AuthFailed(_)));
+    // frontend gets postgread's own challenge instead of backend's one, and its password goes nowhere
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RE".to_vec(), type_bytes(&to_frontend));
+    assert_matches!(decode_written(&to_frontend[0]), Authentication::Md5Password { .. });
+    assert_ne!(encode_msg(&authentication::md5_password(&[1, 2, 3, 4])).unwrap(), to_frontend[0]);
+    assert_invalid_password(&to_frontend[1]);
+    assert_eq!(encoded![initial::startup(11, 12, hashmap!{"user" => "alice"})], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn auth_local_scram_wrong_nonce() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "erin"}), conveyed, streams);
+    backend!(authentication::md5_password(&[1, 2, 3, 4]), conveyed, streams);
+    streams.push_frontend(sasl_initial_response::with_data("SCRAM-SHA-256", "n,,n=,r=abc"));
+    let client_first = scram::ClientFirst {
+        mechanism: scram::Mechanism::ScramSha256,
+        gs2_header: scram::Gs2Header {
+            channel_binding: scram::ChannelBinding::NotSupportedByClient,
+            authzid: None,
+        },
+        username: Text(vec![]),
+        nonce: Text(Vec::from("abc")),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFirst(&client_first)));
+    streams.push_frontend(sasl_response::new("c=biws,r=abcdef,p=EjRW"));
+    let client_final = scram::ClientFinal {
+        channel_binding: Bytes(Vec::from("n,,")),
+        nonce: Text(Vec::from("abcdef")),
+        proof: Bytes(vec![0x12, 0x34, 0x56]),
+    };
+    conveyed.push(Message::Frontend(FrontendMsg::ScramClientFinal(&client_final)));
+    let written = streams.written();
+    assert_matches!(test_convey_authenticating_locally(conveyed, streams), Err(AuthFailed(_)));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RRE".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(encode_msg(&authentication::sasl(&["SCRAM-SHA-256"])).unwrap(), to_frontend[0]);
+    assert_matches!(decode_written(&to_frontend[1]), Authentication::SaslContinue { .. });
+    assert_invalid_password(&to_frontend[2]);
+    assert_eq!(encoded![initial::startup(11, 12, hashmap!{"user" => "erin"})], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn auth_injected_md5() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::md5_password(&[1, 2, 3, 4]), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(error_response::new("shorten test"), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_injecting_credentials(conveyed, streams));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{"user" => "alice"}),
+        password::new("md5521dcd9b7854bbdd70fe48d9a74d6289"),
+    ], written_msgs(&written.backend(), 1));
+    assert_eq!(
+        encoded![authentication::ok(()), error_response::new("shorten test")],
+        written_msgs(&written.frontend(), 0),
+    );
+}
+
+#[test]
+fn auth_injected_cleartext_for_any_database() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "bob"}), conveyed, streams);
+    backend!(authentication::cleartext_password(()), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(error_response::new("shorten test"), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_injecting_credentials(conveyed, streams));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{"user" => "bob"}),
+        password::new("qwerty"),
+    ], written_msgs(&written.backend(), 1));
+    assert_eq!(
+        encoded![authentication::ok(()), error_response::new("shorten test")],
+        written_msgs(&written.frontend(), 0),
+    );
+}
+
+#[test]
+fn auth_injected_scram() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "bob"}), conveyed, streams);
+    backend!(authentication::sasl(&["SCRAM-SHA-256"]), conveyed, streams);
+    // the nonce of postgread is random so the fake backend refuses instead of continuing SCRAM
+    backend!(error_response::new("shorten test"), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_injecting_credentials(conveyed, streams));
+    let to_backend = written_msgs(&written.backend(), 1);
+    assert_eq!(2, to_backend.len());
+    let initial_response: SaslInitialResponse = decode_written(&to_backend[1]);
+    assert_eq!(b"SCRAM-SHA-256".to_vec(), initial_response.selected_mechanism);
+    assert!(initial_response.mechanism_data.unwrap().starts_with(b"n,,n=,r="));
+    assert_eq!(encoded![error_response::new("shorten test")], written_msgs(&written.frontend(), 0));
+}
+
+#[test]
+fn auth_injected_md5_hash_for_cleartext() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "carol"}), conveyed, streams);
+    backend!(authentication::cleartext_password(()), conveyed, streams);
+    let written = streams.written();
+    assert_matches!(test_convey_injecting_credentials(conveyed, streams), Err(AuthFailed(_)));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"E".to_vec(), type_bytes(&to_frontend));
+    assert_invalid_password(&to_frontend[0]);
+    assert_eq!(1, written_msgs(&written.backend(), 1).len());
+}
+
+#[test]
+fn auth_not_injected_for_unknown_database() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "dave"}), conveyed, streams);
+    backend!(authentication::cleartext_password(()), conveyed, streams);
+    frontend!(password::new("qwerty"), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(error_response::new("shorten test"), conveyed, streams);
+    assert_ok!(test_convey_injecting_credentials(conveyed, streams));
+}
+
 #[test]
 fn auth_kerberos_unsupported() {
     let mut streams = TwoFakeStreams::new();
@@ -488,6 +864,24 @@ fn auth_scm_credential_unsupported() {
     assert_matches!(test_convey(conveyed, streams), Err(Unsupported(_)));
 }
 
+#[test]
+fn scm_credential_relayed() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::scm_credential(()), conveyed, streams);
+    let entered = RelayEvent::Entered {
+        reason: "This message type is only issued by pre-9.1 servers. \
+            It may eventually be removed from the protocol specification.".into(),
+    };
+    conveyed.push(Message::Relay(&entered));
+    streams.push_frontend_relayed(b"credential".to_vec());
+    streams.push_backend_relayed(b"R\0\0\0\x08\0\0\0\0".to_vec());
+    let finished = RelayEvent::Finished { backend_bytes: 9, frontend_bytes: 10 };
+    conveyed.push(Message::Relay(&finished));
+    assert_ok!(test_convey_relaying(conveyed, streams));
+}
+
 #[test]
 fn negotiate_and_error_after_auth_ok() {
     let mut streams = TwoFakeStreams::new();
@@ -511,6 +905,63 @@ fn negotiate_before_and_after_auth_ok() {
     assert_ok!(test_convey(conveyed, streams));
 }
 
+#[test]
+fn protocol_3_2_long_secret_key() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 2, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::long_key(21, 32), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn protocol_3_2_negotiated_down_to_3_0() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 2, hashmap!{}), conveyed, streams);
+    backend!(negotiate_protocol_version::new(0, &[]), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn protocol_3_0_long_secret_key() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 2, hashmap!{}), conveyed, streams);
+    backend!(negotiate_protocol_version::new(0, &[]), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    streams.push_backend(backend_key_data::long_key(21, 32));
+    let written = streams.written();
+    assert_matches!(test_convey(conveyed, streams), Err(DecodeError(_)));
+    // frontend doesn't get the key
+    assert_eq!(b"vR".to_vec(), type_bytes(&written_msgs(&written.frontend(), 0)));
+}
+
+#[test]
+fn protocol_3_0_long_secret_key_lenient() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 2, hashmap!{}), conveyed, streams);
+    backend!(negotiate_protocol_version::new(0, &[]), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    streams.push_backend(backend_key_data::long_key(21, 32));
+    let opaque = Opaque {
+        bytes: encode_msg(&backend_key_data::long_key(21, 32)).unwrap(),
+        error: OpaqueError::DecodeError(DecodeProblem::Incorrect("secret key of 32 bytes in protocol 3.0".into())),
+    };
+    conveyed.push(Message::Backend(BackendMsg::Opaque(&opaque)));
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey_lenient(conveyed, streams));
+}
+
 #[test]
 fn error_after_param_status() {
     let mut streams = TwoFakeStreams::new();
@@ -583,6 +1034,56 @@ fn ready_and_terminate_with_notices() {
     assert_ok!(test_convey(conveyed, streams));
 }
 
+#[test]
+fn async_parameter_status() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(parameter_status::new("TimeZone", "UTC"), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(parameter_status::new("application_name", ""), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("set TimeZone = 'Europe/Moscow'"), conveyed, streams);
+    backend!(command_complete::new("SET"), conveyed, streams);
+    backend!(parameter_status::new("TimeZone", "Europe/Moscow"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(command_complete::new("SET"), conveyed, streams);
+    backend!(parameter_status::new("application_name", "psql"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn async_notifications() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("listen jobs"), conveyed, streams);
+    backend!(command_complete::new("LISTEN"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    backend!(notification_response::new("jobs", "1"), conveyed, streams);
+    backend!(notification_response::new("jobs", "2"), conveyed, streams);
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(row_description::fields(&["?column?"]), conveyed, streams);
+    backend!(notification_response::new("jobs", "3"), conveyed, streams);
+    backend!(data_row::columns(&[Some("1")]), conveyed, streams);
+    backend!(command_complete::new("SELECT 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
 #[test]
 fn empty_query() {
     let mut streams = TwoFakeStreams::new();
@@ -712,6 +1213,37 @@ fn multiple_queries_with_error_between() {
     assert_ok!(test_convey(conveyed, streams));
 }
 
+#[test]
+fn function_call() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(function_call::new(957, &[&[0, 0, 0x40, 0x01]]), conveyed, streams);
+    backend!(notice_response::new("first"), conveyed, streams);
+    backend!(function_call_response::new(&[0, 0, 0, 0]), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn function_call_error() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(function_call::new(952, &[b"/tmp/missing"]), conveyed, streams);
+    backend!(error_response::new("could not open server file \"/tmp/missing\""), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
 #[test]
 fn ext_query_parse_error() {
     let mut streams = TwoFakeStreams::new();
@@ -854,23 +1386,1255 @@ fn ext_query_execute_many_rows() {
     assert_ok!(test_convey(conveyed, streams));
 }
 
-fn test_convey(
-    expected_conveyed: Vec<Message>,
-    mut fake_streams: TwoFakeStreams,
-) -> ConveyResult<()> {
-    let mut expected_conveyed = expected_conveyed.iter();
-    let mut conveyor = Conveyor::new(
-        fake_streams.frontend_stream(),
-        fake_streams.backend_stream(),
-        FakeTlsServer(),
-        FakeTlsClient(),
-        |msg| { assert_eq!(expected_conveyed.next(), Some(&msg)) },
-    );
-    let convey_result = task::block_on(conveyor.go());
-    assert!(expected_conveyed.len() == 0,
-        "expected but not conveyed {:?}", expected_conveyed.collect::<Vec<_>>()
-    );
-    let unread = fake_streams.untaken();
-    assert!(unread.is_empty(), "untaken messages {:?}", unread);
-    convey_result
+#[test]
+fn ext_query_describe_statement() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    frontend!(describe::prepared_statement(""), conveyed, streams);
+    backend!(parameter_description::types(&[23, 25]), conveyed, streams);
+    backend!(row_description::fields(&["sum"]), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_describe_statement_without_rows() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    frontend!(describe::prepared_statement(""), conveyed, streams);
+    backend!(parameter_description::types(&[]), conveyed, streams);
+    backend!(no_data::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_describe_portal_and_execute() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    frontend!(describe::portal(""), conveyed, streams);
+    backend!(row_description::fields(&["sum"]), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    backend!(data_row::columns(&[Some("5")]), conveyed, streams);
+    backend!(command_complete::new("SELECT 1"), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_describe_error() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(describe::portal("missing"), conveyed, streams);
+    backend!(error_response::new("portal \"missing\" does not exist"), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_close() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(close::portal(""), conveyed, streams);
+    backend!(close_complete::new(()), conveyed, streams);
+    frontend!(close::prepared_statement("stmt"), conveyed, streams);
+    backend!(close_complete::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_pipeline() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(describe::portal(""), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(close::portal(""), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(row_description::fields(&["sum"]), conveyed, streams);
+    backend!(data_row::columns(&[Some("5")]), conveyed, streams);
+    backend!(command_complete::new("SELECT 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(command_complete::new("INSERT 1"), conveyed, streams);
+    backend!(close_complete::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_pipeline_with_flush() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(flush::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(data_row::columns(&[]), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    backend!(data_row::columns(&[]), conveyed, streams);
+    backend!(command_complete::new(""), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_pipeline_error_discards_till_sync() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(error_response::new("syntax error"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(command_complete::new(""), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_pipeline_error_before_sync() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(describe::prepared_statement(""), conveyed, streams);
+    backend!(error_response::new("syntax error"), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_error_ignores_till_sync() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    backend!(error_response::new("syntax error"), conveyed, streams);
+    frontend_ignored!(bind::new(()), conveyed, streams);
+    frontend_ignored!(describe::portal(""), conveyed, streams);
+    frontend_ignored!(execute::new(()), conveyed, streams);
+    frontend_ignored!(flush::new(()), conveyed, streams);
+    frontend_ignored!(query::new("select 1"), conveyed, streams);
+    frontend_ignored!(close::portal(""), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn ext_query_error_then_terminate() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    backend!(error_response::new("syntax error"), conveyed, streams);
+    frontend_ignored!(bind::new(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_in_simple_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("copy t1 from stdin"), conveyed, streams);
+    backend!(copy_in_response::columns(2), conveyed, streams);
+    frontend!(copy_data::new("1\tone\n"), conveyed, streams);
+    frontend!(copy_data::new("2\ttwo\n"), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("COPY 2"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_in_simple_query_fail() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("copy t1 from stdin"), conveyed, streams);
+    backend!(copy_in_response::columns(2), conveyed, streams);
+    frontend!(copy_data::new("1\tone\n"), conveyed, streams);
+    frontend!(copy_fail::new("canceled"), conveyed, streams);
+    backend!(error_response::new("COPY from stdin failed: canceled"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_out_simple_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("select 1; copy t1 to stdout"), conveyed, streams);
+    backend!(row_description::fields(&["?column?"]), conveyed, streams);
+    backend!(data_row::columns(&[Some("1")]), conveyed, streams);
+    backend!(command_complete::new("SELECT 1"), conveyed, streams);
+    backend!(copy_out_response::columns(2), conveyed, streams);
+    backend!(copy_data::new("1\tone\n"), conveyed, streams);
+    backend!(copy_data::new("2\ttwo\n"), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("COPY 2"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_both_simple_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("START_REPLICATION 0/0"), conveyed, streams);
+    backend!(copy_both_response::new(()), conveyed, streams);
+    backend!(copy_data::new("from backend"), conveyed, streams);
+    frontend!(copy_data::new("from frontend"), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    backend!(copy_data::new("from backend"), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("START_REPLICATION"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_in_extended_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(copy_in_response::columns(1), conveyed, streams);
+    frontend!(copy_data::new("1\n"), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(command_complete::new("COPY 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_in_extended_query_fail() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(copy_in_response::columns(1), conveyed, streams);
+    frontend!(copy_fail::new("canceled"), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(error_response::new("COPY from stdin failed: canceled"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn copy_out_extended_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::new(()), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(copy_out_response::columns(1), conveyed, streams);
+    backend!(copy_data::new("1\n"), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("COPY 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn physical_replication() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 0, hashmap!{"replication" => "true"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("IDENTIFY_SYSTEM"), conveyed, streams);
+    backend!(row_description::fields(&["systemid", "timeline", "xlogpos", "dbname"]), conveyed, streams);
+    backend!(data_row::columns(&[Some("7000000000000000000"), Some("1"), Some("0/1000000"), None]), conveyed, streams);
+    backend!(command_complete::new("IDENTIFY_SYSTEM"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("START_REPLICATION 0/1000000 TIMELINE 1"), conveyed, streams);
+    backend!(copy_both_response::new(()), conveyed, streams);
+    backend!(wal_sender_msg::xlog_data(0x1000000, "some WAL"), conveyed, streams);
+    backend!(wal_sender_msg::primary_keepalive(0x1000008), conveyed, streams);
+    frontend!(wal_receiver_msg::standby_status_update(0x1000008), conveyed, streams);
+    frontend!(wal_receiver_msg::hot_standby_feedback(753), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    backend!(wal_sender_msg::xlog_data(0x1000008, "more WAL"), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("START_REPLICATION"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn physical_replication_timeline_switch() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 0, hashmap!{"replication" => "true"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("START_REPLICATION 0/1000000 TIMELINE 1"), conveyed, streams);
+    backend!(copy_both_response::new(()), conveyed, streams);
+    backend!(wal_sender_msg::xlog_data(0x1000000, "end of timeline"), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    frontend!(wal_receiver_msg::standby_status_update(0x100000f), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    backend!(row_description::fields(&["next_tli", "next_tli_startpos"]), conveyed, streams);
+    backend!(data_row::columns(&[Some("2"), Some("0/100000F")]), conveyed, streams);
+    backend!(command_complete::new("SELECT"), conveyed, streams);
+    backend!(command_complete::new("START_STREAMING"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn logical_replication_pgoutput() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 0, hashmap!{"replication" => "database"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("START_REPLICATION SLOT \"sub\" LOGICAL 0/0 (proto_version '1', publication_names '\"pub\"')"), conveyed, streams);
+    backend!(copy_both_response::new(()), conveyed, streams);
+    backend!(pgoutput_wal_sender_msg::begin(753), conveyed, streams);
+    backend!(pgoutput_wal_sender_msg::insert(16385), conveyed, streams);
+    backend!(pgoutput_wal_sender_msg::commit(()), conveyed, streams);
+    backend!(pgoutput_wal_sender_msg::primary_keepalive(0x108), conveyed, streams);
+    frontend!(wal_receiver_msg::standby_status_update(0x108), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("COPY 0"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn logical_replication_other_plugin() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(3, 0, hashmap!{"replication" => "database"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("START_REPLICATION SLOT \"wal2json\" LOGICAL 0/0"), conveyed, streams);
+    backend!(copy_both_response::new(()), conveyed, streams);
+    backend!(wal_sender_msg::xlog_data(0x100, "{\"change\":[]}"), conveyed, streams);
+    frontend!(copy_done::new(()), conveyed, streams);
+    backend!(copy_done::new(()), conveyed, streams);
+    backend!(command_complete::new("COPY 0"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey(conveyed, streams));
+}
+
+#[test]
+fn lenient_unknown_type() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    let opaque = Opaque { bytes: vec![b'?', 0, 0, 0, 5, 1], error: OpaqueError::UnknownType(b'?') };
+    streams.push_backend_raw(b'?', opaque.bytes.clone());
+    conveyed.push(Message::Backend(BackendMsg::Opaque(&opaque)));
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey_lenient(conveyed, streams));
+}
+
+#[test]
+fn lenient_unexpected_type() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    let opaque = Opaque {
+        bytes: vec![b'd', 0, 0, 0, 5, b'x'],
+        error: OpaqueError::UnexpectedType(State::ReadyForQuery, None, TypeByte::Frontend(FrontendTypeByte::CopyData)),
+    };
+    streams.push_frontend_raw(b'd', opaque.bytes.clone());
+    conveyed.push(Message::Frontend(FrontendMsg::Opaque(&opaque)));
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey_lenient(conveyed, streams));
+}
+
+#[test]
+fn lenient_unexpected_authentication() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    let opaque = Opaque {
+        bytes: encode_msg(&authentication::ok(())).unwrap(),
+        error: OpaqueError::UnexpectedType(State::ReadyForQuery, None, TypeByte::Backend(BackendTypeByte::Authentication)),
+    };
+    // decoded as usual but conveyed as it is
+    streams.push_backend(authentication::ok(()));
+    conveyed.push(Message::Backend(BackendMsg::Opaque(&opaque)));
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_lenient(conveyed, streams));
+    assert_eq!(b"RKZRIZ".to_vec(), type_bytes(&written_msgs(&written.frontend(), 0)));
+}
+
+#[test]
+fn lenient_undecodable() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    let opaque = Opaque {
+        bytes: vec![b'N', 0, 0, 0, 6, b'M', b'x'],
+        error: OpaqueError::DecodeError(DecodeProblem::NoNullByte),
+    };
+    streams.push_backend_undecodable(b'N', opaque.bytes.clone(), MsgError::DecodeError(DecodeProblem::NoNullByte));
+    conveyed.push(Message::Backend(BackendMsg::Opaque(&opaque)));
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey_lenient(conveyed, streams));
+}
+
+#[test]
+fn lenient_left_undecoded() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    let opaque = Opaque {
+        bytes: vec![b'S', 0, 0, 0, 9, b'a', 0, b'b', 0, b'c'],
+        error: OpaqueError::LeftUndecoded(1),
+    };
+    streams.push_backend_undecodable(b'S', opaque.bytes.clone(), MsgError::LeftUndecoded(1));
+    conveyed.push(Message::Backend(BackendMsg::Opaque(&opaque)));
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey_lenient(conveyed, streams));
+}
+
+#[test]
+fn intercepted_and_replaced() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(describe::portal("p1"), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    // backend answers to the statement described instead of the portal
+    backend!(parameter_description::types(&[23]), conveyed, streams);
+    backend!(no_data::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let interceptor = InterceptFrontend(|msg: FrontendMsg| match msg {
+        FrontendMsg::Describe(_) => Intercepted::Replace(encode_msg(&describe::prepared_statement("s1")).unwrap()),
+        _ => Intercepted::Forward,
+    });
+    let written = streams.written();
+    assert_ok!(test_convey_intercepting(interceptor, conveyed, streams));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{}),
+        describe::prepared_statement("s1"),
+        sync::new(()),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn intercepted_and_replaced_with_another_type() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("select 1"), conveyed, streams);
+    let interceptor = InterceptFrontend(|msg: FrontendMsg| match msg {
+        FrontendMsg::Query(_) => Intercepted::Replace(encode_msg(&sync::new(())).unwrap()),
+        _ => Intercepted::Forward,
+    });
+    assert_matches!(test_convey_intercepting(interceptor, conveyed, streams), Err(Unsupported(_)));
+}
+
+#[test]
+fn intercepted_and_dropped() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    // conveying goes on since the first Terminate doesn't reach backend
+    frontend!(terminate::new(()), conveyed, streams);
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let mut terminated = false;
+    let interceptor = InterceptFrontend(move |msg: FrontendMsg| match msg {
+        FrontendMsg::Terminate(_) if !terminated => {
+            terminated = true;
+            Intercepted::Drop
+        },
+        _ => Intercepted::Forward,
+    });
+    let written = streams.written();
+    assert_ok!(test_convey_intercepting(interceptor, conveyed, streams));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{}),
+        query::new("select 1"),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn intercepted_and_responded() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("drop table t1"), conveyed, streams);
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let interceptor = InterceptFrontend(|msg: FrontendMsg| match msg {
+        FrontendMsg::Query(query) if query.0.starts_with(b"drop") => Intercepted::Respond(vec![
+            encode_msg(&error_response::new("denied")).unwrap(),
+            encode_msg(&ready_for_query::idle(())).unwrap(),
+        ]),
+        _ => Intercepted::Forward,
+    });
+    let written = streams.written();
+    assert_ok!(test_convey_intercepting(interceptor, conveyed, streams));
+    assert_eq!(encoded![
+        authentication::ok(()),
+        backend_key_data::new(21, 22),
+        ready_for_query::idle(()),
+        error_response::new("denied"),
+        ready_for_query::idle(()),
+        empty_query_response::new(()),
+        ready_for_query::idle(()),
+    ], written_msgs(&written.frontend(), 0));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{}),
+        query::new("select 1"),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn firewall_denied_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("DROP TABLE t1"), conveyed, streams);
+    let denied = FirewallEvent::Denied { rule: "deny * * keyword drop".into(), statement: "DROP TABLE t1".into() };
+    conveyed.push(Message::Firewall(&denied));
+    // backend doesn't answer since it hasn't got the query
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(row_description::fields(&["?column?"]), conveyed, streams);
+    backend!(data_row::columns(&[Some("1")]), conveyed, streams);
+    backend!(command_complete::new("SELECT 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_with_firewall(conveyed, streams));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZEZTDCZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"42501".to_vec()), error_code(&to_frontend[3]));
+    assert_eq!(ready_for_query::idle(()), decode_written(&to_frontend[4]));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{"user" => "alice"}),
+        query::new("select 1"),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn firewall_allowed_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "admin"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("drop table t1"), conveyed, streams);
+    let allowed = FirewallEvent::Allowed { rule: "allow admin * keyword drop".into(), statement: "drop table t1".into() };
+    conveyed.push(Message::Firewall(&allowed));
+    backend!(command_complete::new("DROP TABLE"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    assert_ok!(test_convey_with_firewall(conveyed, streams));
+}
+
+#[test]
+fn firewall_denied_prepared_statement() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::query("truncate t1"), conveyed, streams);
+    let denied = FirewallEvent::Denied { rule: "deny * * regex (?i)^truncate".into(), statement: "truncate t1".into() };
+    conveyed.push(Message::Firewall(&denied));
+    // the rest is dropped till Sync which is answered on behalf of backend
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_with_firewall(conveyed, streams));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZEZIZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"42501".to_vec()), error_code(&to_frontend[3]));
+    assert_eq!(ready_for_query::idle(()), decode_written(&to_frontend[4]));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{"user" => "alice"}),
+        query::new("select 1"),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn firewall_denied_query_in_pipeline() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("begin"), conveyed, streams);
+    frontend!(query::new("drop table t1"), conveyed, streams);
+    let denied = FirewallEvent::Denied { rule: "deny * * keyword drop".into(), statement: "drop table t1".into() };
+    conveyed.push(Message::Firewall(&denied));
+    backend!(command_complete::new("BEGIN"), conveyed, streams);
+    backend!(ready_for_query::in_transaction(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_with_firewall(conveyed, streams));
+    // the denial is answered after backend answers to BEGIN and keeps the transaction status
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZCZEZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"42501".to_vec()), error_code(&to_frontend[5]));
+    assert_eq!(ready_for_query::in_transaction(()), decode_written(&to_frontend[6]));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{"user" => "alice"}),
+        query::new("begin"),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn firewall_denied_prepared_statement_in_pipeline() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::query("insert into t1 values (1)"), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(parse::query("truncate t1"), conveyed, streams);
+    let denied = FirewallEvent::Denied { rule: "deny * * regex (?i)^truncate".into(), statement: "truncate t1".into() };
+    conveyed.push(Message::Firewall(&denied));
+    // backend answers to Sync since it has got the messages before the denied one
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parse_complete::new(()), conveyed, streams);
+    backend!(bind_complete::new(()), conveyed, streams);
+    backend!(command_complete::new("INSERT 0 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_with_firewall(conveyed, streams));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZ12CEZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"42501".to_vec()), error_code(&to_frontend[6]));
+    assert_eq!(encoded![
+        initial::startup(11, 12, hashmap!{"user" => "alice"}),
+        parse::query("insert into t1 values (1)"),
+        bind::new(()),
+        execute::new(()),
+        sync::new(()),
+        terminate::new(()),
+    ], written_msgs(&written.backend(), 1));
+}
+
+#[test]
+fn firewall_denied_prepared_statement_after_error() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::query("insert into t1 values (1)"), conveyed, streams);
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(parse::query("truncate t1"), conveyed, streams);
+    let denied = FirewallEvent::Denied { rule: "deny * * regex (?i)^truncate".into(), statement: "truncate t1".into() };
+    conveyed.push(Message::Firewall(&denied));
+    frontend!(sync::new(()), conveyed, streams);
+    // backend skips the rest till Sync so the denial isn't answered
+    backend!(error_response::new("relation t1 does not exist"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_with_firewall(conveyed, streams));
+    assert_eq!(b"RKZEZ".to_vec(), type_bytes(&written_msgs(&written.frontend(), 0)));
+}
+
+#[test]
+fn read_only_denied_query() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(query::new("set default_transaction_read_only = off"), conveyed, streams);
+    let denied = FirewallEvent::DeniedInReadOnly {
+        rule: r"deny * * code (?i)\b(set|reset)\b[^;]*\b(default_)?transaction_read_only\b".into(),
+        statement: "set default_transaction_read_only = off".into(),
+    };
+    conveyed.push(Message::Firewall(&denied));
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(empty_query_response::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_read_only(conveyed, streams));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZEZIZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"25006".to_vec()), error_code(&to_frontend[3]));
+    assert_eq!(ready_for_query::idle(()), decode_written(&to_frontend[4]));
+    let to_backend = written_msgs(&written.backend(), 1);
+    assert_read_only_startup(&to_backend[0]);
+    assert_eq!(encoded![query::new("select 1"), terminate::new(())], to_backend[1 ..].to_vec());
+}
+
+#[test]
+fn read_only_denied_prepared_statement() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(parse::query("insert into t1 values (1)"), conveyed, streams);
+    let denied = FirewallEvent::DeniedInReadOnly {
+        rule: "deny * * keyword insert".into(),
+        statement: "insert into t1 values (1)".into(),
+    };
+    conveyed.push(Message::Firewall(&denied));
+    frontend!(bind::new(()), conveyed, streams);
+    frontend!(execute::new(()), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_read_only(conveyed, streams));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZEZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"25006".to_vec()), error_code(&to_frontend[3]));
+    let to_backend = written_msgs(&written.backend(), 1);
+    assert_read_only_startup(&to_backend[0]);
+    assert_eq!(encoded![terminate::new(())], to_backend[1 ..].to_vec());
+}
+
+#[test]
+fn read_only_denied_function_call() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(function_call::new(957, &[&[0, 0, 0x40, 0x01]]), conveyed, streams);
+    let denied = FirewallEvent::DeniedInReadOnly {
+        rule: "deny * * FunctionCall".into(),
+        statement: "function call of OID 957".into(),
+    };
+    conveyed.push(Message::Firewall(&denied));
+    frontend!(terminate::new(()), conveyed, streams);
+    let written = streams.written();
+    assert_ok!(test_convey_read_only(conveyed, streams));
+    let to_frontend = written_msgs(&written.frontend(), 0);
+    assert_eq!(b"RKZEZ".to_vec(), type_bytes(&to_frontend));
+    assert_eq!(Some(b"25006".to_vec()), error_code(&to_frontend[3]));
+    assert_eq!(2, written_msgs(&written.backend(), 1).len());  // Startup and Terminate
+}
+
+#[test]
+fn read_only_startup_param() {
+    let startup = |params: HashMap<&'static str, &'static str>| match initial::startup(3, 0, params) {
+        Initial::Startup(startup) => startup,
+        _ => unreachable!(),
+    };
+    let expected = startup(hashmap!{"default_transaction_read_only" => "on"});
+    assert_eq!(expected, read_only_startup(&startup(hashmap!{})));
+    assert_eq!(expected, read_only_startup(&startup(hashmap!{"default_transaction_read_only" => "off"})));
+}
+
+#[test]
+fn recorded() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    let opaque = Opaque { bytes: b"\xff\0\0\0\x04".to_vec(), error: OpaqueError::UnknownType(0xff) };
+    streams.push_backend_raw(0xff, opaque.bytes.clone());
+    conveyed.push(Message::Backend(BackendMsg::Opaque(&opaque)));
+    frontend!(query::new("select 1"), conveyed, streams);
+    backend!(command_complete::new("SELECT 1"), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let path = std::env::temp_dir().join(format!("postgread-recorded-{}.cap", std::process::id()));
+    let path = path.to_str().unwrap();
+    let recorder = Arc::new(Recorder::create(path).unwrap());
+    let options = ConveyOptions { lenient: true, recording: Some(recorder.connection(7)), ..ConveyOptions::default() };
+    assert_ok!(test_convey_with(options, conveyed, streams));
+    drop(recorder);
+    let records: Vec<_> = CaptureReader::open(path).unwrap().collect::<Result<_, _>>().unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(records.iter().all(|record| record.connection_id == 7));
+    let directions: Vec<_> = records.iter().map(|record| record.direction).collect();
+    use Direction::*;
+    assert_eq!(
+        vec![FromFrontendInitial, FromBackend, FromBackend, FromBackend, FromBackend, FromFrontend, FromBackend, FromBackend, FromFrontend],
+        directions,
+    );
+    assert_eq!(Some(0xff), records[4].type_byte());
+}
+
+#[test]
+fn recording_failed() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    let stopped = CaptureEvent::RecordingStopped { error: "writer thread has stopped".into() };
+    conveyed.push(Message::Capture(&stopped));
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let disk = FullDisk::default();
+    let recorder = Arc::new(Recorder::new(disk.clone()).unwrap());
+    let recording = recorder.connection(7);
+    disk.0.store(true, Ordering::SeqCst);
+    // the writer thread stops at the next record
+    while recording.record(Direction::FromBackend, b"Z\0\0\0\x05I").is_ok() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    let options = ConveyOptions { recording: Some(recording), ..ConveyOptions::default() };
+    assert_ok!(test_convey_with(options, conveyed, streams));
+}
+
+#[test]
+fn captured_as_written() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{}), conveyed, streams);
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(describe::portal("p1"), conveyed, streams);
+    frontend!(sync::new(()), conveyed, streams);
+    backend!(parameter_description::types(&[23]), conveyed, streams);
+    backend!(no_data::new(()), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let interceptor = InterceptFrontend(|msg: FrontendMsg| match msg {
+        FrontendMsg::Describe(_) => Intercepted::Replace(encode_msg(&describe::prepared_statement("s1")).unwrap()),
+        _ => Intercepted::Forward,
+    });
+    let path = std::env::temp_dir().join(format!("postgread-captured-{}.pcapng", std::process::id()));
+    let path = path.to_str().unwrap();
+    let writer = Arc::new(PcapngWriter::create(path).unwrap());
+    let pcapng = writer.connection("10.0.0.1:40000".parse().unwrap(), "10.0.0.2:5432".parse().unwrap()).unwrap();
+    let options = ConveyOptions { pcapng: Some(pcapng), ..ConveyOptions::default() };
+    assert_ok!(test_convey_intercepting_with(options, interceptor, conveyed, streams));
+    drop(writer);
+    let captured = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let contains = |msg: Vec<u8>| captured.windows(msg.len()).any(|window| window == &msg[..]);
+    assert!(contains(encode_msg(&describe::prepared_statement("s1")).unwrap()));
+    assert!(!contains(encode_msg(&describe::portal("p1")).unwrap()));
+}
+
+#[test]
+fn capture_failed() {
+    let mut streams = TwoFakeStreams::new();
+    let mut conveyed = vec![];
+    frontend!(initial::startup(11, 12, hashmap!{"user" => "alice"}), conveyed, streams);
+    let stopped = CaptureEvent::PcapngStopped { error: "writer thread has stopped".into() };
+    conveyed.push(Message::Capture(&stopped));
+    backend!(authentication::ok(()), conveyed, streams);
+    backend!(backend_key_data::new(21, 22), conveyed, streams);
+    backend!(ready_for_query::idle(()), conveyed, streams);
+    frontend!(terminate::new(()), conveyed, streams);
+    let disk = FullDisk::default();
+    let writer = Arc::new(PcapngWriter::new(disk.clone()).unwrap());
+    let pcapng = writer.connection("10.0.0.1:40000".parse().unwrap(), "10.0.0.2:5432".parse().unwrap()).unwrap();
+    disk.0.store(true, Ordering::SeqCst);
+    // the writer thread stops at the next packet
+    while pcapng.sent_to_client(b"Z\0\0\0\x05I").is_ok() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    let options = ConveyOptions { pcapng: Some(pcapng), ..ConveyOptions::default() };
+    let written = streams.written();
+    assert_ok!(test_convey_with(options, conveyed, streams));
+    assert_eq!(b"RKZ".to_vec(), type_bytes(&written_msgs(&written.frontend(), 0)));
+}
+
+// Fails writes once it's full.
+#[derive(Clone, Default)]
+struct FullDisk(Arc<AtomicBool>);
+
+impl Write for FullDisk {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0.load(Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "disk is full"))
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct InterceptFrontend<Intercept>(Intercept);
+
+impl<Intercept> Interceptor for InterceptFrontend<Intercept>
+where Intercept: FnMut(FrontendMsg) -> Intercepted + Send {
+    fn intercept_frontend(&mut self, msg: FrontendMsg) -> Intercepted {
+        (self.0)(msg)
+    }
+}
+
+fn test_convey(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    test_convey_with(ConveyOptions::default(), expected_conveyed, fake_streams)
+}
+
+fn test_convey_lenient(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    test_convey_with(ConveyOptions { lenient: true, ..ConveyOptions::default() }, expected_conveyed, fake_streams)
+}
+
+fn test_convey_relaying(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    test_convey_with(ConveyOptions { relay_fallback: true, ..ConveyOptions::default() }, expected_conveyed, fake_streams)
+}
+
+fn test_convey_terminating_scram(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    let secrets = Secrets::parse("alice SCRAM-SHA-256$4096:W22ZaJ0SNY7soEsUEjb6gQ==$\
+        WG5d8oPm3OtcPnkdi4Uo7BkeZkBFzpcXkuLmtbsT4qY=:wfPLwcE6nTWhTAmQ7tl2KeoiWGPlZqQxSrmfPwDl2dU=").unwrap();
+    let options = ConveyOptions { scram_secrets: Some(Arc::new(secrets)), ..ConveyOptions::default() };
+    test_convey_with(options, expected_conveyed, fake_streams)
+}
+
+fn test_convey_authenticating_locally(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    let secrets = Secrets::parse("\
+        alice md53175bce1d3201d16594cebf9d7eb3f9d\n\
+        erin SCRAM-SHA-256$4096:W22ZaJ0SNY7soEsUEjb6gQ==$\
+            WG5d8oPm3OtcPnkdi4Uo7BkeZkBFzpcXkuLmtbsT4qY=:wfPLwcE6nTWhTAmQ7tl2KeoiWGPlZqQxSrmfPwDl2dU=\n\
+    ").unwrap();
+    let options = ConveyOptions { local_auth: Some(Arc::new(secrets)), ..ConveyOptions::default() };
+    test_convey_with(options, expected_conveyed, fake_streams)
+}
+
+fn test_convey_injecting_credentials(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    let credentials = Credentials::parse("\
+        alice alice qwerty\n\
+        bob * qwerty\n\
+        carol carol md53175bce1d3201d16594cebf9d7eb3f9d\n\
+        dave prod qwerty\n\
+    ").unwrap();
+    let options = ConveyOptions { backend_credentials: Some(Arc::new(credentials)), ..ConveyOptions::default() };
+    test_convey_with(options, expected_conveyed, fake_streams)
+}
+
+fn test_convey_with_firewall(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    let rules = Rules::parse("\
+        allow admin * keyword drop\n\
+        deny * * keyword drop\n\
+        deny * * regex (?i)^truncate\n\
+    ").unwrap();
+    let options = ConveyOptions { firewall: Some(Arc::new(rules)), ..ConveyOptions::default() };
+    test_convey_with(options, expected_conveyed, fake_streams)
+}
+
+fn test_convey_read_only(
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    let options = ConveyOptions { read_only: Some(Arc::new(Rules::read_only())), ..ConveyOptions::default() };
+    test_convey_with(options, expected_conveyed, fake_streams)
+}
+
+fn test_convey_intercepting(
+    interceptor: impl Interceptor,
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    test_convey_intercepting_with(ConveyOptions::default(), interceptor, expected_conveyed, fake_streams)
+}
+
+fn test_convey_with(
+    options: ConveyOptions,
+    expected_conveyed: Vec<Message>,
+    fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    test_convey_intercepting_with(options, ForwardAll, expected_conveyed, fake_streams)
+}
+
+fn test_convey_intercepting_with(
+    options: ConveyOptions,
+    interceptor: impl Interceptor,
+    expected_conveyed: Vec<Message>,
+    mut fake_streams: TwoFakeStreams,
+) -> ConveyResult<()> {
+    let mut expected_conveyed = expected_conveyed.iter();
+    let mut conveyor = Conveyor::new(
+        fake_streams.frontend_stream(),
+        fake_streams.backend_stream(),
+        FakeTlsServer(),
+        FakeTlsClient(),
+        options,
+        interceptor,
+        |msg| { assert_eq!(expected_conveyed.next(), Some(&msg)) },
+    );
+    let convey_result = task::block_on(conveyor.go());
+    assert!(expected_conveyed.len() == 0,
+        "expected but not conveyed {:?}", expected_conveyed.collect::<Vec<_>>()
+    );
+    let unread = fake_streams.untaken();
+    assert!(unread.is_empty(), "untaken messages {:?}", unread);
+    convey_result
+}
+
+// Splits bytes written to one side into messages, the first `initial` ones go without a type byte.
+fn written_msgs(mut bytes: &[u8], initial: usize) -> Vec<Vec<u8>> {
+    let mut msgs = vec![];
+    while !bytes.is_empty() {
+        let len_at = if msgs.len() < initial { 0 } else { 1 };
+        let len = u32::from_be_bytes(bytes[len_at..len_at + 4].try_into().unwrap()) as usize;
+        let (msg, rest) = bytes.split_at(len_at + len);
+        msgs.push(msg.to_vec());
+        bytes = rest;
+    }
+    msgs
+}
+
+fn type_bytes(msgs: &[Vec<u8>]) -> Vec<u8> {
+    msgs.iter().map(|msg| msg[0]).collect()
+}
+
+fn decode_written<Msg: MsgDecode>(msg: &[u8]) -> Msg {
+    decode_replacement(msg).unwrap()
+}
+
+fn error_code(msg: &[u8]) -> Option<Vec<u8>> {
+    decode_written::<ErrorResponse>(msg).0.code
+}
+
+fn assert_read_only_startup(msg: &[u8]) {
+    match decode_written(msg) {
+        Initial::Startup(startup) => assert_eq!(
+            vec![
+                StartupParam::new(b"user".to_vec(), b"alice".to_vec()),
+                StartupParam::new(b"default_transaction_read_only".to_vec(), b"on".to_vec()),
+            ],
+            startup.params,
+        ),
+        initial => panic!("unexpected {:?}", initial),
+    }
+}
+
+fn assert_invalid_password(msg: &[u8]) {
+    let error: ErrorResponse = decode_written(msg);
+    assert_eq!(Some(b"FATAL".to_vec()), error.0.severity);
+    assert_eq!(Some(b"28P01".to_vec()), error.0.code);
 }
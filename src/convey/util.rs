@@ -1,10 +1,16 @@
 use crate::msg::body::*;
-use crate::convey::{Message, BackendMsg, FrontendMsg};
+use crate::msg::replication::{PgOutputWalSenderMsg, WalReceiverMsg, WalSenderMsg};
+use crate::msg::scram::{ClientFinal, ClientFirst, ServerFinal, ServerFirst};
+use crate::convey::{Message, BackendMsg, CaptureEvent, FirewallEvent, FrontendMsg, Opaque, RelayEvent};
 
 #[derive(Debug, PartialEq)]
 pub enum MessageClone {
     Backend(BackendMsgClone),
     Frontend(FrontendMsgClone),
+    FrontendIgnored(FrontendMsgClone),
+    Firewall(FirewallEvent),
+    Relay(RelayEvent),
+    Capture(CaptureEvent),
 }
 
 #[derive(Debug, PartialEq)]
@@ -12,32 +18,58 @@ pub enum BackendMsgClone {
     Authentication(Authentication),
     BackendKeyData(BackendKeyData),
     BindComplete(BindComplete),
+    CloseComplete(CloseComplete),
     CommandComplete(CommandComplete),
+    CopyBothResponse(CopyBothResponse),
+    CopyData(CopyData),
+    CopyDone(CopyDone),
+    CopyInResponse(CopyInResponse),
+    CopyOutResponse(CopyOutResponse),
     DataRow(DataRow),
     EmptyQueryResponse(EmptyQueryResponse),
     ErrorResponse(ErrorResponse),
+    FunctionCallResponse(FunctionCallResponse),
     NegotiateProtocolVersion(NegotiateProtocolVersion),
+    NoData(NoData),
     NoticeResponse(NoticeResponse),
+    NotificationResponse(NotificationResponse),
+    Opaque(Opaque),
+    ParameterDescription(ParameterDescription),
     ParameterStatus(ParameterStatus),
     ParseComplete(ParseComplete),
+    PgOutputWalSenderMsg(PgOutputWalSenderMsg),
     PortalSuspended(PortalSuspended),
     ReadyForQuery(ReadyForQuery),
     RowDescription(RowDescription),
+    ScramServerFinal(ServerFinal),
+    ScramServerFirst(ServerFirst),
+    WalSenderMsg(WalSenderMsg),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum FrontendMsgClone {
     Bind(Bind),
+    Close(Close),
+    CopyData(CopyData),
+    CopyDone(CopyDone),
+    CopyFail(CopyFail),
+    Describe(Describe),
     Execute(Execute),
+    Flush(Flush),
+    FunctionCall(FunctionCall),
     GssResponse(GssResponse),
     Initial(Initial),
+    Opaque(Opaque),
     Parse(Parse),
     Password(Password),
     Query(Query),
     SaslInitialResponse(SaslInitialResponse),
     SaslResponse(SaslResponse),
+    ScramClientFinal(ClientFinal),
+    ScramClientFirst(ClientFirst),
     Sync(Sync),
     Terminate(Terminate),
+    WalReceiverMsg(WalReceiverMsg),
 }
 
 impl MessageClone {
@@ -47,6 +79,10 @@ impl MessageClone {
         match refer {
             Ref::Backend(refer) => Backend(BackendMsgClone::make(refer)),
             Ref::Frontend(refer) => Frontend(FrontendMsgClone::make(refer)),
+            Ref::FrontendIgnored(refer) => FrontendIgnored(FrontendMsgClone::make(refer)),
+            Ref::Firewall(refer) => Firewall((*refer).clone()),
+            Ref::Relay(refer) => Relay((*refer).clone()),
+            Ref::Capture(refer) => Capture((*refer).clone()),
         }
     }
 }
@@ -59,17 +95,32 @@ impl BackendMsgClone {
             Ref::Authentication(refer) => Authentication((*refer).clone()),
             Ref::BackendKeyData(refer) => BackendKeyData((*refer).clone()),
             Ref::BindComplete(refer) => BindComplete((*refer).clone()),
+            Ref::CloseComplete(refer) => CloseComplete((*refer).clone()),
             Ref::CommandComplete(refer) => CommandComplete((*refer).clone()),
+            Ref::CopyBothResponse(refer) => CopyBothResponse((*refer).clone()),
+            Ref::CopyData(refer) => CopyData((*refer).clone()),
+            Ref::CopyDone(refer) => CopyDone((*refer).clone()),
+            Ref::CopyInResponse(refer) => CopyInResponse((*refer).clone()),
+            Ref::CopyOutResponse(refer) => CopyOutResponse((*refer).clone()),
             Ref::DataRow(refer) => DataRow((*refer).clone()),
             Ref::EmptyQueryResponse(refer) => EmptyQueryResponse((*refer).clone()),
             Ref::ErrorResponse(refer) => ErrorResponse((*refer).clone()),
+            Ref::FunctionCallResponse(refer) => FunctionCallResponse((*refer).clone()),
             Ref::NegotiateProtocolVersion(refer) => NegotiateProtocolVersion((*refer).clone()),
+            Ref::NoData(refer) => NoData((*refer).clone()),
             Ref::NoticeResponse(refer) => NoticeResponse((*refer).clone()),
+            Ref::NotificationResponse(refer) => NotificationResponse((*refer).clone()),
+            Ref::Opaque(refer) => Opaque((*refer).clone()),
+            Ref::ParameterDescription(refer) => ParameterDescription((*refer).clone()),
             Ref::ParameterStatus(refer) => ParameterStatus((*refer).clone()),
             Ref::ParseComplete(refer) => ParseComplete((*refer).clone()),
+            Ref::PgOutputWalSenderMsg(refer) => PgOutputWalSenderMsg((*refer).clone()),
             Ref::PortalSuspended(refer) => PortalSuspended((*refer).clone()),
             Ref::ReadyForQuery(refer) => ReadyForQuery((*refer).clone()),
             Ref::RowDescription(refer) => RowDescription((*refer).clone()),
+            Ref::ScramServerFinal(refer) => ScramServerFinal((*refer).clone()),
+            Ref::ScramServerFirst(refer) => ScramServerFirst((*refer).clone()),
+            Ref::WalSenderMsg(refer) => WalSenderMsg((*refer).clone()),
         }
     }
 }
@@ -80,16 +131,27 @@ impl FrontendMsgClone {
         use FrontendMsgClone::*;
         match refer {
             Ref::Bind(refer) => Bind((*refer).clone()),
+            Ref::Close(refer) => Close((*refer).clone()),
+            Ref::CopyData(refer) => CopyData((*refer).clone()),
+            Ref::CopyDone(refer) => CopyDone((*refer).clone()),
+            Ref::CopyFail(refer) => CopyFail((*refer).clone()),
+            Ref::Describe(refer) => Describe((*refer).clone()),
             Ref::Execute(refer) => Execute((*refer).clone()),
+            Ref::Flush(refer) => Flush((*refer).clone()),
+            Ref::FunctionCall(refer) => FunctionCall((*refer).clone()),
             Ref::GssResponse(refer) => GssResponse((*refer).clone()),
             Ref::Initial(refer) => Initial((*refer).clone()),
+            Ref::Opaque(refer) => Opaque((*refer).clone()),
             Ref::Parse(refer) => Parse((*refer).clone()),
             Ref::Password(refer) => Password((*refer).clone()),
             Ref::Query(refer) => Query((*refer).clone()),
             Ref::SaslInitialResponse(refer) => SaslInitialResponse((*refer).clone()),
             Ref::SaslResponse(refer) => SaslResponse((*refer).clone()),
+            Ref::ScramClientFinal(refer) => ScramClientFinal((*refer).clone()),
+            Ref::ScramClientFirst(refer) => ScramClientFirst((*refer).clone()),
             Ref::Sync(refer) => Sync((*refer).clone()),
             Ref::Terminate(refer) => Terminate((*refer).clone()),
+            Ref::WalReceiverMsg(refer) => WalReceiverMsg((*refer).clone()),
         }        
     }
 }
\ No newline at end of file
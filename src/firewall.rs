@@ -0,0 +1,377 @@
+use crate::auth::secrets::{parse_lines, take_word};
+
+use ::regex::bytes::Regex;
+use ::std::fmt::{self, Debug, Display, Formatter};
+use ::std::fs;
+use ::std::io;
+
+// Lines like "allow|deny" "username" "database" "keyword|regex|code" pattern where "*" stands for any user or database.
+// The first rule matching a statement decides, a statement matching no rule is allowed.
+// Keywords are whole words of the statement compared case-insensitively, "drop table" matches them in a row.
+// They aren't looked for in string literals, quoted identifiers and comments.
+// A regex is searched in the statement as is, literals and comments included, use (?i) to ignore case.
+// A code regex is searched in the statement whose literals and comments are replaced with a space each
+// and whose quoted identifiers are unquoted, so "set_config" /**/ ( reads as set_config  (.
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+pub struct Rule {
+    pub action: Action,
+    user: Option<Vec<u8>>,  // None for any
+    database: Option<Vec<u8>>,  // None for any
+    pattern: Pattern,
+    line: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+enum Pattern {
+    Keywords(Vec<Vec<u8>>),
+    Regex(Regex),
+    Code(Regex),
+}
+
+impl Rules {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, err)))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Ok(Self { rules: parse_lines(text, Rule::parse)? })
+    }
+
+    // Statements which write or make the session writable despite default_transaction_read_only=on.
+    // Backend rejects writes in read-only transactions anyway, these rules catch them earlier.
+    pub fn read_only() -> Self {
+        Self::parse(READ_ONLY_RULES).expect("read-only rules are valid")
+    }
+
+    pub fn check(&self, user: &[u8], database: &[u8], statement: &[u8]) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(user, database, statement))
+    }
+}
+
+const READ_ONLY_RULES: &str = r#"
+deny * * code (?i)\b(set|reset)\b[^;]*\b(default_)?transaction_read_only\b
+deny * * code (?i)\bset_config\s*\(
+deny * * keyword read write
+deny * * keyword reset all
+deny * * code (?i)(^|;)\s*do\b
+deny * * code (?i)\bcopy\s+[\w.]+\s*(\([^)]*\))?\s*from\b
+deny * * keyword insert
+deny * * keyword update
+deny * * keyword delete
+deny * * keyword merge
+deny * * keyword truncate
+deny * * keyword create
+deny * * keyword alter
+deny * * keyword drop
+deny * * keyword grant
+deny * * keyword revoke
+deny * * keyword comment on
+deny * * keyword security label
+deny * * keyword vacuum
+deny * * keyword cluster
+deny * * keyword reindex
+deny * * keyword refresh materialized view
+deny * * keyword call
+"#;
+
+impl Rule {
+    fn parse(line: &str) -> Result<Self, String> {
+        let (action, rest) = take_word(line, "action")?;
+        let (user, rest) = take_word(rest, "user")?;
+        let (database, rest) = take_word(rest, "database")?;
+        let (kind, rest) = take_word(rest, "pattern kind")?;
+        let action = match action.as_str() {
+            "allow" => Action::Allow,
+            "deny" => Action::Deny,
+            _ => return Err(format!("action {:?} is neither allow nor deny", action)),
+        };
+        let pattern = match (kind.as_str(), rest.trim()) {
+            (_, "") => return Err("pattern expected".into()),
+            ("keyword", words) => Pattern::Keywords(words.split_whitespace().map(|word| word.as_bytes().to_vec()).collect()),
+            ("regex", regex) => Pattern::Regex(Regex::new(regex).map_err(|err| err.to_string())?),
+            ("code", regex) => Pattern::Code(Regex::new(regex).map_err(|err| err.to_string())?),
+            _ => return Err(format!("pattern kind {:?} is neither keyword, regex nor code", kind)),
+        };
+        Ok(Self {
+            action,
+            user: any_or(user),
+            database: any_or(database),
+            pattern,
+            line: line.into(),
+        })
+    }
+
+    fn matches(&self, user: &[u8], database: &[u8], statement: &[u8]) -> bool {
+        self.user.iter().all(|rule_user| rule_user == user)
+            && self.database.iter().all(|rule_database| rule_database == database)
+            && self.pattern.matches(statement)
+    }
+}
+
+impl Pattern {
+    fn matches(&self, statement: &[u8]) -> bool {
+        match self {
+            Self::Keywords(keywords) => {
+                // standard_conforming_strings=off makes a backslash escape a quote in any literal,
+                // the setting is unknown here so the keywords are looked for in both readings
+                [false, true].iter().any(|backslash_escapes| {
+                    let statement = strip_quoted(statement, *backslash_escapes, false);
+                    let words: Vec<_> = statement
+                        .split(|byte| !(byte.is_ascii_alphanumeric() || *byte == b'_'))
+                        .filter(|word| !word.is_empty())
+                        .collect();
+                    words.windows(keywords.len()).any(|window| {
+                        window.iter().zip(keywords).all(|(word, keyword)| word.eq_ignore_ascii_case(keyword))
+                    })
+                })
+            },
+            Self::Regex(regex) => regex.is_match(statement),
+            Self::Code(regex) => [false, true].iter().any(|backslash_escapes| {
+                regex.is_match(&strip_quoted(statement, *backslash_escapes, true))
+            }),
+        }
+    }
+}
+
+// Replaces string literals, quoted identifiers and comments with a space each, or unquotes the identifiers.
+// An unclosed one lasts till the end as backend fails the statement anyway.
+fn strip_quoted(statement: &[u8], backslash_escapes: bool, unquote_identifiers: bool) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(statement.len());
+    let mut pos = 0;
+    while pos < statement.len() {
+        let rest = &statement[pos ..];
+        let after_word = pos > 0 && is_identifier_byte(statement[pos - 1]);
+        let quoted_len = match rest[0] {
+            b'\'' => {
+                let escape_string = after_word && statement[pos - 1].eq_ignore_ascii_case(&b'e')
+                    && !(pos > 1 && is_identifier_byte(statement[pos - 2]));
+                quoted_len(rest, b'\'', backslash_escapes || escape_string)
+            },
+            b'"' if unquote_identifiers => {
+                let len = quoted_len(rest, b'"', false);
+                let end = if len > 1 && rest[len - 1] == b'"' { len - 1 } else { len };
+                stripped.extend_from_slice(&rest[1 .. end]);
+                pos += len;
+                continue
+            },
+            b'"' => quoted_len(rest, b'"', false),
+            b'-' if rest.starts_with(b"--") => rest.iter().position(|byte| *byte == b'\n').unwrap_or(rest.len()),
+            b'/' if rest.starts_with(b"/*") => block_comment_len(rest),
+            b'$' if !after_word => dollar_quoted_len(rest).unwrap_or(0),
+            _ => 0,
+        };
+        if quoted_len == 0 {
+            stripped.push(rest[0]);
+            pos += 1;
+        } else {
+            stripped.push(b' ');
+            pos += quoted_len;
+        }
+    }
+    stripped
+}
+
+fn quoted_len(text: &[u8], quote: u8, backslash_escapes: bool) -> usize {
+    let mut pos = 1;
+    while pos < text.len() {
+        match text[pos] {
+            b'\\' if backslash_escapes => pos += 2,
+            byte if byte == quote && text.get(pos + 1) == Some(&quote) => pos += 2,  // doubled
+            byte if byte == quote => return pos + 1,
+            _ => pos += 1,
+        }
+    }
+    text.len()
+}
+
+fn block_comment_len(text: &[u8]) -> usize {
+    let mut depth = 0;
+    let mut pos = 0;
+    while pos < text.len() {
+        if text[pos ..].starts_with(b"/*") {
+            depth += 1;
+            pos += 2;
+        } else if text[pos ..].starts_with(b"*/") {
+            depth -= 1;
+            pos += 2;
+            if depth == 0 {
+                return pos
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    text.len()
+}
+
+// $tag$...$tag$ where the tag may be empty, None for a parameter like $1.
+fn dollar_quoted_len(text: &[u8]) -> Option<usize> {
+    let tag_len = text[1 ..].iter().position(|byte| !is_identifier_byte(*byte) || *byte == b'$')? + 2;
+    if text[tag_len - 1] != b'$' || text[1].is_ascii_digit() {
+        return None
+    }
+    let tag = &text[.. tag_len];
+    let closing = text[tag_len ..].windows(tag_len).position(|window| window == tag);
+    Some(closing.map_or(text.len(), |closing| 2 * tag_len + closing))
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || byte >= 0x80
+}
+
+fn any_or(word: String) -> Option<Vec<u8>> {
+    match word.as_str() {
+        "*" => None,
+        _ => Some(word.into_bytes()),
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.line)
+    }
+}
+
+impl Debug for Rule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("Rule").field(&self.line).finish()
+    }
+}
+
+impl Debug for Rules {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Rules")
+            .field("rules", &self.rules)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Rules};
+
+    fn check(rules: &Rules, user: &str, database: &str, statement: &str) -> Option<(Action, String)> {
+        rules.check(user.as_bytes(), database.as_bytes(), statement.as_bytes())
+            .map(|rule| (rule.action, rule.to_string()))
+    }
+
+    #[test]
+    fn keywords() {
+        let rules = Rules::parse("deny * * keyword drop table\ndeny * * keyword TRUNCATE").unwrap();
+        let deny = |line: &str| Some((Action::Deny, line.to_string()));
+        assert_eq!(deny("deny * * keyword drop table"), check(&rules, "alice", "db", "DROP TABLE t1"));
+        assert_eq!(deny("deny * * keyword drop table"), check(&rules, "alice", "db", "begin;drop\n table t1"));
+        assert_eq!(deny("deny * * keyword TRUNCATE"), check(&rules, "alice", "db", "truncate t1"));
+        assert_eq!(None, check(&rules, "alice", "db", "drop index i1"));
+        assert_eq!(None, check(&rules, "alice", "db", "select * from truncated"));
+        assert_eq!(None, check(&rules, "alice", "db", "select 1"));
+    }
+
+    #[test]
+    fn keywords_outside_quotes() {
+        let rules = Rules::parse("deny * * keyword update\ndeny * * keyword drop").unwrap();
+        let denied = |statement: &str| check(&rules, "alice", "db", statement).is_some();
+        assert!(!denied("select * from t where s = 'update'"));
+        assert!(!denied("select * from t where s = E'it\\'s an update'"));
+        assert!(!denied(r#"select "update" from t"#));
+        assert!(!denied("select $$update$$, $body$ drop $x$ $body$"));
+        assert!(!denied("select 1 -- drop\n"));
+        assert!(!denied("select /* drop /* nested */ update */ 1"));
+        assert!(denied("select 'it''s'; update t set a = 1"));
+        assert!(denied("select $1 from t; drop table t"));
+        assert!(denied("select a$$b from t; drop table t"));
+        assert!(denied("select 1 -- comment\n; drop table t"));
+        // a backslash may or may not escape the quote depending on standard_conforming_strings
+        assert!(denied(r"select '\''; update t set a = 1; --'"));
+        assert!(denied(r"select '\'; update t set a = 1; --'"));
+        // the regex sees literals
+        let rules = Rules::parse("deny * * regex (?i)drop").unwrap();
+        assert!(check(&rules, "alice", "db", "select 'drop'").is_some());
+        // the code regex doesn't but sees quoted identifiers unquoted
+        let rules = Rules::parse(r"deny * * code (?i)\bdrop\s+table").unwrap();
+        assert!(check(&rules, "alice", "db", "select 'drop table'").is_none());
+        assert!(check(&rules, "alice", "db", r#"select 1; "drop" /* x */ table t"#).is_some());
+    }
+
+    #[test]
+    fn regex() {
+        let rules = Rules::parse(r"deny * * regex (?i)^\s*delete\s+from\s+\w+\s*;?\s*$").unwrap();
+        assert!(check(&rules, "alice", "db", "  DELETE FROM t1;").is_some());
+        assert!(check(&rules, "alice", "db", "delete from t1 where id = 1").is_none());
+    }
+
+    #[test]
+    fn scopes_and_order() {
+        let rules = Rules::parse("\
+            # action user database kind pattern\n\
+            allow admin * keyword drop\n\
+            allow * sandbox keyword drop\n\
+            deny * * keyword drop\n\
+        ").unwrap();
+        assert_eq!(Some(Action::Allow), check(&rules, "admin", "prod", "drop table t1").map(|(action, _)| action));
+        assert_eq!(Some(Action::Allow), check(&rules, "alice", "sandbox", "drop table t1").map(|(action, _)| action));
+        assert_eq!(Some(Action::Deny), check(&rules, "alice", "prod", "drop table t1").map(|(action, _)| action));
+        assert_eq!(
+            r#"Rules { rules: [Rule("allow admin * keyword drop"), Rule("allow * sandbox keyword drop"), Rule("deny * * keyword drop")] }"#,
+            format!("{:?}", rules),
+        );
+    }
+
+    #[test]
+    fn read_only() {
+        let rules = Rules::read_only();
+        let denied = |statement: &str| check(&rules, "alice", "db", statement).map(|(action, _)| action) == Some(Action::Deny);
+        assert!(denied("SET default_transaction_read_only = off"));
+        assert!(denied("set session characteristics as transaction read write"));
+        assert!(denied("begin read write"));
+        assert!(denied("select 1; reset all"));
+        assert!(denied("SET LOCAL transaction_read_only TO off"));
+        assert!(denied("select set_config('default_transaction_read_only', 'off', false)"));
+        assert!(denied("select SET_CONFIG ('transaction_read_only', 'off', true)"));
+        assert!(denied("select set_config('search_path', 'public', false)"));
+        assert!(denied("do $$ begin perform 1; end $$"));
+        assert!(denied(r#"select "set_config"('default_transaction_read_only','off',false)"#));
+        assert!(denied("select set_config/**/('default_transaction_read_only','off',false)"));
+        assert!(denied("/* x */ do $$ begin execute 'SET default_tran' || 'saction_read_only = off'; end $$"));
+        assert!(denied("set /*;*/ default_transaction_read_only = off"));
+        assert!(denied(r#"set "default_transaction_read_only" = off"#));
+        assert!(denied(r#"copy "t1" from stdin"#));
+        assert!(denied("copy t1 (a, b) from stdin"));
+        assert!(denied("insert into t1 values (1)"));
+        assert!(denied("with d as (delete from t1 returning *) select * from d"));
+        assert!(denied("select * from t1 for update"));
+        assert!(!denied("select * from t1"));
+        assert!(!denied("show default_transaction_read_only"));
+        assert!(!denied("copy (select * from t1) to stdout"));
+        assert!(!denied("begin read only"));
+        assert!(!denied("select * from updates where created > now()"));
+        assert!(!denied("select * from t where s = 'update'"));
+        assert!(!denied("select 'read write', \"insert\" from t"));
+        assert!(!denied("select current_setting('transaction_read_only')"));
+    }
+
+    #[test]
+    fn file_with_errors() {
+        assert_eq!(Err("line 1: user expected".into()), Rules::parse("deny").map(|_| ()));
+        assert_eq!(Err("line 1: database expected".into()), Rules::parse("deny alice").map(|_| ()));
+        assert_eq!(Err("line 1: pattern expected".into()), Rules::parse("deny * * keyword").map(|_| ()));
+        assert_eq!(
+            Err("line 2: action \"block\" is neither allow nor deny".into()),
+            Rules::parse("\nblock * * keyword drop").map(|_| ()),
+        );
+        assert_eq!(
+            Err("line 1: pattern kind \"word\" is neither keyword, regex nor code".into()),
+            Rules::parse("deny * * word drop").map(|_| ()),
+        );
+        assert!(Rules::parse("deny * * regex (").is_err());
+    }
+}
@@ -5,7 +5,13 @@ extern crate hex;
 #[cfg(test)] #[macro_use] extern crate maplit;
 #[cfg(test)] #[macro_use] extern crate claim;
 
+pub mod auth;
 pub mod convey;
+pub mod firewall;
+pub mod mock;
 pub mod msg;
+pub mod pcapng;
+pub mod record;
+pub mod replay;
 pub mod server;
 pub mod tls;
@@ -1,13 +1,15 @@
 extern crate async_std;
 extern crate futures;
 extern crate postgread;
+extern crate serde_yaml;
 extern crate structopt;
 
 use postgread::server::{self, Config};
 use postgread::convey::Message;
 
 use async_std::task;
-use std::io;
+use std::io::{self, Write};
+use std::process;
 use std::sync::Arc;
 use structopt::StructOpt;
 
@@ -17,12 +19,31 @@ fn dump_msg(msg: Message) {
             println!("postgread got from server {:?}", backend_msg),
         Message::Frontend(frontend_msg) =>
             println!("postgread got from client {:?}", frontend_msg),
+        Message::FrontendIgnored(frontend_msg) =>
+            println!("postgread got from client but server ignores {:?}", frontend_msg),
+        Message::Firewall(firewall_event) =>
+            println!("postgread firewall {:?}", firewall_event),
+        Message::Relay(relay_event) =>
+            println!("postgread relays {:?}", relay_event),
+        Message::Capture(capture_event) =>
+            println!("postgread capture {:?}", capture_event),
     }
 }
 
 fn main() -> io::Result<()> {
     let config = Config::from_args();
     task::block_on(async {
+        if config.replay_file.is_some() {
+            let report = server::replay_capture(&config).await?;
+            let yaml = serde_yaml::to_string(&report).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            print!("{}", yaml);
+            io::stdout().flush()?;
+            if report.differs() {
+                // lets a regression run fail
+                process::exit(1)
+            }
+            return Ok(())
+        }
         let server = server::listen(config).await?;
         server::loop_accepting(server, Arc::new(dump_msg)).await
     })
@@ -0,0 +1,352 @@
+pub mod fixture;
+
+#[cfg(test)] mod tests;
+
+use crate::convey::{BackendMsg, ConveyError::*, ConveyResult, FrontendMsg, Message, Side, TlsError};
+use crate::convey::{GSS_ENC_NOT_SUPPORTED, TLS_SUPPORTED, map_read_err};
+use crate::mock::fixture::{Answer, Fixture};
+use crate::msg::body::*;
+use crate::msg::body::error_and_notice_responses::ErrorOrNoticeFields;
+use crate::msg::body::initial::Startup;
+use crate::msg::body::ready_for_query::Status;
+use crate::msg::type_byte::FrontendTypeByte as F;
+use crate::msg::util::async_io;
+use crate::msg::util::decode::MsgDecode;
+use crate::msg::util::encode::encode_msg;
+use crate::msg::util::read::{MsgError, ReadData, read_msg};
+use crate::tls::interface::TlsServer;
+
+use ::futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use ::std::collections::HashMap;
+use ::std::convert::TryFrom;
+use ::std::io;
+
+// Pretends to be a backend answering from the fixture instead of conveying to a real one.
+// Any user is authenticated without a password, every statement is answered at once in Idle status,
+// and statements of the extended query protocol are looked up by their text ignoring parameters' values.
+// The callback gets what frontend sends as Frontend and what the mock answers as Backend messages.
+pub async fn serve<Plain, FrontTlsServer, Callback>(
+    frontend: Plain,
+    frontend_tls_server: FrontTlsServer,
+    fixture: &Fixture,
+    callback: Callback,
+) -> ConveyResult<()>
+where
+    Plain: AsyncRead + AsyncWrite + Send + Unpin,
+    FrontTlsServer: TlsServer<Plain> + Send,
+    FrontTlsServer::Tls: AsyncRead + AsyncWrite,
+    Callback: Fn(Message) + Send,
+{
+    let mut session = Session::new(frontend, fixture, callback);
+    loop {
+        match session.read_initial().await? {
+            Initial::Startup(startup) => return session.run(&startup).await,
+            Initial::Cancel(_) => return Ok(()),  // nothing to cancel as every statement is answered at once
+            Initial::GssEnc => session.write(&[GSS_ENC_NOT_SUPPORTED]).await?,
+            Initial::TLS => {
+                session.write(&[TLS_SUPPORTED]).await?;
+                let Session { stream, fixture, callback, .. } = session;
+                let tls = frontend_tls_server.accept(stream).await
+                    .map_err(|err| IoError(io::Error::new(io::ErrorKind::InvalidData, err.to_string())))?;
+                let mut session = Session::new(tls, fixture, callback);
+                return match session.read_initial().await? {
+                    Initial::Startup(startup) => session.run(&startup).await,
+                    Initial::Cancel(_) => Ok(()),
+                    _ => Err(TlsError(TlsError::TlsRequestedInsideTls)),
+                }
+            },
+        }
+    }
+}
+
+struct Session<'f, Stream, Callback> {
+    stream: Stream,
+    fixture: &'f Fixture,
+    callback: Callback,
+    statements: HashMap<Vec<u8>, Parse>,
+    portals: HashMap<Vec<u8>, Vec<u8>>,  // portal name to the statement's text
+    skipping_until_sync: bool,  // like backend does after an error in extended query
+}
+
+macro_rules! receive {
+    (<$msg_type:ident>, $self:ident, $skipping:expr) => {{
+        let msg = $self.read::<$msg_type>().await?;
+        if $skipping {
+            ($self.callback)(Message::FrontendIgnored(FrontendMsg::$msg_type(&msg)));
+            continue
+        }
+        ($self.callback)(Message::Frontend(FrontendMsg::$msg_type(&msg)));
+        msg
+    }}
+}
+
+macro_rules! send {
+    (<$msg_type:ident>($msg:expr), $self:ident) => {{
+        let msg: &$msg_type = $msg;
+        $self.write(&encode_msg(msg).map_err(EncodeError)?).await?;
+        ($self.callback)(Message::Backend(BackendMsg::$msg_type(msg)));
+    }}
+}
+
+impl<'f, Stream, Callback> Session<'f, Stream, Callback>
+where
+    Stream: AsyncRead + AsyncWrite + Send + Unpin,
+    Callback: Fn(Message) + Send,
+{
+    fn new(stream: Stream, fixture: &'f Fixture, callback: Callback) -> Self {
+        Self {
+            stream,
+            fixture,
+            callback,
+            statements: HashMap::new(),
+            portals: HashMap::new(),
+            skipping_until_sync: false,
+        }
+    }
+
+    async fn read_initial(&mut self) -> ConveyResult<Initial> {
+        let initial = self.read::<Initial>().await?;
+        (self.callback)(Message::Frontend(FrontendMsg::Initial(&initial)));
+        Ok(initial)
+    }
+
+    async fn run(&mut self, startup: &Startup) -> ConveyResult<()> {
+        self.start(startup).await?;
+        loop {
+            let byte = match async_io::accept_eof(async_io::read_u8(&mut self.stream).await).map_err(IoError)? {
+                Some(byte) => byte,
+                None => return Ok(()),
+            };
+            let type_byte = F::try_from(byte).map_err(|_| UnknownType(Side::Frontend, byte))?;
+            let skipping = self.skipping_until_sync && !matches!(type_byte, F::Sync | F::Terminate);
+            match type_byte {
+                F::Bind => {
+                    let bind = receive!(<Bind>, self, skipping);
+                    self.bind(bind).await?
+                },
+                F::Close => {
+                    let close = receive!(<Close>, self, skipping);
+                    match close {
+                        Close::Portal(name) => self.portals.remove(&name.0),
+                        Close::PreparedStatement(name) => self.statements.remove(&name.0).map(|parse| parse.query.0),
+                    };
+                    send!(<CloseComplete>(&CloseComplete()), self)
+                },
+                F::Describe => {
+                    let describe = receive!(<Describe>, self, skipping);
+                    self.describe(describe).await?
+                },
+                F::Execute => {
+                    let execute = receive!(<Execute>, self, skipping);
+                    self.execute(execute).await?
+                },
+                F::Flush => {
+                    receive!(<Flush>, self, skipping);  // every answer is written at once
+                },
+                F::Parse => {
+                    let parse = receive!(<Parse>, self, skipping);
+                    self.parse(parse).await?
+                },
+                F::Query => {
+                    let query = receive!(<Query>, self, skipping);
+                    self.query(query).await?
+                },
+                F::Sync => {
+                    receive!(<Sync>, self, skipping);
+                    self.skipping_until_sync = false;
+                    self.portals.remove(&b""[..]);
+                    self.send_ready_for_query().await?
+                },
+                F::Terminate => {
+                    receive!(<Terminate>, self, skipping);
+                    return Ok(())
+                },
+                F::CopyData | F::CopyDone | F::CopyFail =>
+                    return Err(Unsupported("copying in mock mode")),
+                F::FunctionCall =>
+                    return Err(Unsupported("function calls in mock mode")),
+                F::GssResponse_or_Password_or_SaslResponses =>
+                    return Err(Unsupported("authentication in mock mode")),
+            }
+        }
+    }
+
+    async fn start(&mut self, startup: &Startup) -> ConveyResult<()> {
+        send!(<Authentication>(&Authentication::Ok), self);
+        let fixture = self.fixture;
+        let user = startup.params.iter().find(|param| param.name == b"user").map(|param| param.value.clone());
+        for parameter in fixture.parameters() {
+            send!(<ParameterStatus>(parameter), self);
+        }
+        if let Some(user) = user {
+            send!(<ParameterStatus>(&ParameterStatus::new(b"session_authorization".to_vec(), user)), self);
+        }
+        let key_data = BackendKeyData { process_id: rand::random(), secret_key: rand::random::<[u8; 4]>().to_vec() };
+        send!(<BackendKeyData>(&key_data), self);
+        self.send_ready_for_query().await
+    }
+
+    async fn query(&mut self, query: Query) -> ConveyResult<()> {
+        let fixture = self.fixture;
+        if is_empty(&query.0) {
+            send!(<EmptyQueryResponse>(&EmptyQueryResponse {}), self);
+        } else {
+            match fixture.answer(&query.0) {
+                Some(Answer::Rows { row_description, data_rows, command_complete }) => {
+                    if let Some(row_description) = row_description {
+                        send!(<RowDescription>(row_description), self);
+                    }
+                    self.send_rows(data_rows, command_complete).await?
+                },
+                Some(Answer::Error(error)) => send!(<ErrorResponse>(error), self),
+                None => send!(<ErrorResponse>(&no_fixture_error(&query.0)), self),
+            }
+        }
+        self.send_ready_for_query().await
+    }
+
+    async fn parse(&mut self, parse: Parse) -> ConveyResult<()> {
+        if !is_empty(&parse.query.0) && self.fixture.answer(&parse.query.0).is_none() {
+            return self.fail(no_fixture_error(&parse.query.0)).await
+        }
+        self.statements.insert(parse.prepared_statement_name.0.clone(), parse);
+        send!(<ParseComplete>(&ParseComplete()), self);
+        Ok(())
+    }
+
+    async fn bind(&mut self, bind: Bind) -> ConveyResult<()> {
+        match self.statements.get(&bind.prepared_statement_name.0) {
+            Some(parse) => {
+                let statement = parse.query.0.clone();
+                self.portals.insert(bind.portal_name.0, statement);
+                send!(<BindComplete>(&BindComplete()), self);
+                Ok(())
+            },
+            None => self.fail(missing_error(INVALID_SQL_STATEMENT_NAME, "prepared statement", &bind.prepared_statement_name.0)).await,
+        }
+    }
+
+    async fn describe(&mut self, describe: Describe) -> ConveyResult<()> {
+        let fixture = self.fixture;
+        let statement = match &describe {
+            Describe::PreparedStatement(name) => match self.statements.get(&name.0) {
+                Some(parse) => {
+                    let parameters_types = parameters_types(parse);
+                    let statement = parse.query.0.clone();
+                    send!(<ParameterDescription>(&ParameterDescription { parameters_types }), self);
+                    statement
+                },
+                None => return self.fail(missing_error(INVALID_SQL_STATEMENT_NAME, "prepared statement", &name.0)).await,
+            },
+            Describe::Portal(name) => match self.portals.get(&name.0) {
+                Some(statement) => statement.clone(),
+                None => return self.fail(missing_error(INVALID_CURSOR_NAME, "portal", &name.0)).await,
+            },
+        };
+        match fixture.answer(&statement) {
+            Some(Answer::Rows { row_description: Some(row_description), .. }) if !is_empty(&statement) =>
+                send!(<RowDescription>(row_description), self),
+            _ => send!(<NoData>(&NoData()), self),
+        }
+        Ok(())
+    }
+
+    async fn execute(&mut self, execute: Execute) -> ConveyResult<()> {
+        let fixture = self.fixture;
+        let statement = match self.portals.get(&execute.portal_name.0) {
+            Some(statement) => statement.clone(),
+            None => return self.fail(missing_error(INVALID_CURSOR_NAME, "portal", &execute.portal_name.0)).await,
+        };
+        if is_empty(&statement) {
+            send!(<EmptyQueryResponse>(&EmptyQueryResponse {}), self);
+            return Ok(())
+        }
+        // rows_limit is ignored, all the rows are sent at once
+        match fixture.answer(&statement) {
+            Some(Answer::Rows { data_rows, command_complete, .. }) => self.send_rows(data_rows, command_complete).await,
+            Some(Answer::Error(error)) => self.fail((**error).clone()).await,
+            None => self.fail(no_fixture_error(&statement)).await,
+        }
+    }
+
+    async fn fail(&mut self, error: ErrorResponse) -> ConveyResult<()> {
+        send!(<ErrorResponse>(&error), self);
+        self.skipping_until_sync = true;
+        Ok(())
+    }
+
+    async fn send_rows(&mut self, data_rows: &[DataRow], command_complete: &CommandComplete) -> ConveyResult<()> {
+        for data_row in data_rows {
+            send!(<DataRow>(data_row), self);
+        }
+        send!(<CommandComplete>(command_complete), self);
+        Ok(())
+    }
+
+    async fn send_ready_for_query(&mut self) -> ConveyResult<()> {
+        send!(<ReadyForQuery>(&ReadyForQuery { status: Status::Idle }), self);
+        Ok(())
+    }
+
+    async fn read<Msg>(&mut self) -> ConveyResult<Msg>
+    where Msg: 'static + MsgDecode {
+        let ReadData { msg_result, .. } = read_msg(&mut self.stream).await.map_err(map_read_err)?;
+        msg_result.map_err(|msg_error| match msg_error {
+            MsgError::DecodeError(decode_error) => DecodeError(decode_error),
+            MsgError::LeftUndecoded(left) => LeftUndecoded(left),
+        })
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> ConveyResult<()> {
+        self.stream.write_all(bytes).await.map_err(IoError)
+    }
+}
+
+fn is_empty(statement: &[u8]) -> bool {
+    statement.iter().all(|byte| byte.is_ascii_whitespace() || *byte == b';')
+}
+
+// Backend infers types of parameters not specified by Parse, here they are all text.
+fn parameters_types(parse: &Parse) -> Vec<u32> {
+    let count = parse.parameters_types.len().max(max_parameter_number(&parse.query.0));
+    (0..count)
+        .map(|i| match parse.parameters_types.get(i) {
+            Some(0) | None => TEXT_OID,
+            Some(type_oid) => *type_oid,
+        })
+        .collect()
+}
+
+fn max_parameter_number(statement: &[u8]) -> usize {
+    statement.split(|byte| *byte == b'$')
+        .skip(1)
+        .filter_map(|rest| {
+            let digits: Vec<u8> = rest.iter().cloned().take_while(u8::is_ascii_digit).collect();
+            String::from_utf8(digits).ok()?.parse().ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn no_fixture_error(statement: &[u8]) -> ErrorResponse {
+    new_error(FEATURE_NOT_SUPPORTED, format!("no fixture matches the statement: {}", String::from_utf8_lossy(statement)))
+}
+
+fn missing_error(code: &str, what: &str, name: &[u8]) -> ErrorResponse {
+    new_error(code, format!("{} \"{}\" does not exist", what, String::from_utf8_lossy(name)))
+}
+
+fn new_error(code: &str, message: String) -> ErrorResponse {
+    ErrorResponse(ErrorOrNoticeFields {
+        localized_severity: Some(b"ERROR".to_vec()),
+        severity: Some(b"ERROR".to_vec()),
+        code: Some(code.into()),
+        message: Some(message.into_bytes()),
+        ..Default::default()
+    })
+}
+
+const FEATURE_NOT_SUPPORTED: &str = "0A000";
+const INVALID_CURSOR_NAME: &str = "34000";
+const INVALID_SQL_STATEMENT_NAME: &str = "26000";
+pub(crate) const TEXT_OID: u32 = 25;
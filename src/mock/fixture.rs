@@ -0,0 +1,289 @@
+use crate::mock::TEXT_OID;
+use crate::msg::body::{CommandComplete, DataRow, ErrorResponse, ParameterStatus, RowDescription};
+use crate::msg::body::error_and_notice_responses::ErrorOrNoticeFields;
+use crate::msg::body::row_description::Field;
+use crate::msg::parts::{Bytes, Format, Value};
+
+use ::regex::bytes::Regex;
+use ::serde::Deserialize;
+use ::serde_yaml::Value as YamlValue;
+use ::std::collections::BTreeMap;
+use ::std::fmt::{self, Debug, Formatter};
+use ::std::fs;
+use ::std::io;
+
+// A YAML document (JSON is fine too) like
+//   parameters:
+//     server_version: "14.5"
+//   queries:
+//     - pattern: (?i)^select id, name from users
+//       columns: [{name: id, type_oid: 23}, {name: name}]
+//       rows: [[1, alice], [2, null]]
+//     - pattern: (?i)^(begin|commit)
+//       tag: COMMIT
+//     - pattern: (?i)^drop
+//       error: {code: "42501", message: permission denied}
+// The first entry whose regex is found in a statement answers it. Columns are text (OID 25) unless told,
+// cells are sent in text format, the tag is "SELECT <rows>" unless told.
+pub struct Fixture {
+    parameters: Vec<ParameterStatus>,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    regex: Regex,
+    answer: Answer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Answer {
+    Rows {
+        row_description: Option<RowDescription>,  // None for a statement returning no rows like BEGIN
+        data_rows: Vec<DataRow>,
+        command_complete: CommandComplete,
+    },
+    Error(Box<ErrorResponse>),  // boxed since it's much larger than Rows
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureDoc {
+    #[serde(default)]
+    parameters: BTreeMap<String, String>,
+    #[serde(default)]
+    queries: Vec<EntryDoc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntryDoc {
+    pattern: String,
+    #[serde(default)]
+    columns: Vec<ColumnDoc>,
+    #[serde(default)]
+    rows: Vec<Vec<YamlValue>>,
+    tag: Option<String>,
+    error: Option<ErrorDoc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnDoc {
+    name: String,
+    #[serde(default = "ColumnDoc::text_oid")]
+    type_oid: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ErrorDoc {
+    code: String,
+    message: String,
+}
+
+impl Fixture {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, err)))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc: FixtureDoc = serde_yaml::from_str(text).map_err(|err| err.to_string())?;
+        let mut parameters: Vec<_> = DEFAULT_PARAMETERS.iter()
+            .filter(|(name, _)| !doc.parameters.contains_key(*name))
+            .map(|(name, value)| ParameterStatus::new(name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect();
+        parameters.extend(doc.parameters.into_iter()
+            .map(|(name, value)| ParameterStatus::new(name.into_bytes(), value.into_bytes())));
+        let entries = doc.queries.into_iter().enumerate()
+            .map(|(i, entry)| Entry::parse(entry).map_err(|err| format!("query {}: {}", i + 1, err)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { parameters, entries })
+    }
+
+    // Reported by ParameterStatus after authentication, the fixture's ones over the defaults.
+    pub fn parameters(&self) -> &[ParameterStatus] {
+        &self.parameters
+    }
+
+    pub fn answer(&self, statement: &[u8]) -> Option<&Answer> {
+        self.entries.iter()
+            .find(|entry| entry.regex.is_match(statement))
+            .map(|entry| &entry.answer)
+    }
+}
+
+const DEFAULT_PARAMETERS: &[(&str, &str)] = &[
+    ("client_encoding", "UTF8"),
+    ("DateStyle", "ISO, MDY"),
+    ("integer_datetimes", "on"),
+    ("IntervalStyle", "postgres"),
+    ("server_encoding", "UTF8"),
+    ("server_version", "14.0"),
+    ("standard_conforming_strings", "on"),
+    ("TimeZone", "UTC"),
+];
+
+impl Entry {
+    fn parse(doc: EntryDoc) -> Result<Self, String> {
+        let regex = Regex::new(&doc.pattern).map_err(|err| err.to_string())?;
+        let answer = match doc.error {
+            Some(_) if !doc.columns.is_empty() || !doc.rows.is_empty() || doc.tag.is_some() =>
+                return Err("error goes without columns, rows and tag".into()),
+            Some(ErrorDoc { code, message }) => Answer::Error(Box::new(ErrorResponse(ErrorOrNoticeFields {
+                localized_severity: Some(b"ERROR".to_vec()),
+                severity: Some(b"ERROR".to_vec()),
+                code: Some(code.into_bytes()),
+                message: Some(message.into_bytes()),
+                ..Default::default()
+            }))),
+            None => {
+                let data_rows = doc.rows.iter().enumerate()
+                    .map(|(i, row)| match row.len() {
+                        len if len == doc.columns.len() => row.iter().map(cell_value).collect::<Result<_, _>>()
+                            .map(|columns| DataRow { columns }),
+                        len => Err(format!("{} cells in row {} of {} columns", len, i + 1, doc.columns.len())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let tag = doc.tag.unwrap_or_else(|| format!("SELECT {}", data_rows.len()));
+                let row_description = if doc.columns.is_empty() {
+                    None
+                } else {
+                    Some(RowDescription { fields: doc.columns.into_iter().map(ColumnDoc::into_field).collect() })
+                };
+                Answer::Rows { row_description, data_rows, command_complete: CommandComplete { tag: tag.into_bytes() } }
+            },
+        };
+        Ok(Self { regex, answer })
+    }
+}
+
+impl ColumnDoc {
+    fn text_oid() -> u32 {
+        TEXT_OID
+    }
+
+    fn into_field(self) -> Field {
+        Field {
+            name: self.name.into_bytes(),
+            column_oid: 0,
+            column_attr_num: 0,
+            type_oid: self.type_oid,
+            type_size: -1,
+            type_modifier: -1,
+            format: Format::Text,
+        }
+    }
+}
+
+// Cells are written as PostgreSQL prints them in text format.
+fn cell_value(cell: &YamlValue) -> Result<Value, String> {
+    let text = match cell {
+        YamlValue::Null => return Ok(Value::Null),
+        YamlValue::Bool(true) => "t".to_string(),
+        YamlValue::Bool(false) => "f".to_string(),
+        YamlValue::Number(number) => number.to_string(),
+        YamlValue::String(string) => string.clone(),
+        _ => return Err(format!("cell {:?} is not a scalar", cell)),
+    };
+    Ok(Value::Bytes(Bytes(text.into_bytes())))
+}
+
+impl Debug for Entry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("regex", &self.regex.as_str())
+            .field("answer", &self.answer)
+            .finish()
+    }
+}
+
+impl Debug for Fixture {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Fixture")
+            .field("parameters", &self.parameters)
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Answer, Fixture};
+    use crate::msg::body::{CommandComplete, DataRow, ParameterStatus};
+    use crate::msg::parts::{Bytes, Value};
+
+    fn text(s: &str) -> Value {
+        Value::Bytes(Bytes(s.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn rows() {
+        let fixture = Fixture::parse(r#"
+            queries:
+              - pattern: (?i)^select id, name, active from users
+                columns: [{name: id, type_oid: 23}, {name: name}, {name: active, type_oid: 16}]
+                rows: [[1, alice, true], [2, null, false]]
+        "#).unwrap();
+        match fixture.answer(b"SELECT id, name, active FROM users") {
+            Some(Answer::Rows { row_description: Some(row_description), data_rows, command_complete }) => {
+                let types: Vec<_> = row_description.fields.iter().map(|field| (field.name.as_slice(), field.type_oid)).collect();
+                assert_eq!(vec![(&b"id"[..], 23), (&b"name"[..], 25), (&b"active"[..], 16)], types);
+                assert_eq!(&vec![
+                    DataRow { columns: vec![text("1"), text("alice"), text("t")] },
+                    DataRow { columns: vec![text("2"), Value::Null, text("f")] },
+                ], data_rows);
+                assert_eq!(&CommandComplete { tag: b"SELECT 2".to_vec() }, command_complete);
+            },
+            answer => panic!("unexpected {:?}", answer),
+        }
+        assert_eq!(None, fixture.answer(b"select * from users"));
+    }
+
+    #[test]
+    fn tag_and_error() {
+        let fixture = Fixture::parse(r#"{
+            "queries": [
+                {"pattern": "(?i)^drop", "error": {"code": "42501", "message": "permission denied"}},
+                {"pattern": "(?i)^(begin|commit)", "tag": "COMMIT"},
+                {"pattern": ".*", "tag": "DROP TABLE"}
+            ]
+        }"#).unwrap();
+        match fixture.answer(b"drop table t1") {
+            Some(Answer::Error(error)) => {
+                assert_eq!(Some(b"42501".to_vec()), error.0.code);
+                assert_eq!(Some(b"permission denied".to_vec()), error.0.message);
+            },
+            answer => panic!("unexpected {:?}", answer),
+        }
+        assert_eq!(
+            Some(&Answer::Rows { row_description: None, data_rows: vec![], command_complete: CommandComplete { tag: b"COMMIT".to_vec() } }),
+            fixture.answer(b"commit"),
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        let fixture = Fixture::parse("parameters: {server_version: '9.6.24', application_name: mock}").unwrap();
+        let parameters = fixture.parameters();
+        assert!(parameters.contains(&ParameterStatus::new(b"server_version".to_vec(), b"9.6.24".to_vec())));
+        assert!(parameters.contains(&ParameterStatus::new(b"application_name".to_vec(), b"mock".to_vec())));
+        assert!(parameters.contains(&ParameterStatus::new(b"server_encoding".to_vec(), b"UTF8".to_vec())));
+        assert_eq!(1, parameters.iter().filter(|parameter| parameter.name == b"server_version").count());
+    }
+
+    #[test]
+    fn file_with_errors() {
+        assert_eq!(
+            Err("query 1: 1 cells in row 2 of 2 columns".into()),
+            Fixture::parse("queries: [{pattern: x, columns: [{name: a}, {name: b}], rows: [[1, 2], [1]]}]").map(|_| ()),
+        );
+        assert_eq!(
+            Err("query 2: error goes without columns, rows and tag".into()),
+            Fixture::parse("queries: [{pattern: x}, {pattern: y, tag: DROP, error: {code: '42501', message: no}}]").map(|_| ()),
+        );
+        assert!(Fixture::parse("queries: [{pattern: '('}]").is_err());
+        assert!(Fixture::parse("queries: [{pattern: x, colums: []}]").is_err());
+        assert!(Fixture::parse("queries: [{pattern: x, columns: [{name: a}], rows: [[[1]]]}]").is_err());
+    }
+}
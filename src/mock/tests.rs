@@ -0,0 +1,291 @@
+use crate::convey::{BackendMsg, ConveyError::*, ConveyResult, Message};
+use crate::convey::tests::fake_tls::{FakeTlsServer, FakeTlsStream};
+use crate::convey::tests::new_msg::*;
+use crate::convey::util::{BackendMsgClone, MessageClone};
+use crate::mock::{fixture::Fixture, serve};
+use crate::msg::body::ErrorResponse;
+use crate::msg::body::error_and_notice_responses::ErrorOrNoticeFields;
+use crate::msg::body::row_description::{Field, RowDescription};
+use crate::msg::parts::Format;
+use crate::msg::util::decode::MsgDecode;
+use crate::msg::util::encode::{MsgEncode, encode_msg};
+
+use ::async_std::task;
+use ::futures::io::{AsyncRead, AsyncWrite, Cursor};
+use ::futures::task::{Context, Poll};
+use ::std::io;
+use ::std::pin::Pin;
+use ::std::sync::{Arc, Mutex};
+
+macro_rules! frontend {
+    (
+        $module:ident::$func:ident( $( $arg:expr ),* ),
+        $expected:ident,
+        $client:ident
+    ) => {
+        let msg_holder_ = $module::$func( $( $arg, )* );
+        $client.push(&msg_holder_);
+        $expected.push(MessageClone::make(Message::Frontend($module::FrontendMsg(&msg_holder_))));
+    }
+}
+
+macro_rules! frontend_ignored {
+    (
+        $module:ident::$func:ident( $( $arg:expr ),* ),
+        $expected:ident,
+        $client:ident
+    ) => {
+        let msg_holder_ = $module::$func( $( $arg, )* );
+        $client.push(&msg_holder_);
+        $expected.push(MessageClone::make(Message::FrontendIgnored($module::FrontendMsg(&msg_holder_))));
+    }
+}
+
+macro_rules! backend {
+    (
+        $module:ident::$func:ident( $( $arg:expr ),* ),
+        $expected:ident
+    ) => {
+        let msg_holder_ = $module::$func( $( $arg, )* );
+        $expected.push(MessageClone::make(Message::Backend($module::BackendMsg(&msg_holder_))));
+    }
+}
+
+const FIXTURE: &str = r#"
+parameters:
+  server_version: "13.4"
+queries:
+  - pattern: (?i)^select id, name from users
+    columns: [{name: id, type_oid: 23}, {name: name}]
+    rows: [[1, alice], [2, null]]
+  - pattern: (?i)^begin
+    tag: BEGIN
+  - pattern: (?i)^drop
+    error: {code: "42501", message: permission denied}
+"#;
+
+#[test]
+fn simple_queries() {
+    let fixture = Fixture::parse(FIXTURE).unwrap();
+    let mut client = FakeClient::new();
+    let mut expected = vec![];
+    started(&fixture, &mut expected, &mut client);
+    frontend!(query::new("select id, name from users"), expected, client);
+    expected.push(backend_clone(BackendMsg::RowDescription(&users())));
+    backend!(data_row::columns(&[Some("1"), Some("alice")]), expected);
+    backend!(data_row::columns(&[Some("2"), None]), expected);
+    backend!(command_complete::new("SELECT 2"), expected);
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(query::new("BEGIN"), expected, client);
+    backend!(command_complete::new("BEGIN"), expected);
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(query::new("drop table users"), expected, client);
+    expected.push(backend_clone(BackendMsg::ErrorResponse(&error("42501", "permission denied"))));
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(query::new("select 1"), expected, client);
+    expected.push(backend_clone(BackendMsg::ErrorResponse(&error("0A000", "no fixture matches the statement: select 1"))));
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(query::new(" ;"), expected, client);
+    backend!(empty_query_response::new(()), expected);
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(terminate::new(()), expected, client);
+    assert_ok!(test_serve(&fixture, expected, client));
+}
+
+#[test]
+fn extended_query() {
+    let fixture = Fixture::parse(FIXTURE).unwrap();
+    let mut client = FakeClient::new();
+    let mut expected = vec![];
+    started(&fixture, &mut expected, &mut client);
+    frontend!(parse::query("select id, name from users where id = $1"), expected, client);
+    backend!(parse_complete::new(()), expected);
+    frontend!(describe::prepared_statement(""), expected, client);
+    backend!(parameter_description::types(&[25]), expected);
+    expected.push(backend_clone(BackendMsg::RowDescription(&users())));
+    frontend!(bind::new(()), expected, client);
+    backend!(bind_complete::new(()), expected);
+    frontend!(execute::new(()), expected, client);
+    backend!(data_row::columns(&[Some("1"), Some("alice")]), expected);
+    backend!(data_row::columns(&[Some("2"), None]), expected);
+    backend!(command_complete::new("SELECT 2"), expected);
+    frontend!(sync::new(()), expected, client);
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(close::prepared_statement(""), expected, client);
+    backend!(close_complete::new(()), expected);
+    frontend!(sync::new(()), expected, client);
+    backend!(ready_for_query::idle(()), expected);
+    assert_ok!(test_serve(&fixture, expected, client));
+}
+
+#[test]
+fn extended_query_skipped_till_sync_after_error() {
+    let fixture = Fixture::parse(FIXTURE).unwrap();
+    let mut client = FakeClient::new();
+    let mut expected = vec![];
+    started(&fixture, &mut expected, &mut client);
+    frontend!(parse::query("drop table users"), expected, client);
+    backend!(parse_complete::new(()), expected);
+    frontend!(bind::new(()), expected, client);
+    backend!(bind_complete::new(()), expected);
+    frontend!(execute::new(()), expected, client);
+    expected.push(backend_clone(BackendMsg::ErrorResponse(&error("42501", "permission denied"))));
+    frontend_ignored!(execute::new(()), expected, client);
+    frontend!(sync::new(()), expected, client);
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(parse::query("select 1"), expected, client);
+    expected.push(backend_clone(BackendMsg::ErrorResponse(&error("0A000", "no fixture matches the statement: select 1"))));
+    frontend_ignored!(bind::new(()), expected, client);
+    frontend!(sync::new(()), expected, client);
+    backend!(ready_for_query::idle(()), expected);
+    frontend!(execute::new(()), expected, client);
+    expected.push(backend_clone(BackendMsg::ErrorResponse(&error("34000", "portal \"\" does not exist"))));
+    frontend!(terminate::new(()), expected, client);
+    assert_ok!(test_serve(&fixture, expected, client));
+}
+
+#[test]
+fn gss_enc_declined_then_tls() {
+    let fixture = Fixture::parse("{}").unwrap();
+    let mut client = FakeClient::new();
+    let mut expected = vec![];
+    frontend!(initial::gss_enc(()), expected, client);
+    frontend!(initial::tls(()), expected, client);
+    started(&fixture, &mut expected, &mut client);
+    frontend!(terminate::new(()), expected, client);
+    let output = assert_ok!(test_serve(&fixture, expected, client));
+    assert_eq!(b"NSR", &output[..3]);
+}
+
+#[test]
+fn cancel() {
+    let fixture = Fixture::parse("{}").unwrap();
+    let mut client = FakeClient::new();
+    let mut expected = vec![];
+    frontend!(initial::cancel(11, 12), expected, client);
+    assert_eq!(Vec::<u8>::new(), assert_ok!(test_serve(&fixture, expected, client)));
+}
+
+#[test]
+fn copy_unsupported() {
+    let fixture = Fixture::parse("{}").unwrap();
+    let mut client = FakeClient::new();
+    let mut expected = vec![];
+    started(&fixture, &mut expected, &mut client);
+    client.push(&copy_done::new(()));
+    assert!(matches!(test_serve(&fixture, expected, client), Err(Unsupported(_))));
+}
+
+fn started(fixture: &Fixture, expected: &mut Vec<MessageClone>, client: &mut FakeClient) {
+    frontend!(initial::startup(3, 0, hashmap!{"user" => "alice"}), expected, client);
+    backend!(authentication::ok(()), expected);
+    for parameter in fixture.parameters() {
+        expected.push(backend_clone(BackendMsg::ParameterStatus(parameter)));
+    }
+    backend!(parameter_status::new("session_authorization", "alice"), expected);
+    backend!(backend_key_data::new(0, 0), expected);
+    backend!(ready_for_query::idle(()), expected);
+}
+
+fn users() -> RowDescription {
+    let field = |name: &str, type_oid| Field {
+        name: name.into(),
+        column_oid: 0,
+        column_attr_num: 0,
+        type_oid,
+        type_size: -1,
+        type_modifier: -1,
+        format: Format::Text,
+    };
+    RowDescription { fields: vec![field("id", 23), field("name", 25)] }
+}
+
+fn error(code: &str, message: &str) -> ErrorResponse {
+    ErrorResponse(ErrorOrNoticeFields {
+        localized_severity: Some("ERROR".into()),
+        severity: Some("ERROR".into()),
+        code: Some(code.into()),
+        message: Some(message.into()),
+        ..Default::default()
+    })
+}
+
+fn backend_clone(msg: BackendMsg) -> MessageClone {
+    MessageClone::make(Message::Backend(msg))
+}
+
+// Returns what the mock has written.
+fn test_serve(fixture: &Fixture, expected: Vec<MessageClone>, client: FakeClient) -> ConveyResult<Vec<u8>> {
+    let output = client.output.clone();
+    let served = Mutex::new(vec![]);
+    let serve_result = task::block_on(serve(client, FakeTlsServer(), fixture, |msg| {
+        let msg = match MessageClone::make(msg) {
+            // the key is random
+            MessageClone::Backend(BackendMsgClone::BackendKeyData(key_data)) => {
+                assert_eq!(4, key_data.secret_key.len());
+                MessageClone::make(Message::Backend(backend_key_data::BackendMsg(&backend_key_data::new(0, 0))))
+            },
+            msg => msg,
+        };
+        served.lock().unwrap().push(msg);
+    }));
+    assert_eq!(expected, served.into_inner().unwrap());
+    let output = output.lock().unwrap().clone();
+    serve_result.map(|()| output)
+}
+
+struct FakeClient {
+    input: Cursor<Vec<u8>>,
+    output: Arc<Mutex<Vec<u8>>>,
+}
+
+impl FakeClient {
+    fn new() -> Self {
+        Self { input: Cursor::new(vec![]), output: Default::default() }
+    }
+
+    fn push(&mut self, msg: &(impl MsgDecode + MsgEncode)) {
+        self.input.get_mut().extend(encode_msg(msg).unwrap());
+    }
+}
+
+impl AsyncRead for FakeClient {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.input).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for FakeClient {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.output.lock().unwrap().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for FakeTlsStream<FakeClient> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.plain).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for FakeTlsStream<FakeClient> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.plain).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.plain).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.plain).poll_close(cx)
+    }
+}
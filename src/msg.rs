@@ -1,4 +1,6 @@
 pub mod body;
 pub mod parts;
+pub mod replication;
+pub mod scram;
 pub mod type_byte;
 pub mod util;
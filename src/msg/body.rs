@@ -2,14 +2,27 @@ pub mod authentication;
 pub mod backend_key_data;
 pub mod bind;
 pub mod bind_complete;
+pub mod close;
+pub mod close_complete;
 pub mod command_complete;
+pub mod copy_data;
+pub mod copy_done;
+pub mod copy_fail;
+pub mod copy_responses;
 pub mod data_row;
+pub mod describe;
 pub mod error_and_notice_responses;
 pub mod execute;
+pub mod flush;
+pub mod function_call;
+pub mod function_call_response;
 pub mod empty_query_response;
 pub mod gss_response;
 pub mod initial;
 pub mod negotiate_protocol_version;
+pub mod no_data;
+pub mod notification_response;
+pub mod parameter_description;
 pub mod parameter_status;
 pub mod password;
 pub mod parse;
@@ -27,14 +40,27 @@ pub use authentication::Authentication;
 pub use backend_key_data::BackendKeyData;
 pub use bind::Bind;
 pub use bind_complete::BindComplete;
+pub use close::Close;
+pub use close_complete::CloseComplete;
 pub use command_complete::CommandComplete;
+pub use copy_data::CopyData;
+pub use copy_done::CopyDone;
+pub use copy_fail::CopyFail;
+pub use copy_responses::{CopyBothResponse, CopyInResponse, CopyOutResponse};
 pub use data_row::DataRow;
+pub use describe::Describe;
 pub use error_and_notice_responses::{ErrorResponse, NoticeResponse};
 pub use execute::Execute;
+pub use flush::Flush;
+pub use function_call::FunctionCall;
+pub use function_call_response::FunctionCallResponse;
 pub use gss_response::GssResponse;
 pub use initial::Initial;
 pub use empty_query_response::EmptyQueryResponse;
 pub use negotiate_protocol_version::NegotiateProtocolVersion;
+pub use no_data::NoData;
+pub use notification_response::NotificationResponse;
+pub use parameter_description::ParameterDescription;
 pub use parameter_status::ParameterStatus;
 pub use password::Password;
 pub use parse::Parse;
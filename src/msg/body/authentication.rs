@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Authentication {
@@ -21,7 +21,7 @@ impl Authentication {
 }
 
 impl MsgDecode for Authentication {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Authentication);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let auth_type = bytes.take_u32()?;
@@ -42,6 +42,42 @@ impl MsgDecode for Authentication {
     }
 }
 
+impl MsgEncode for Authentication {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self {
+            Self::Ok => bytes.put_u32(0),
+            Self::KerberosV5 => bytes.put_u32(2),
+            Self::CleartextPassword => bytes.put_u32(3),
+            Self::Md5Password { salt } => {
+                bytes.put_u32(5)?;
+                bytes.put_slice(salt)
+            },
+            Self::ScmCredential => bytes.put_u32(6),
+            Self::Gss => bytes.put_u32(7),
+            Self::GssContinue { auth_data } => {
+                bytes.put_u32(8)?;
+                bytes.put_slice(auth_data)
+            },
+            Self::Sspi => bytes.put_u32(9),
+            Self::Sasl { auth_mechanisms } => {
+                bytes.put_u32(10)?;
+                for mech in auth_mechanisms {
+                    bytes.put_with_null(mech)?;
+                }
+                bytes.put_u8(0)
+            },
+            Self::SaslContinue { challenge_data } => {
+                bytes.put_u32(11)?;
+                bytes.put_slice(challenge_data)
+            },
+            Self::SaslFinal { additional_data } => {
+                bytes.put_u32(12)?;
+                bytes.put_slice(additional_data)
+            },
+        }
+    }
+}
+
 fn decode_gss_continue(bytes: &mut BytesSource) -> DecodeResult<Authentication> {
     let auth_data = bytes.take_vec(bytes.left())?;
     Ok(Authentication::GssContinue { auth_data })
@@ -85,7 +121,7 @@ mod tests {
         let bytes: &[u8] = &[
             0,0,0,3, // cleartext password is required
         ];
-        assert_decode_ok(CleartextPassword, bytes);
+        assert_codec_ok(CleartextPassword, bytes);
     }
 
     #[test]
@@ -93,7 +129,7 @@ mod tests {
         let bytes: &[u8] = &[
             0,0,0,7, // GSSAPI authentication is required
         ];
-        assert_decode_ok(Gss, bytes);
+        assert_codec_ok(Gss, bytes);
     }
 
     #[test]
@@ -102,7 +138,7 @@ mod tests {
             0,0,0,8, // contains GSS or SSPI data
             b'G', b'S', b'S', // data
         ];
-        assert_decode_ok(GssContinue { auth_data: Vec::from("GSS") }, bytes);
+        assert_codec_ok(GssContinue { auth_data: Vec::from("GSS") }, bytes);
     }
 
     #[test]
@@ -110,7 +146,7 @@ mod tests {
         let bytes: &[u8] = &[
             0,0,0,2, // Kerberos V5 is required
         ];
-        assert_decode_ok(KerberosV5, bytes);
+        assert_codec_ok(KerberosV5, bytes);
     }
 
     #[test]
@@ -119,7 +155,7 @@ mod tests {
             0,0,0,5, // MD5 password is required
             1,2,3,4, // salt
         ];
-        assert_decode_ok(Md5Password { salt: [1,2,3,4] }, bytes);
+        assert_codec_ok(Md5Password { salt: [1,2,3,4] }, bytes);
     }
 
     #[test]
@@ -127,7 +163,7 @@ mod tests {
         let bytes: &[u8] = &[
             0,0,0,0, // ok
         ];
-        assert_decode_ok(Ok, bytes);
+        assert_codec_ok(Ok, bytes);
     }
 
     #[test]
@@ -138,7 +174,7 @@ mod tests {
             b'S', b'K', b'E', b'Y', 0,  // second
             0,  // list terminator
         ];
-        assert_decode_ok(Sasl {
+        assert_codec_ok(Sasl {
             auth_mechanisms: vec![
                 Vec::from("OTP"),
                 Vec::from("SKEY"),
@@ -154,7 +190,7 @@ mod tests {
             b' ', b'C', b'O', b'N',  // data
             b'T'
         ];
-        assert_decode_ok(SaslContinue { challenge_data: Vec::from("SASL CONT") }, bytes);
+        assert_codec_ok(SaslContinue { challenge_data: Vec::from("SASL CONT") }, bytes);
     }
 
     #[test]
@@ -164,7 +200,7 @@ mod tests {
             b'S', b'A', b'S', b'L',  // data
             b' ', b'F', b'I', b'N',  // data
         ];
-        assert_decode_ok(SaslFinal { additional_data: Vec::from("SASL FIN") }, bytes);
+        assert_codec_ok(SaslFinal { additional_data: Vec::from("SASL FIN") }, bytes);
     }
 
     #[test]
@@ -172,7 +208,7 @@ mod tests {
         let bytes: &[u8] = &[
             0,0,0,6, // SCM credentials message is required
         ];
-        assert_decode_ok(ScmCredential, bytes);
+        assert_codec_ok(ScmCredential, bytes);
     }
 
     #[test]
@@ -180,6 +216,6 @@ mod tests {
         let bytes: &[u8] = &[
             0,0,0,9, // SSPI authentication is required
         ];
-        assert_decode_ok(Sspi, bytes);
+        assert_codec_ok(Sspi, bytes);
     }
 }
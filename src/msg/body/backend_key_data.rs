@@ -1,10 +1,10 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct BackendKeyData {
     pub process_id: u32,
-    pub secret_key: u32,
+    pub secret_key: Vec<u8>,  // 4 bytes before protocol 3.2, see Version::secret_key_fits
 }
 
 impl BackendKeyData {
@@ -12,15 +12,22 @@ impl BackendKeyData {
 }
 
 impl MsgDecode for BackendKeyData {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::BackendKeyData);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let process_id = bytes.take_u32()?;
-        let secret_key = bytes.take_u32()?;
+        let secret_key = bytes.take_vec(bytes.left())?;
         Ok(Self { process_id, secret_key })
     }
 }
 
+impl MsgEncode for BackendKeyData {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u32(self.process_id)?;
+        bytes.put_slice(&self.secret_key)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{BackendKeyData};
@@ -32,6 +39,15 @@ mod tests {
             0x1, 0x2, 0x3, 0x4,  // process ID
             0x5, 0x6, 0x7, 0x8,  // secret key
         ];
-        assert_decode_ok(BackendKeyData { process_id: 0x01020304, secret_key: 0x05060708 }, bytes);
+        assert_codec_ok(BackendKeyData { process_id: 0x01020304, secret_key: vec![5, 6, 7, 8] }, bytes);
+    }
+
+    #[test]
+    fn long_key() {
+        let mut bytes = vec![
+            0x1, 0x2, 0x3, 0x4,  // process ID
+        ];
+        bytes.extend_from_slice(&[9; 32]);  // secret key since protocol 3.2
+        assert_codec_ok(BackendKeyData { process_id: 0x01020304, secret_key: vec![9; 32] }, &bytes);
     }
 }
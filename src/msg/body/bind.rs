@@ -1,6 +1,6 @@
-use crate::msg::parts::{Format, Text, Value, decode_vec};
-use crate::msg::type_byte::TypeByte;
+use crate::msg::parts::{Format, Text, Value, decode_vec, encode_vec};
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Bind {
@@ -11,8 +11,12 @@ pub struct Bind {
     pub results_formats: Vec<Format>,
 }
 
+impl Bind {
+    pub const TYPE_BYTE: u8 = b'B';
+}
+
 impl MsgDecode for Bind {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Bind);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let prepared_statement_name = Text::decode(bytes)?;
@@ -30,6 +34,19 @@ impl MsgDecode for Bind {
     }
 }
 
+impl MsgEncode for Bind {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.prepared_statement_name.encode(bytes)?;
+        self.portal_name.encode(bytes)?;
+        bytes.put_u16(self.parameters_formats.len() as u16)?;
+        encode_vec(&self.parameters_formats, bytes)?;
+        bytes.put_u16(self.parameters_values.len() as u16)?;
+        encode_vec(&self.parameters_values, bytes)?;
+        bytes.put_u16(self.results_formats.len() as u16)?;
+        encode_vec(&self.results_formats, bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Bind;
@@ -45,7 +62,7 @@ mod tests {
             0, 0,  // no parameters values
             0, 0,  // no results formats
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "".into(),
             portal_name: "".into(),
             parameters_formats: vec![],
@@ -63,7 +80,7 @@ mod tests {
             0, 0,  // no parameters values
             0, 0,  // no results formats
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "pr st".into(),
             portal_name: "".into(),
             parameters_formats: vec![],
@@ -81,7 +98,7 @@ mod tests {
             0, 0,  // no parameters values
             0, 0,  // no results formats
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "".into(),
             portal_name: "portal".into(),
             parameters_formats: vec![],
@@ -102,7 +119,7 @@ mod tests {
             0, 0,  // no parameters values
             0, 0,  // no results formats
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "".into(),
             portal_name: "".into(),
             parameters_formats: vec![Format::Text, Format::Binary, Format::Text],
@@ -125,7 +142,7 @@ mod tests {
             0xff, 0xff, 0xff, 0xff,  // third is -1 which means NULL
             0, 0,  // no results formats
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "".into(),
             portal_name: "".into(),
             parameters_formats: vec![],
@@ -150,7 +167,7 @@ mod tests {
             0, 0,
             0, 1,
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "".into(),
             portal_name: "".into(),
             parameters_formats: vec![],
@@ -178,7 +195,7 @@ mod tests {
             0, 1,
             0, 0,
         ];
-        assert_decode_ok(Bind {
+        assert_codec_ok(Bind {
             prepared_statement_name: "PreP".into(),
             portal_name: "PortaL".into(),
             parameters_formats: vec![
@@ -1,17 +1,27 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct BindComplete();
 
+impl BindComplete {
+    pub const TYPE_BYTE: u8 = b'2';
+}
+
 impl MsgDecode for BindComplete {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::BindComplete);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
         Ok(Self())
     }
 }
 
+impl MsgEncode for BindComplete {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::BindComplete;
@@ -20,6 +30,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes: &[u8] = &[];
-        assert_decode_ok(BindComplete(), bytes);
+        assert_codec_ok(BindComplete(), bytes);
     }
 }
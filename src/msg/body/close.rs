@@ -0,0 +1,71 @@
+use crate::msg::parts::Text;
+use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Close {
+    Portal(Text),
+    PreparedStatement(Text),
+}
+
+impl Close {
+    pub const TYPE_BYTE: u8 = b'C';
+}
+
+impl MsgDecode for Close {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            b'P' => Ok(Self::Portal(Text::decode(bytes)?)),
+            b'S' => Ok(Self::PreparedStatement(Text::decode(bytes)?)),
+            x => Err(Incorrect(format!("closed object should be 'P' or 'S' but is {}", x))),
+        }
+    }
+}
+
+impl MsgEncode for Close {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self {
+            Self::Portal(name) => {
+                bytes.put_u8(b'P')?;
+                name.encode(bytes)
+            },
+            Self::PreparedStatement(name) => {
+                bytes.put_u8(b'S')?;
+                name.encode(bytes)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Close;
+    use crate::msg::util::decode::Problem::*;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn named_portal() {
+        let bytes = &[
+            b'P',  // portal
+            b'P', b'o', b'r', b't', b'a', b'L', 0,  // name
+        ];
+        assert_codec_ok(Close::Portal("PortaL".into()), bytes);
+    }
+
+    #[test]
+    fn unnamed_prepared_statement() {
+        let bytes = &[
+            b'S',  // prepared statement
+            0,  // unnamed
+        ];
+        assert_codec_ok(Close::PreparedStatement("".into()), bytes);
+    }
+
+    #[test]
+    fn unknown_object() {
+        let bytes = b"x";  // neither portal nor prepared statement
+        assert_decode_err::<Close>(Incorrect("closed object should be 'P' or 'S' but is 120".to_owned()), bytes);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -19,7 +19,7 @@ impl CommandComplete {
 }
 
 impl MsgDecode for CommandComplete {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::CommandComplete);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let tag = bytes.take_until_null()?;
@@ -27,6 +27,12 @@ impl MsgDecode for CommandComplete {
     }
 }
 
+impl MsgEncode for CommandComplete {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.tag)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{CommandComplete};
@@ -35,6 +41,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes = b"UPDATE 9000\0";
-        assert_decode_ok(CommandComplete { tag: Vec::from( & b"UPDATE 9000"[..]), }, bytes);
+        assert_codec_ok(CommandComplete { tag: Vec::from( & b"UPDATE 9000"[..]), }, bytes);
     }
 }
@@ -0,0 +1,44 @@
+use crate::msg::parts::Bytes;
+use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyData(pub Bytes);
+
+impl CopyData {
+    pub const TYPE_BYTE: u8 = b'd';
+}
+
+impl MsgDecode for CopyData {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let data = bytes.take_vec(bytes.left())?;
+        Ok(Self(Bytes(data)))
+    }
+}
+
+impl MsgEncode for CopyData {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.0.encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CopyData;
+    use crate::msg::parts::Bytes;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn empty() {
+        let bytes: &[u8] = &[];
+        assert_codec_ok(CopyData(Bytes(vec![])), bytes);
+    }
+
+    #[test]
+    fn row() {
+        let bytes = b"1\tone\n";
+        assert_codec_ok(CopyData(Bytes(b"1\tone\n".to_vec())), bytes);
+    }
+}
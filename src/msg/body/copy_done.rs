@@ -0,0 +1,35 @@
+use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyDone();
+
+impl CopyDone {
+    pub const TYPE_BYTE: u8 = b'c';
+}
+
+impl MsgDecode for CopyDone {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
+        Ok(Self())
+    }
+}
+
+impl MsgEncode for CopyDone {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CopyDone;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn simple() {
+        let bytes: &[u8] = &[];
+        assert_codec_ok(CopyDone(), bytes);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::msg::parts::Text;
+use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyFail {
+    pub error_message: Text,
+}
+
+impl CopyFail {
+    pub const TYPE_BYTE: u8 = b'f';
+}
+
+impl MsgDecode for CopyFail {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let error_message = Text::decode(bytes)?;
+        Ok(Self { error_message })
+    }
+}
+
+impl MsgEncode for CopyFail {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.error_message.encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CopyFail;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn simple() {
+        let bytes = b"aborted by user\0";
+        assert_codec_ok(CopyFail { error_message: "aborted by user".into() }, bytes);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::msg::parts::{Format, decode_vec, encode_vec};
+use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyInResponse(pub CopyFormats);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyOutResponse(pub CopyFormats);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyBothResponse(pub CopyFormats);
+
+impl CopyInResponse {
+    pub const TYPE_BYTE: u8 = b'G';
+}
+
+impl MsgDecode for CopyInResponse {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        CopyFormats::decode(bytes).map(Self)
+    }
+}
+
+impl MsgEncode for CopyInResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.0.encode(bytes)
+    }
+}
+
+impl CopyOutResponse {
+    pub const TYPE_BYTE: u8 = b'H';
+}
+
+impl MsgDecode for CopyOutResponse {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        CopyFormats::decode(bytes).map(Self)
+    }
+}
+
+impl MsgEncode for CopyOutResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.0.encode(bytes)
+    }
+}
+
+impl CopyBothResponse {
+    pub const TYPE_BYTE: u8 = b'W';
+}
+
+impl MsgDecode for CopyBothResponse {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        CopyFormats::decode(bytes).map(Self)
+    }
+}
+
+impl MsgEncode for CopyBothResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.0.encode(bytes)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyFormats {
+    pub overall_format: Format,
+    pub columns_formats: Vec<Format>,
+}
+
+impl PartDecode for CopyFormats {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        // unlike the columns formats, the overall format takes a single byte
+        let overall_format = match bytes.take_u8()? {
+            0 => Format::Text,
+            1 => Format::Binary,
+            x => return Err(Unknown(format!("Unknown format {}", x))),
+        };
+        let columns_formats = decode_vec(bytes.take_u16()? as usize, bytes)?;
+        Ok(Self { overall_format, columns_formats })
+    }
+}
+
+impl PartEncode for CopyFormats {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self.overall_format {
+            Format::Text => bytes.put_u8(0)?,
+            Format::Binary => bytes.put_u8(1)?,
+        }
+        bytes.put_u16(self.columns_formats.len() as u16)?;
+        encode_vec(&self.columns_formats, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CopyBothResponse, CopyFormats, CopyInResponse, CopyOutResponse};
+    use crate::msg::parts::Format;
+    use crate::msg::util::decode::Problem::*;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn copy_in_text() {
+        let bytes = &[
+            0,  // text
+            0, 2,  // 2 columns
+            0, 0,
+            0, 0,
+        ];
+        assert_codec_ok(CopyInResponse(CopyFormats {
+            overall_format: Format::Text,
+            columns_formats: vec![Format::Text, Format::Text],
+        }), bytes);
+    }
+
+    #[test]
+    fn copy_out_binary() {
+        let bytes = &[
+            1,  // binary
+            0, 3,  // 3 columns
+            0, 1,
+            0, 1,
+            0, 1,
+        ];
+        assert_codec_ok(CopyOutResponse(CopyFormats {
+            overall_format: Format::Binary,
+            columns_formats: vec![Format::Binary, Format::Binary, Format::Binary],
+        }), bytes);
+    }
+
+    #[test]
+    fn copy_both_no_columns() {
+        let bytes = &[
+            0,  // text
+            0, 0,  // no columns
+        ];
+        assert_codec_ok(CopyBothResponse(CopyFormats {
+            overall_format: Format::Text,
+            columns_formats: vec![],
+        }), bytes);
+    }
+
+    #[test]
+    fn unknown_overall_format() {
+        let bytes = &[
+            2,  // unknown
+        ];
+        assert_decode_err::<CopyInResponse>(Unknown("Unknown format 2".into()), bytes);
+    }
+}
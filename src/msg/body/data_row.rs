@@ -1,6 +1,6 @@
-use crate::msg::parts::{Value, decode_vec};
-use crate::msg::type_byte::TypeByte;
+use crate::msg::parts::{Value, decode_vec, encode_vec};
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct DataRow {
@@ -12,7 +12,7 @@ impl DataRow {
 }
 
 impl MsgDecode for DataRow {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::DataRow);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let columns = decode_vec(bytes.take_u16()? as usize, bytes)?;
@@ -20,6 +20,13 @@ impl MsgDecode for DataRow {
     }
 }
 
+impl MsgEncode for DataRow {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u16(self.columns.len() as u16)?;
+        encode_vec(&self.columns, bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::DataRow;
@@ -31,7 +38,7 @@ mod tests {
         let bytes: &[u8] = &[
             0, 0,  // columns count
         ];
-        assert_decode_ok(DataRow { columns: vec![] }, bytes);
+        assert_codec_ok(DataRow { columns: vec![] }, bytes);
     }
 
     #[test]
@@ -46,7 +53,7 @@ mod tests {
             0, 0, 0, 3,  //  value len
             12, 34, 56  // value
         ];
-        assert_decode_ok(
+        assert_codec_ok(
             DataRow { columns: vec![
                 Value::Null,
                 Value::Bytes(Bytes(vec![])),
@@ -0,0 +1,71 @@
+use crate::msg::parts::Text;
+use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Describe {
+    Portal(Text),
+    PreparedStatement(Text),
+}
+
+impl Describe {
+    pub const TYPE_BYTE: u8 = b'D';
+}
+
+impl MsgDecode for Describe {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            b'P' => Ok(Self::Portal(Text::decode(bytes)?)),
+            b'S' => Ok(Self::PreparedStatement(Text::decode(bytes)?)),
+            x => Err(Incorrect(format!("described object should be 'P' or 'S' but is {}", x))),
+        }
+    }
+}
+
+impl MsgEncode for Describe {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self {
+            Self::Portal(name) => {
+                bytes.put_u8(b'P')?;
+                name.encode(bytes)
+            },
+            Self::PreparedStatement(name) => {
+                bytes.put_u8(b'S')?;
+                name.encode(bytes)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Describe;
+    use crate::msg::util::decode::Problem::*;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn unnamed_portal() {
+        let bytes = &[
+            b'P',  // portal
+            0,  // unnamed
+        ];
+        assert_codec_ok(Describe::Portal("".into()), bytes);
+    }
+
+    #[test]
+    fn named_prepared_statement() {
+        let bytes = &[
+            b'S',  // prepared statement
+            b'P', b'r', b'e', b'P', 0,  // name
+        ];
+        assert_codec_ok(Describe::PreparedStatement("PreP".into()), bytes);
+    }
+
+    #[test]
+    fn unknown_object() {
+        let bytes = b"X";  // neither portal nor prepared statement
+        assert_decode_err::<Describe>(Incorrect("described object should be 'P' or 'S' but is 88".to_owned()), bytes);
+    }
+}
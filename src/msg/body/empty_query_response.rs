@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct EmptyQueryResponse {}
@@ -9,13 +9,19 @@ impl EmptyQueryResponse {
 }
 
 impl MsgDecode for EmptyQueryResponse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::EmptyQueryResponse);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
         Ok(Self {})
     }
 }
 
+impl MsgEncode for EmptyQueryResponse {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{EmptyQueryResponse};
@@ -24,6 +30,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes = b"";
-        assert_decode_ok(EmptyQueryResponse {}, bytes);
+        assert_codec_ok(EmptyQueryResponse {}, bytes);
     }
 }
@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -13,25 +13,37 @@ impl ErrorResponse {
 }
 
 impl MsgDecode for ErrorResponse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Execute_or_ErrorResponse);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         ErrorOrNoticeFields::decode(bytes).map(Self)
     }
 }
 
+impl MsgEncode for ErrorResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.0.encode(bytes)
+    }
+}
+
 impl NoticeResponse {
     pub const TYPE_BYTE: u8 = b'N';
 }
 
 impl MsgDecode for NoticeResponse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::NoticeResponse);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         ErrorOrNoticeFields::decode(bytes).map(Self)
     }
 }
 
+impl MsgEncode for NoticeResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.0.encode(bytes)
+    }
+}
+
 #[derive(Clone, Default, PartialEq)]
 pub struct ErrorOrNoticeFields {
     // https://www.postgresql.org/docs/current/protocol-error-fields.html
@@ -138,6 +150,24 @@ macro_rules! read_struct_of_opt_fields {
     };
 }
 
+macro_rules! write_struct_of_opt_fields {
+    (
+        $bytes:ident,
+        $self:ident,
+        $($field_type_byte:expr => $field:ident),*
+    ) => {
+        {
+            $(
+                if let Some(value) = &$self.$field {
+                    $bytes.put_u8($field_type_byte)?;
+                    $bytes.put_with_null(value)?;
+                }
+            )*
+            $bytes.put_u8(0)
+        }
+    };
+}
+
 impl ErrorOrNoticeFields {
     fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let mut body = Self { ..Default::default() };
@@ -163,6 +193,29 @@ impl ErrorOrNoticeFields {
         );
         Ok(body)
     }
+
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        write_struct_of_opt_fields!(bytes, self,
+            b'S' => localized_severity,
+            b'V' => severity,
+            b'C' => code,
+            b'M' => message,
+            b'D' => detail,
+            b'H' => hint,
+            b'P' => position,
+            b'p' => internal_position,
+            b'q' => internal_query,
+            b'W' => where_,
+            b's' => schema,
+            b't' => table,
+            b'c' => column,
+            b'd' => data_type,
+            b'n' => constraint,
+            b'F' => file,
+            b'L' => line,
+            b'R' => routine
+        )
+    }
 }
 
 #[cfg(test)]
@@ -173,8 +226,8 @@ mod tests {
     #[test]
     fn no_fields() {
         let bytes: &[u8] = &[0];  // zero instead of field type means "no fields more"
-        assert_decode_ok(ErrorResponse(ErrorOrNoticeFields { .. Default::default() }), bytes);
-        assert_decode_ok(NoticeResponse(ErrorOrNoticeFields { .. Default::default() }), bytes);
+        assert_codec_ok(ErrorResponse(ErrorOrNoticeFields { .. Default::default() }), bytes);
+        assert_codec_ok(NoticeResponse(ErrorOrNoticeFields { .. Default::default() }), bytes);
     }
 
     #[test]
@@ -220,7 +273,7 @@ mod tests {
             line: Some(Vec::from("78")),
             routine: Some(Vec::from("yz")),
         };
-        assert_decode_ok(ErrorResponse(expected_fields()), bytes);
-        assert_decode_ok(NoticeResponse(expected_fields()), bytes);
+        assert_codec_ok(ErrorResponse(expected_fields()), bytes);
+        assert_codec_ok(NoticeResponse(expected_fields()), bytes);
     }
 }
@@ -1,6 +1,6 @@
 use crate::msg::parts::Text;
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Execute {
@@ -8,8 +8,12 @@ pub struct Execute {
     pub rows_limit: u32,
 }
 
+impl Execute {
+    pub const TYPE_BYTE: u8 = b'E';
+}
+
 impl MsgDecode for Execute {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Execute_or_ErrorResponse);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let portal_name = Text::decode(bytes)?;
@@ -21,6 +25,13 @@ impl MsgDecode for Execute {
     }
 }
 
+impl MsgEncode for Execute {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.portal_name.encode(bytes)?;
+        bytes.put_u32(self.rows_limit)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Execute;
@@ -32,7 +43,7 @@ mod tests {
             0,  // unnamed portal
             0x12, 0x34, 0x56, 0x78,  // limited rows
         ];
-        assert_decode_ok(Execute {
+        assert_codec_ok(Execute {
             portal_name: "".into(),
             rows_limit: 0x12345678,
         }, bytes);
@@ -44,7 +55,7 @@ mod tests {
             b'P', b'o', b'r', b't', b'a', b'L', 0,  // named portal
             0, 0, 0, 0,  // unlimited rows
         ];
-        assert_decode_ok(Execute {
+        assert_codec_ok(Execute {
             portal_name: "PortaL".into(),
             rows_limit: 0,
         }, bytes);
@@ -56,7 +67,7 @@ mod tests {
             b'p', b'o', b'R', b't', b'a', b'L', 0,  // named portal
             0x23, 0x45, 0x67, 0x89,  // limited rows
         ];
-        assert_decode_ok(Execute {
+        assert_codec_ok(Execute {
             portal_name: "poRtaL".into(),
             rows_limit: 0x23456789,
         }, bytes);
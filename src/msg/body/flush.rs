@@ -0,0 +1,35 @@
+use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flush();
+
+impl Flush {
+    pub const TYPE_BYTE: u8 = b'H';
+}
+
+impl MsgDecode for Flush {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
+        Ok(Self())
+    }
+}
+
+impl MsgEncode for Flush {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Flush;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn simple() {
+        let bytes: &[u8] = &[];
+        assert_codec_ok(Flush(), bytes);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::msg::parts::{Format, Value, decode_vec, encode_vec};
+use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionCall {
+    pub function_oid: u32,
+    pub arguments_formats: Vec<Format>,
+    pub arguments_values: Vec<Value>,
+    pub result_format: Format,
+}
+
+impl FunctionCall {
+    pub const TYPE_BYTE: u8 = b'F';
+}
+
+impl MsgDecode for FunctionCall {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let function_oid = bytes.take_u32()?;
+        let arguments_formats = decode_vec(bytes.take_u16()? as usize, bytes)?;
+        let arguments_values = decode_vec(bytes.take_u16()? as usize, bytes)?;
+        let result_format = Format::decode(bytes)?;
+        Ok(Self {
+            function_oid,
+            arguments_formats,
+            arguments_values,
+            result_format,
+        })
+    }
+}
+
+impl MsgEncode for FunctionCall {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u32(self.function_oid)?;
+        bytes.put_u16(self.arguments_formats.len() as u16)?;
+        encode_vec(&self.arguments_formats, bytes)?;
+        bytes.put_u16(self.arguments_values.len() as u16)?;
+        encode_vec(&self.arguments_values, bytes)?;
+        self.result_format.encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FunctionCall;
+    use crate::msg::parts::{Bytes, Format, Value};
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn without_arguments() {
+        let bytes = &[
+            0, 0, 0x03, 0xb8,  // function oid
+            0, 0,  // no arguments formats
+            0, 0,  // no arguments values
+            0, 0,  // text result
+        ];
+        assert_codec_ok(FunctionCall {
+            function_oid: 952,
+            arguments_formats: vec![],
+            arguments_values: vec![],
+            result_format: Format::Text,
+        }, bytes);
+    }
+
+    #[test]
+    fn with_arguments() {
+        let bytes = &[
+            0, 0, 0x03, 0xbc,  // function oid
+            0, 1,  // 1 argument format for all the arguments
+            0, 1,
+            0, 3,  // 3 arguments values
+            0, 0, 0, 4,  // first is 4 bytes long
+            0, 0, 0, 7,
+            0xff, 0xff, 0xff, 0xff,  // second is -1 which means NULL
+            0, 0, 0, 0,  // third is 0 bytes long
+            0, 1,  // binary result
+        ];
+        assert_codec_ok(FunctionCall {
+            function_oid: 956,
+            arguments_formats: vec![Format::Binary],
+            arguments_values: vec![
+                Value::Bytes(Bytes(vec![0, 0, 0, 7])),
+                Value::Null,
+                Value::Bytes(Bytes(vec![])),
+            ],
+            result_format: Format::Binary,
+        }, bytes);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::msg::parts::Value;
+use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionCallResponse {
+    pub result: Value,
+}
+
+impl FunctionCallResponse {
+    pub const TYPE_BYTE: u8 = b'V';
+}
+
+impl MsgDecode for FunctionCallResponse {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let result = Value::decode(bytes)?;
+        Ok(Self { result })
+    }
+}
+
+impl MsgEncode for FunctionCallResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.result.encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FunctionCallResponse;
+    use crate::msg::parts::{Bytes, Value};
+    use crate::msg::util::decode::Problem::*;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn null() {
+        let bytes = &[
+            0xff, 0xff, 0xff, 0xff,  // -1 means NULL
+        ];
+        assert_codec_ok(FunctionCallResponse { result: Value::Null }, bytes);
+    }
+
+    #[test]
+    fn value() {
+        let bytes = &[
+            0, 0, 0, 4,  // 4 bytes long
+            0, 0, 0x40, 0x01,
+        ];
+        assert_codec_ok(FunctionCallResponse {
+            result: Value::Bytes(Bytes(vec![0, 0, 0x40, 0x01])),
+        }, bytes);
+    }
+
+    #[test]
+    fn incorrect_length() {
+        let bytes = &[
+            0xff, 0xff, 0xff, 0xfe,  // -2
+        ];
+        assert_decode_err::<FunctionCallResponse>(
+            Incorrect("length of value should be >= -1 but is -2".into()),
+            bytes,
+        );
+    }
+}
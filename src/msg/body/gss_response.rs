@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -12,7 +12,7 @@ impl GssResponse {
 }
 
 impl MsgDecode for GssResponse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::GssResponse_or_Password_or_SaslResponses);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let data = bytes.take_vec(bytes.left())?;
@@ -20,6 +20,12 @@ impl MsgDecode for GssResponse {
     }
 }
 
+impl MsgEncode for GssResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_slice(&self.0)
+    }
+}
+
 impl Debug for GssResponse {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_tuple("GssResponse")
@@ -36,6 +42,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes = &[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc];
-        assert_decode_ok(GssResponse(vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc]), bytes);
+        assert_codec_ok(GssResponse(vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc]), bytes);
     }
 }
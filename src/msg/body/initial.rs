@@ -1,10 +1,11 @@
-use crate::msg::type_byte::TypeByte;
-use crate::msg::util::decode::*;
+use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Initial {
     Cancel(Cancel),
+    GssEnc,
     TLS,
     Startup(Startup),
 }
@@ -12,7 +13,7 @@ pub enum Initial {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Cancel {
     pub process_id: u32,
-    pub secret_key: u32,
+    pub secret_key: Vec<u8>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -22,17 +23,23 @@ pub struct Startup {
 }
 
 impl MsgDecode for Initial {
-    const TYPE_BYTE_OPT: Option<TypeByte> = None;
+    const TYPE_BYTE_OPT: Option<u8> = None;
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         match Version::decode(bytes)? {
             Version { major: 1234, minor: 5678 } => {
                 let process_id = bytes.take_u32()?;
-                let secret_key = bytes.take_u32()?;
+                // the request doesn't tell the protocol version so any allowed length is accepted
+                let secret_key = bytes.take_vec(bytes.left())?;
+                if !(Version::V3_0.secret_key_fits(&secret_key) || Version::V3_2.secret_key_fits(&secret_key)) {
+                    return Err(Incorrect(format!("cancel key of {} bytes", secret_key.len())))
+                }
                 Ok(Self::Cancel(Cancel { process_id, secret_key }))
             },
             Version { major: 1234, minor: 5679 } =>
                 Ok(Self::TLS),
+            Version { major: 1234, minor: 5680 } =>
+                Ok(Self::GssEnc),
             version => {
                 let params = StartupParam::decode_many(bytes)?;
                 Ok(Self::Startup(Startup { version, params }))
@@ -41,17 +48,56 @@ impl MsgDecode for Initial {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl MsgEncode for Initial {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self {
+            Self::Cancel(Cancel { process_id, secret_key }) => {
+                Version { major: 1234, minor: 5678 }.encode(bytes)?;
+                bytes.put_u32(*process_id)?;
+                bytes.put_slice(secret_key)
+            },
+            Self::TLS =>
+                Version { major: 1234, minor: 5679 }.encode(bytes),
+            Self::GssEnc =>
+                Version { major: 1234, minor: 5680 }.encode(bytes),
+            Self::Startup(Startup { version, params }) => {
+                version.encode(bytes)?;
+                StartupParam::encode_many(params, bytes)
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Version {
     pub major: u16,
     pub minor: u16,
 }
 impl Version {
+    pub const V3_0: Self = Self { major: 3, minor: 0 };
+    pub const V3_2: Self = Self { major: 3, minor: 2 };
+
+    pub const SECRET_KEY_MAX_LEN: usize = 256;
+
+    // Since 3.2 secret keys in BackendKeyData and CancelRequest may be of any length up to 256 bytes.
+    pub fn secret_key_fits(&self, secret_key: &[u8]) -> bool {
+        if self.major == 3 && self.minor >= 2 {
+            secret_key.len() <= Self::SECRET_KEY_MAX_LEN
+        } else {
+            secret_key.len() == 4
+        }
+    }
+
     fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let major = bytes.take_u16()?;
         let minor = bytes.take_u16()?;
         Ok(Version { major, minor })
     }
+
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u16(self.major)?;
+        bytes.put_u16(self.minor)
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -76,6 +122,14 @@ impl StartupParam {
         }
         Ok(params)
     }
+
+    fn encode_many(params: &[Self], bytes: &mut BytesTarget) -> EncodeResult<()> {
+        for param in params {
+            bytes.put_with_null(&param.name)?;
+            bytes.put_with_null(&param.value)?;
+        }
+        bytes.put_u8(0)
+    }
 }
 impl Debug for StartupParam {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
@@ -90,6 +144,7 @@ impl Debug for StartupParam {
 #[cfg(test)]
 mod tests {
     use super::{Cancel, Initial, Startup, StartupParam, Version};
+    use crate::msg::util::decode::Problem::*;
     use crate::msg::util::test::*;
 
     #[test]
@@ -99,7 +154,37 @@ mod tests {
             0x1, 0x2, 0x3, 0x4,  // process ID
             0x5, 0x6, 0x7, 0x8,  // secret key
         ];
-        assert_decode_ok(Initial::Cancel(Cancel { process_id: 0x01020304, secret_key: 0x05060708 }), bytes);
+        assert_codec_ok(Initial::Cancel(Cancel { process_id: 0x01020304, secret_key: vec![5, 6, 7, 8] }), bytes);
+    }
+
+    #[test]
+    fn cancel_with_long_key() {
+        let mut bytes = vec![
+            4, 210, 22, 46, // 4*256+210=1234, 22*256+46=5678, these numbers instead of version mean "cancel"
+            0x1, 0x2, 0x3, 0x4,  // process ID
+        ];
+        bytes.extend_from_slice(&[7; 32]);  // secret key since protocol 3.2
+        assert_codec_ok(Initial::Cancel(Cancel { process_id: 0x01020304, secret_key: vec![7; 32] }), &bytes);
+    }
+
+    #[test]
+    fn cancel_with_too_long_key() {
+        let mut bytes = vec![
+            4, 210, 22, 46, // 4*256+210=1234, 22*256+46=5678, these numbers instead of version mean "cancel"
+            0x1, 0x2, 0x3, 0x4,  // process ID
+        ];
+        bytes.extend_from_slice(&[7; 257]);
+        assert_decode_err::<Initial>(Incorrect("cancel key of 257 bytes".into()), &bytes);
+    }
+
+    #[test]
+    fn secret_key_fits() {
+        assert!(Version::V3_0.secret_key_fits(&[1, 2, 3, 4]));
+        assert!(!Version::V3_0.secret_key_fits(&[1; 32]));
+        assert!(Version::V3_2.secret_key_fits(&[1, 2, 3, 4]));
+        assert!(Version::V3_2.secret_key_fits(&[1; 256]));
+        assert!(!Version::V3_2.secret_key_fits(&[1; 257]));
+        assert!(Version { major: 3, minor: 3 }.secret_key_fits(&[1; 32]));
     }
 
     #[test]
@@ -107,7 +192,15 @@ mod tests {
         let bytes: &[u8] = &[
             4, 210, 22, 47, // 4*256+210=1234, 22*256+47=5679, these numbers instead of version mean "TLS"
         ];
-        assert_decode_ok(Initial::TLS, bytes);
+        assert_codec_ok(Initial::TLS, bytes);
+    }
+
+    #[test]
+    fn gss_enc() {
+        let bytes: &[u8] = &[
+            4, 210, 22, 48, // 4*256+210=1234, 22*256+48=5680, these numbers instead of version mean "GSS encryption"
+        ];
+        assert_codec_ok(Initial::GssEnc, bytes);
     }
 
     #[test]
@@ -116,7 +209,7 @@ mod tests {
             0, 3, 0, 1, // version
             0, // params
         ];
-        assert_decode_ok(
+        assert_codec_ok(
             Initial::Startup(Startup {
                 version: Version { major: 3, minor: 1 },
                 params: vec![],
@@ -132,7 +225,7 @@ mod tests {
         ];
         bytes.extend_from_slice(b"user\0root\0database\0postgres\0\0");
         let bytes = bytes.as_slice();
-        assert_decode_ok(
+        assert_codec_ok(
             Initial::Startup(Startup {
                 version: Version { major: 3, minor: 0x100 },
                 params: vec![
@@ -1,6 +1,6 @@
-use crate::msg::parts::{Text, decode_vec};
-use crate::msg::type_byte::TypeByte;
+use crate::msg::parts::{Text, decode_vec, encode_vec};
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct NegotiateProtocolVersion {
@@ -13,7 +13,7 @@ impl NegotiateProtocolVersion {
 }
 
 impl MsgDecode for NegotiateProtocolVersion {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::NegotiateProtocolVersion);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let newest_backend_minor = bytes.take_u32()?;
@@ -22,6 +22,14 @@ impl MsgDecode for NegotiateProtocolVersion {
     }
 }
 
+impl MsgEncode for NegotiateProtocolVersion {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u32(self.newest_backend_minor)?;
+        bytes.put_u32(self.unrecognized_options.len() as u32)?;
+        encode_vec(&self.unrecognized_options, bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::NegotiateProtocolVersion;
@@ -33,7 +41,7 @@ mod tests {
             0x12, 0x34, 0x56, 0x78,  // newest backend minor version
                0,    0,    0,    0,  // count of unrecognized options
         ];
-        assert_decode_ok(
+        assert_codec_ok(
             NegotiateProtocolVersion {
                 newest_backend_minor: 0x12345678,
                 unrecognized_options: vec![]
@@ -52,7 +60,7 @@ mod tests {
         bytes.extend_from_slice(b"second\0");
         bytes.extend_from_slice(b"third\0");
         let bytes = bytes.as_slice();
-        assert_decode_ok(
+        assert_codec_ok(
             NegotiateProtocolVersion {
                 newest_backend_minor: 0x12345678,
                 unrecognized_options: vec![
@@ -0,0 +1,67 @@
+use crate::msg::parts::Text;
+use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotificationResponse {
+    pub process_id: u32,
+    pub channel: Text,
+    pub payload: Text,
+}
+
+impl NotificationResponse {
+    pub const TYPE_BYTE: u8 = b'A';
+}
+
+impl MsgDecode for NotificationResponse {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let process_id = bytes.take_u32()?;
+        let channel = Text::decode(bytes)?;
+        let payload = Text::decode(bytes)?;
+        Ok(Self { process_id, channel, payload })
+    }
+}
+
+impl MsgEncode for NotificationResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u32(self.process_id)?;
+        self.channel.encode(bytes)?;
+        self.payload.encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotificationResponse;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn without_payload() {
+        let bytes = &[
+            0, 0, 0x30, 0x39,  // process id
+            b'j', b'o', b'b', b's', 0,  // channel
+            0,  // empty payload
+        ];
+        assert_codec_ok(NotificationResponse {
+            process_id: 12345,
+            channel: "jobs".into(),
+            payload: "".into(),
+        }, bytes);
+    }
+
+    #[test]
+    fn with_payload() {
+        let bytes = &[
+            0, 0, 0x30, 0x39,  // process id
+            b'j', b'o', b'b', b's', 0,  // channel
+            b'4', b'2', 0,  // payload
+        ];
+        assert_codec_ok(NotificationResponse {
+            process_id: 12345,
+            channel: "jobs".into(),
+            payload: "42".into(),
+        }, bytes);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::msg::parts::{decode_vec, encode_vec};
+use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterDescription {
+    pub parameters_types: Vec<u32>,
+}
+
+impl ParameterDescription {
+    pub const TYPE_BYTE: u8 = b't';
+}
+
+impl MsgDecode for ParameterDescription {
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let parameters_types = decode_vec(bytes.take_u16()? as usize, bytes)?;
+        Ok(Self { parameters_types })
+    }
+}
+
+impl MsgEncode for ParameterDescription {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u16(self.parameters_types.len() as u16)?;
+        encode_vec(&self.parameters_types, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParameterDescription;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn no_parameters() {
+        let bytes: &[u8] = &[
+            0, 0,  // parameters count
+        ];
+        assert_codec_ok(ParameterDescription { parameters_types: vec![] }, bytes);
+    }
+
+    #[test]
+    fn many_parameters() {
+        let bytes: &[u8] = &[
+            0, 3,  // parameters count
+            0x12, 0x34, 0x56, 0x78,
+            0x23, 0x45, 0x67, 0x89,
+            0x35, 0x79, 0xbd, 0xf1,
+        ];
+        assert_codec_ok(ParameterDescription {
+            parameters_types: vec![0x12345678, 0x23456789, 0x3579bdf1],
+        }, bytes);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -17,7 +17,7 @@ impl ParameterStatus {
 }
 
 impl MsgDecode for ParameterStatus {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::ParameterStatus_or_Sync);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let name = bytes.take_until_null()?;
@@ -26,6 +26,13 @@ impl MsgDecode for ParameterStatus {
     }
 }
 
+impl MsgEncode for ParameterStatus {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.name)?;
+        bytes.put_with_null(&self.value)
+    }
+}
+
 impl Debug for ParameterStatus {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_struct("ParameterStatus")
@@ -44,7 +51,7 @@ mod tests {
     #[test]
     fn simple() {
         let bytes = b"TimeZone\0UTC\0";
-        assert_decode_ok(
+        assert_codec_ok(
             ParameterStatus {
                 name: Vec::from(&b"TimeZone"[..]),
                 value: Vec::from(&b"UTC"[..]),
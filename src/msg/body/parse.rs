@@ -1,6 +1,6 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
-use crate::msg::parts::{Text, decode_vec};
+use crate::msg::parts::{Text, decode_vec, encode_vec};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Parse {
@@ -9,8 +9,12 @@ pub struct Parse {
     pub parameters_types: Vec<u32>,
 }
 
+impl Parse {
+    pub const TYPE_BYTE: u8 = b'P';
+}
+
 impl MsgDecode for Parse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Parse);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let prepared_statement_name = Text::decode(bytes)?;
@@ -20,6 +24,15 @@ impl MsgDecode for Parse {
     }
 }
 
+impl MsgEncode for Parse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        self.prepared_statement_name.encode(bytes)?;
+        self.query.encode(bytes)?;
+        bytes.put_u16(self.parameters_types.len() as u16)?;
+        encode_vec(&self.parameters_types, bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Parse;
@@ -32,7 +45,7 @@ mod tests {
             b'S', b'Q', b'L', 0,  // SQL query
             0, 0,  // no parameters types
         ];
-        assert_decode_ok(Parse {
+        assert_codec_ok(Parse {
             prepared_statement_name: "".into(),
             query: "SQL".into(),
             parameters_types: vec![],
@@ -46,7 +59,7 @@ mod tests {
             b's', b'q', b'l', 0,  // SQL query
             0, 0,  // no parameters types
         ];
-        assert_decode_ok(Parse {
+        assert_codec_ok(Parse {
             prepared_statement_name: "Name".into(),
             query: "sql".into(),
             parameters_types: vec![],
@@ -63,7 +76,7 @@ mod tests {
             0x23, 0x45, 0x67, 0x89,
             0x35, 0x79, 0xbd, 0xf1,
         ];
-        assert_decode_ok(Parse {
+        assert_codec_ok(Parse {
             prepared_statement_name: "Name".into(),
             query: "sql".into(),
             parameters_types: vec![0x12345678, 0x23456789, 0x3579bdf1],
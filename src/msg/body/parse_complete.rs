@@ -1,17 +1,27 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ParseComplete();
 
+impl ParseComplete {
+    pub const TYPE_BYTE: u8 = b'1';
+}
+
 impl MsgDecode for ParseComplete {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::ParseComplete);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
         Ok(Self())
     }
 }
 
+impl MsgEncode for ParseComplete {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::ParseComplete;
@@ -20,6 +30,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes: &[u8] = &[];
-        assert_decode_ok(ParseComplete(), bytes);
+        assert_codec_ok(ParseComplete(), bytes);
     }
 }
@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -12,7 +12,7 @@ impl Password {
 }
 
 impl MsgDecode for Password {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::GssResponse_or_Password_or_SaslResponses);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let password = bytes.take_until_null()?;
@@ -20,6 +20,12 @@ impl MsgDecode for Password {
     }
 }
 
+impl MsgEncode for Password {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.0)
+    }
+}
+
 impl Debug for Password {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_tuple("Password")
@@ -36,6 +42,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes = b"qwerty123\0";
-        assert_decode_ok(Password("qwerty123".into()), bytes);
+        assert_codec_ok(Password("qwerty123".into()), bytes);
     }
 }
@@ -1,17 +1,27 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct PortalSuspended();
 
+impl PortalSuspended {
+    pub const TYPE_BYTE: u8 = b's';
+}
+
 impl MsgDecode for PortalSuspended {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::PortalSuspended);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
         Ok(Self())
     }
 }
 
+impl MsgEncode for PortalSuspended {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::PortalSuspended;
@@ -20,6 +30,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes: &[u8] = &[];
-        assert_decode_ok(PortalSuspended(), bytes);
+        assert_codec_ok(PortalSuspended(), bytes);
     }
 }
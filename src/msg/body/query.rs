@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -12,7 +12,7 @@ impl Query {
 }
 
 impl MsgDecode for Query {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Query);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let query = bytes.take_until_null()?;
@@ -20,6 +20,12 @@ impl MsgDecode for Query {
     }
 }
 
+impl MsgEncode for Query {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.0)
+    }
+}
+
 impl Debug for Query {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_tuple("Query")
@@ -36,6 +42,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes = b"select 1;\0";
-        assert_decode_ok(Query(Vec::from("select 1;")), bytes);
+        assert_codec_ok(Query(Vec::from("select 1;")), bytes);
     }
 }
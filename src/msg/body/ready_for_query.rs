@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ReadyForQuery {
@@ -18,7 +18,7 @@ impl ReadyForQuery {
 }
 
 impl MsgDecode for ReadyForQuery {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::ReadyForQuery);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let status = match bytes.take_u8()? {
@@ -31,6 +31,16 @@ impl MsgDecode for ReadyForQuery {
     }
 }
 
+impl MsgEncode for ReadyForQuery {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u8(match self.status {
+            Status::Idle => b'I',
+            Status::Transaction => b'T',
+            Status::Error => b'E',
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{ReadyForQuery, Status::*};
@@ -40,19 +50,19 @@ mod tests {
     #[test]
     fn idle() {
         let bytes = b"I";
-        assert_decode_ok(ReadyForQuery { status: Idle }, bytes);
+        assert_codec_ok(ReadyForQuery { status: Idle }, bytes);
     }
 
     #[test]
     fn transaction() {
         let bytes = b"T";
-        assert_decode_ok(ReadyForQuery { status: Transaction }, bytes);
+        assert_codec_ok(ReadyForQuery { status: Transaction }, bytes);
     }
 
     #[test]
     fn error() {
         let bytes = b"E";
-        assert_decode_ok(ReadyForQuery { status: Error }, bytes);
+        assert_codec_ok(ReadyForQuery { status: Error }, bytes);
     }
 
     #[test]
@@ -1,6 +1,6 @@
-use crate::msg::parts::{Format, decode_vec};
-use crate::msg::type_byte::TypeByte;
+use crate::msg::parts::{Format, decode_vec, encode_vec};
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode, PartEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -38,7 +38,7 @@ impl RowDescription {
 }
 
 impl MsgDecode for RowDescription {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::RowDescription);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let fields = decode_vec(bytes.take_u16()? as usize, bytes)?;
@@ -46,6 +46,13 @@ impl MsgDecode for RowDescription {
     }
 }
 
+impl MsgEncode for RowDescription {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u16(self.fields.len() as u16)?;
+        encode_vec(&self.fields, bytes)
+    }
+}
+
 impl PartDecode for Field {
     fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let name = bytes.take_until_null()?;
@@ -59,6 +66,18 @@ impl PartDecode for Field {
     }
 }
 
+impl PartEncode for Field {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.name)?;
+        bytes.put_u32(self.column_oid)?;
+        bytes.put_u16(self.column_attr_num)?;
+        bytes.put_u32(self.type_oid)?;
+        bytes.put_u16(self.type_size as u16)?;
+        bytes.put_u32(self.type_modifier as u32)?;
+        self.format.encode(bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{RowDescription, Field, Format::*};
@@ -69,7 +88,7 @@ mod tests {
         let bytes: &[u8] = &[
             0, 0,  // fields count
         ];
-        assert_decode_ok(RowDescription { fields: vec![] }, bytes);
+        assert_codec_ok(RowDescription { fields: vec![] }, bytes);
     }
 
     #[test]
@@ -93,7 +112,7 @@ mod tests {
             0x23, 0x22, 0x21, 0x20,  // type modifier
             0, 1,  //  format=binary
         ];
-        assert_decode_ok(
+        assert_codec_ok(
             RowDescription { fields: vec![
                 Field {
                     name: Vec::from("First"),
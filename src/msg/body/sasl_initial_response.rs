@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -8,8 +8,12 @@ pub struct SaslInitialResponse {
     pub mechanism_data: Option<Vec<u8>>,
 }
 
+impl SaslInitialResponse {
+    pub const TYPE_BYTE: u8 = b'p';
+}
+
 impl MsgDecode for SaslInitialResponse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::GssResponse_or_Password_or_SaslResponses);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let selected_mechanism = bytes.take_until_null()?;
@@ -25,6 +29,19 @@ impl MsgDecode for SaslInitialResponse {
     }
 }
 
+impl MsgEncode for SaslInitialResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.selected_mechanism)?;
+        match &self.mechanism_data {
+            None => bytes.put_u32(-1i32 as u32),
+            Some(data) => {
+                bytes.put_u32(data.len() as u32)?;
+                bytes.put_slice(data)
+            },
+        }
+    }
+}
+
 impl Debug for SaslInitialResponse {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_struct("SaslInitialResponse")
@@ -45,7 +62,7 @@ mod tests {
             b'O', b'T', b'P', 0,  // selected mechanism
             0xff, 0xff, 0xff, 0xff,  // -1 which means "no mechanism data"
         ];
-        assert_decode_ok(SaslInitialResponse {
+        assert_codec_ok(SaslInitialResponse {
             selected_mechanism: Vec::from("OTP"),
             mechanism_data: None,
         }, bytes);
@@ -58,7 +75,7 @@ mod tests {
             0, 0, 0, 3,  // len of mechanism data
             0x12, 0x34, 0x56,
         ];
-        assert_decode_ok(SaslInitialResponse {
+        assert_codec_ok(SaslInitialResponse {
             selected_mechanism: Vec::from("SKEY"),
             mechanism_data: Some(vec![0x12, 0x34, 0x56]),
         }, bytes);
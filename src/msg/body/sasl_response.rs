@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 use ::std::fmt::{self, Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
@@ -7,8 +7,12 @@ pub struct SaslResponse {
     pub mechanism_data: Vec<u8>,
 }
 
+impl SaslResponse {
+    pub const TYPE_BYTE: u8 = b'p';
+}
+
 impl MsgDecode for SaslResponse {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::GssResponse_or_Password_or_SaslResponses);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
         let mechanism_data = bytes.take_vec(bytes.left())?;
@@ -16,6 +20,12 @@ impl MsgDecode for SaslResponse {
     }
 }
 
+impl MsgEncode for SaslResponse {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_slice(&self.mechanism_data)
+    }
+}
+
 impl Debug for SaslResponse {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_struct("SaslResponse")
@@ -34,7 +44,7 @@ mod tests {
         let bytes = &[
             0x12, 0x34, 0x56,
         ];
-        assert_decode_ok(SaslResponse {
+        assert_codec_ok(SaslResponse {
             mechanism_data: vec![0x12, 0x34, 0x56],
         }, bytes);
     }
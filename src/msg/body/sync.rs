@@ -1,17 +1,27 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Sync();
 
+impl Sync {
+    pub const TYPE_BYTE: u8 = b'S';
+}
+
 impl MsgDecode for Sync {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::ParameterStatus_or_Sync);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
         Ok(Self())
     }
 }
 
+impl MsgEncode for Sync {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Sync;
@@ -20,6 +30,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes: &[u8] = &[];
-        assert_decode_ok(Sync(), bytes);
+        assert_codec_ok(Sync(), bytes);
     }
 }
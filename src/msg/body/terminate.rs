@@ -1,5 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, MsgEncode};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Terminate {}
@@ -9,13 +9,19 @@ impl Terminate {
 }
 
 impl MsgDecode for Terminate {
-    const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::Terminate);
+    const TYPE_BYTE_OPT: Option<u8> = Some(Self::TYPE_BYTE);
 
     fn decode_body(_: &mut BytesSource) -> DecodeResult<Self> {
         Ok(Self {})
     }
 }
 
+impl MsgEncode for Terminate {
+    fn encode_body(&self, _: &mut BytesTarget) -> EncodeResult<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Terminate;
@@ -24,6 +30,6 @@ mod tests {
     #[test]
     fn simple() {
         let bytes: &[u8] = &[];
-        assert_decode_ok(Terminate {}, bytes);
+        assert_codec_ok(Terminate {}, bytes);
     }
 }
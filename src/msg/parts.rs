@@ -1,4 +1,5 @@
 use crate::msg::util::decode::{*, Problem::*};
+use crate::msg::util::encode::{BytesTarget, EncodeResult, PartEncode};
 use ::std::fmt::{self, Debug, Formatter};
 use ::hex;
 
@@ -42,6 +43,13 @@ impl From<&str> for Text {
     }
 }
 
+impl PartDecode for Bytes {
+    // without a length ahead the bytes take all the rest
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        bytes.take_vec(bytes.left()).map(Self)
+    }
+}
+
 impl PartDecode for Format {
     fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
         match bytes.take_u16()? {
@@ -77,10 +85,56 @@ impl PartDecode for u32 {
     }
 }
 
+impl PartEncode for Bytes {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_slice(&self.0)
+    }
+}
+
+impl PartEncode for Format {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self {
+            Self::Text => bytes.put_u16(0),
+            Self::Binary => bytes.put_u16(1),
+        }
+    }
+}
+
+impl PartEncode for Text {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_with_null(&self.0)
+    }
+}
+
+impl PartEncode for Value {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        match self {
+            Self::Null => bytes.put_u32(-1i32 as u32),
+            Self::Bytes(value) => {
+                bytes.put_u32(value.0.len() as u32)?;
+                value.encode(bytes)
+            },
+        }
+    }
+}
+
+impl PartEncode for u32 {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()> {
+        bytes.put_u32(*self)
+    }
+}
+
 pub fn decode_vec<Part: PartDecode> (count: usize, bytes: &mut BytesSource) -> DecodeResult<Vec<Part>> {
-    let mut items = Vec::with_capacity(count as usize);
+    let mut items = Vec::with_capacity(count);
     for _ in 0..count {
         items.push(Part::decode(bytes)?);
     }
     Ok(items)
 }
+
+pub fn encode_vec<Part: PartEncode> (items: &[Part], bytes: &mut BytesTarget) -> EncodeResult<()> {
+    for item in items {
+        item.encode(bytes)?;
+    }
+    Ok(())
+}
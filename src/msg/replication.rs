@@ -0,0 +1,144 @@
+pub mod hot_standby_feedback;
+pub mod pgoutput;
+pub mod primary_keepalive;
+pub mod standby_status_update;
+pub mod xlog_data;
+
+pub use hot_standby_feedback::HotStandbyFeedback;
+pub use pgoutput::PgOutputMsg;
+pub use primary_keepalive::PrimaryKeepalive;
+pub use standby_status_update::StandbyStatusUpdate;
+pub use xlog_data::XLogData;
+
+use crate::msg::body::CopyData;
+use crate::msg::body::initial::Startup;
+use crate::msg::parts::Bytes;
+use crate::msg::util::decode::{*, Problem::*};
+use ::std::fmt::{self, Debug, Formatter};
+
+// https://www.postgresql.org/docs/current/protocol-replication.html
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplicationMode {
+    Physical,
+    Logical,
+}
+
+impl ReplicationMode {
+    pub fn of(startup: &Startup) -> Option<Self> {
+        let param = startup.params.iter().find(|param| param.name == b"replication")?;
+        match param.value.to_ascii_lowercase().as_slice() {
+            b"database" => Some(Self::Logical),
+            b"true" | b"on" | b"yes" | b"1" => Some(Self::Physical),
+            _ => None,
+        }
+    }
+}
+
+// CopyData payloads sent by backend (WAL sender) inside CopyBoth.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalSenderMsg<Data = Bytes> {
+    PrimaryKeepalive(PrimaryKeepalive),
+    XLogData(XLogData<Data>),
+}
+
+pub type PgOutputWalSenderMsg = WalSenderMsg<PgOutputMsg>;
+
+// CopyData payloads sent by frontend (WAL receiver) inside CopyBoth.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalReceiverMsg {
+    HotStandbyFeedback(HotStandbyFeedback),
+    StandbyStatusUpdate(StandbyStatusUpdate),
+}
+
+impl<Data: PartDecode> MsgDecode for WalSenderMsg<Data> {
+    const TYPE_BYTE_OPT: Option<u8> = Some(CopyData::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            XLogData::<Data>::TYPE_BYTE => XLogData::decode(bytes).map(Self::XLogData),
+            PrimaryKeepalive::TYPE_BYTE => PrimaryKeepalive::decode(bytes).map(Self::PrimaryKeepalive),
+            x => Err(Unknown(format!("Unknown WAL sender message {}", x))),
+        }
+    }
+}
+
+impl MsgDecode for WalReceiverMsg {
+    const TYPE_BYTE_OPT: Option<u8> = Some(CopyData::TYPE_BYTE);
+
+    fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            StandbyStatusUpdate::TYPE_BYTE => StandbyStatusUpdate::decode(bytes).map(Self::StandbyStatusUpdate),
+            HotStandbyFeedback::TYPE_BYTE => HotStandbyFeedback::decode(bytes).map(Self::HotStandbyFeedback),
+            x => Err(Unknown(format!("Unknown WAL receiver message {}", x))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Lsn(pub u64);
+
+impl Debug for Lsn {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 as u32)
+    }
+}
+
+impl PartDecode for Lsn {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        bytes.take_u64().map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lsn, ReplicationMode, WalReceiverMsg, WalSenderMsg};
+    use crate::msg::body::initial::{Startup, StartupParam, Version};
+    use crate::msg::util::decode::Problem::*;
+    use crate::msg::util::test::*;
+
+    fn startup(params: &[(&str, &str)]) -> Startup {
+        Startup {
+            version: Version { major: 3, minor: 0 },
+            params: params.iter().map(|(name, value)| {
+                StartupParam::new(Vec::from(*name), Vec::from(*value))
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn no_replication() {
+        assert_eq!(None, ReplicationMode::of(&startup(&[("user", "postgres")])));
+        assert_eq!(None, ReplicationMode::of(&startup(&[("replication", "off")])));
+    }
+
+    #[test]
+    fn physical_replication() {
+        assert_eq!(Some(ReplicationMode::Physical), ReplicationMode::of(&startup(&[("replication", "true")])));
+        assert_eq!(Some(ReplicationMode::Physical), ReplicationMode::of(&startup(&[("replication", "On")])));
+    }
+
+    #[test]
+    fn logical_replication() {
+        let startup = startup(&[("user", "postgres"), ("replication", "database")]);
+        assert_eq!(Some(ReplicationMode::Logical), ReplicationMode::of(&startup));
+    }
+
+    #[test]
+    fn lsn_debug() {
+        assert_eq!("16/B374D848", format!("{:?}", Lsn(0x16_B374D848)));
+        assert_eq!("0/0", format!("{:?}", Lsn(0)));
+    }
+
+    #[test]
+    fn unknown_wal_sender_msg() {
+        let bytes = b"r";
+        assert_decode_err::<WalSenderMsg>(Unknown("Unknown WAL sender message 114".into()), bytes);
+    }
+
+    #[test]
+    fn unknown_wal_receiver_msg() {
+        let bytes = b"w";
+        assert_decode_err::<WalReceiverMsg>(Unknown("Unknown WAL receiver message 119".into()), bytes);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::msg::util::decode::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotStandbyFeedback {
+    pub clock: i64,  // microseconds since 2000-01-01 00:00:00 UTC
+    pub xmin: u32,
+    pub xmin_epoch: u32,
+    pub catalog_xmin: u32,
+    pub catalog_xmin_epoch: u32,
+}
+
+impl HotStandbyFeedback {
+    pub const TYPE_BYTE: u8 = b'h';
+}
+
+impl PartDecode for HotStandbyFeedback {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let clock = bytes.take_u64()? as i64;
+        let xmin = bytes.take_u32()?;
+        let xmin_epoch = bytes.take_u32()?;
+        let catalog_xmin = bytes.take_u32()?;
+        let catalog_xmin_epoch = bytes.take_u32()?;
+        Ok(Self { clock, xmin, xmin_epoch, catalog_xmin, catalog_xmin_epoch })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HotStandbyFeedback;
+    use crate::msg::replication::WalReceiverMsg;
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn simple() {
+        let bytes = &[
+            b'h',
+            0, 0x02, 0x9a, 0x3b, 0x1c, 0x5f, 0x2e, 0x10,  // clock
+            0, 0, 0x02, 0xf1,  // xmin
+            0, 0, 0, 1,  // xmin epoch
+            0, 0, 0x02, 0xe0,  // catalog xmin
+            0, 0, 0, 1,  // catalog xmin epoch
+        ];
+        assert_decode_ok(WalReceiverMsg::HotStandbyFeedback(HotStandbyFeedback {
+            clock: 0x029a3b1c5f2e10,
+            xmin: 753,
+            xmin_epoch: 1,
+            catalog_xmin: 736,
+            catalog_xmin_epoch: 1,
+        }), bytes);
+    }
+}
@@ -0,0 +1,500 @@
+use crate::msg::body::Query;
+use crate::msg::parts::{Bytes, Format, Text, Value, decode_vec};
+use crate::msg::replication::Lsn;
+use crate::msg::util::decode::{*, Problem::*};
+
+// https://www.postgresql.org/docs/current/protocol-logicalrep-message-formats.html
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PgOutputMsg {
+    Begin(Begin),
+    Commit(Commit),
+    Delete(Delete),
+    Insert(Insert),
+    Origin(Origin),
+    Relation(Relation),
+    Truncate(Truncate),
+    Type(Type),
+    Update(Update),
+    // The type byte and the rest of any other message, like logical decoding messages, the stream and
+    // two-phase commit ones, so a subscriber asking for them keeps working.
+    Other(u8, Bytes),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Begin {
+    pub final_lsn: Lsn,
+    pub commit_time: i64,  // microseconds since 2000-01-01 00:00:00 UTC
+    pub xid: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commit {
+    pub flags: u8,
+    pub commit_lsn: Lsn,
+    pub end_lsn: Lsn,
+    pub commit_time: i64,  // microseconds since 2000-01-01 00:00:00 UTC
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delete {
+    pub relation_oid: u32,
+    pub old_tuple: OldTuple,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insert {
+    pub relation_oid: u32,
+    pub new_tuple: TupleData,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin {
+    pub commit_lsn: Lsn,
+    pub name: Text,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relation {
+    pub oid: u32,
+    pub namespace: Text,
+    pub name: Text,
+    pub replica_identity: ReplicaIdentity,
+    pub columns: Vec<RelationColumn>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Truncate {
+    pub cascade: bool,
+    pub restart_identity: bool,
+    pub relations_oids: Vec<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Type {
+    pub oid: u32,
+    pub namespace: Text,
+    pub name: Text,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    pub relation_oid: u32,
+    pub old_tuple: Option<OldTuple>,
+    pub new_tuple: TupleData,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplicaIdentity {
+    Default,
+    Nothing,
+    Full,
+    Index,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelationColumn {
+    pub is_key: bool,
+    pub name: Text,
+    pub type_oid: u32,
+    pub type_modifier: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OldTuple {
+    Key(TupleData),
+    Full(TupleData),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TupleData {
+    pub columns: Vec<TupleColumn>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TupleColumn {
+    UnchangedToast,
+    Value(Format, Value),  // NULL comes without any format, so it's reported as Text
+}
+
+pub fn is_started_by(query: &Query) -> bool {
+    // pgoutput is the only built-in plugin requiring publication_names option
+    let query = query.0.to_ascii_uppercase();
+    let command_start = query.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(query.len());
+    let contains = |word: &[u8]| query.windows(word.len()).any(|window| window == word);
+    query[command_start..].starts_with(b"START_REPLICATION")
+        && contains(b" LOGICAL ")
+        && contains(b"PUBLICATION_NAMES")
+}
+
+impl PartDecode for PgOutputMsg {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            b'B' => {
+                let final_lsn = Lsn::decode(bytes)?;
+                let commit_time = bytes.take_u64()? as i64;
+                let xid = bytes.take_u32()?;
+                Ok(Self::Begin(Begin { final_lsn, commit_time, xid }))
+            },
+            b'C' => {
+                let flags = bytes.take_u8()?;
+                let commit_lsn = Lsn::decode(bytes)?;
+                let end_lsn = Lsn::decode(bytes)?;
+                let commit_time = bytes.take_u64()? as i64;
+                Ok(Self::Commit(Commit { flags, commit_lsn, end_lsn, commit_time }))
+            },
+            b'D' => {
+                let relation_oid = bytes.take_u32()?;
+                let old_tuple = OldTuple::decode(bytes)?;
+                Ok(Self::Delete(Delete { relation_oid, old_tuple }))
+            },
+            b'I' => {
+                let relation_oid = bytes.take_u32()?;
+                let new_tuple = decode_new_tuple(bytes)?;
+                Ok(Self::Insert(Insert { relation_oid, new_tuple }))
+            },
+            b'O' => {
+                let commit_lsn = Lsn::decode(bytes)?;
+                let name = Text::decode(bytes)?;
+                Ok(Self::Origin(Origin { commit_lsn, name }))
+            },
+            b'R' => {
+                let oid = bytes.take_u32()?;
+                let namespace = Text::decode(bytes)?;
+                let name = Text::decode(bytes)?;
+                let replica_identity = ReplicaIdentity::decode(bytes)?;
+                let columns = decode_vec(bytes.take_u16()? as usize, bytes)?;
+                Ok(Self::Relation(Relation { oid, namespace, name, replica_identity, columns }))
+            },
+            b'T' => {
+                let relations_count = bytes.take_u32()? as usize;
+                let options = bytes.take_u8()?;
+                let relations_oids = decode_vec(relations_count, bytes)?;
+                Ok(Self::Truncate(Truncate {
+                    cascade: options & 1 != 0,
+                    restart_identity: options & 2 != 0,
+                    relations_oids,
+                }))
+            },
+            b'U' => {
+                let relation_oid = bytes.take_u32()?;
+                let (old_tuple, new_tuple) = match bytes.take_u8()? {
+                    b'K' => (Some(OldTuple::Key(TupleData::decode(bytes)?)), decode_new_tuple(bytes)?),
+                    b'O' => (Some(OldTuple::Full(TupleData::decode(bytes)?)), decode_new_tuple(bytes)?),
+                    b'N' => (None, TupleData::decode(bytes)?),
+                    x => return Err(Incorrect(format!("tuple kind should be 'K', 'O' or 'N' but is {}", x))),
+                };
+                Ok(Self::Update(Update { relation_oid, old_tuple, new_tuple }))
+            },
+            b'Y' => {
+                let oid = bytes.take_u32()?;
+                let namespace = Text::decode(bytes)?;
+                let name = Text::decode(bytes)?;
+                Ok(Self::Type(Type { oid, namespace, name }))
+            },
+            x => {
+                let rest = bytes.take_vec(bytes.left())?;
+                Ok(Self::Other(x, Bytes(rest)))
+            },
+        }
+    }
+}
+
+fn decode_new_tuple(bytes: &mut BytesSource) -> DecodeResult<TupleData> {
+    match bytes.take_u8()? {
+        b'N' => TupleData::decode(bytes),
+        x => Err(Incorrect(format!("new tuple kind should be 'N' but is {}", x))),
+    }
+}
+
+impl PartDecode for OldTuple {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            b'K' => TupleData::decode(bytes).map(Self::Key),
+            b'O' => TupleData::decode(bytes).map(Self::Full),
+            x => Err(Incorrect(format!("old tuple kind should be 'K' or 'O' but is {}", x))),
+        }
+    }
+}
+
+impl PartDecode for ReplicaIdentity {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            b'd' => Ok(Self::Default),
+            b'n' => Ok(Self::Nothing),
+            b'f' => Ok(Self::Full),
+            b'i' => Ok(Self::Index),
+            x => Err(Unknown(format!("Unknown replica identity {}", x))),
+        }
+    }
+}
+
+impl PartDecode for RelationColumn {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let is_key = bytes.take_u8()? & 1 != 0;
+        let name = Text::decode(bytes)?;
+        let type_oid = bytes.take_u32()?;
+        let type_modifier = bytes.take_u32()? as i32;
+        Ok(Self { is_key, name, type_oid, type_modifier })
+    }
+}
+
+impl PartDecode for TupleData {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let columns = decode_vec(bytes.take_u16()? as usize, bytes)?;
+        Ok(Self { columns })
+    }
+}
+
+impl PartDecode for TupleColumn {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        match bytes.take_u8()? {
+            b'n' => Ok(Self::Value(Format::Text, Value::Null)),
+            b'u' => Ok(Self::UnchangedToast),
+            b't' => Value::decode(bytes).map(|value| Self::Value(Format::Text, value)),
+            b'b' => Value::decode(bytes).map(|value| Self::Value(Format::Binary, value)),
+            x => Err(Unknown(format!("Unknown tuple column kind {}", x))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::parts::Bytes;
+    use crate::msg::replication::{WalSenderMsg, XLogData};
+    use crate::msg::util::test::*;
+
+    fn assert_pgoutput_ok(expected: PgOutputMsg, message: &[u8]) {
+        let mut bytes = vec![
+            b'w',
+            0, 0, 0, 0, 0, 0, 0, 1,  // start
+            0, 0, 0, 0, 0, 0, 0, 2,  // WAL end
+            0, 0, 0, 0, 0, 0, 0, 3,  // clock
+        ];
+        bytes.extend_from_slice(message);
+        assert_decode_ok(WalSenderMsg::XLogData(XLogData {
+            start: Lsn(1),
+            wal_end: Lsn(2),
+            clock: 3,
+            data: expected,
+        }), &bytes);
+    }
+
+    #[test]
+    fn started_by_query() {
+        let query = |text: &str| Query(Vec::from(text));
+        assert!(is_started_by(&query(
+            "START_REPLICATION SLOT \"sub\" LOGICAL 0/0 (proto_version '1', publication_names '\"pub\"')"
+        )));
+        assert!(is_started_by(&query(
+            " start_replication slot sub logical 0/0 (\"proto_version\" '2', \"publication_names\" 'pub')"
+        )));
+        assert!(!is_started_by(&query("START_REPLICATION SLOT sub LOGICAL 0/0 (\"include-xids\" '0')")));
+        assert!(!is_started_by(&query("START_REPLICATION 0/0 TIMELINE 1")));
+        assert!(!is_started_by(&query("select 'START_REPLICATION LOGICAL publication_names'")));
+    }
+
+    #[test]
+    fn begin() {
+        let bytes = &[
+            b'B',
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x48,  // final LSN
+            0, 0x02, 0x9a, 0x3b, 0x1c, 0x5f, 0x2e, 0x10,  // commit time
+            0, 0, 0x02, 0xf1,  // xid
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Begin(Begin {
+            final_lsn: Lsn(0x16_b374d848),
+            commit_time: 0x029a3b1c5f2e10,
+            xid: 753,
+        }), bytes);
+    }
+
+    #[test]
+    fn commit() {
+        let bytes = &[
+            b'C',
+            0,  // flags
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x48,  // commit LSN
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x50,  // end LSN
+            0, 0x02, 0x9a, 0x3b, 0x1c, 0x5f, 0x2e, 0x10,  // commit time
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Commit(Commit {
+            flags: 0,
+            commit_lsn: Lsn(0x16_b374d848),
+            end_lsn: Lsn(0x16_b374d850),
+            commit_time: 0x029a3b1c5f2e10,
+        }), bytes);
+    }
+
+    #[test]
+    fn origin() {
+        let bytes = &[
+            b'O',
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x48,  // commit LSN
+            b'n', b'o', b'd', b'e', b'1', 0,  // name
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Origin(Origin {
+            commit_lsn: Lsn(0x16_b374d848),
+            name: "node1".into(),
+        }), bytes);
+    }
+
+    #[test]
+    fn relation() {
+        let bytes = &[
+            b'R',
+            0, 0, 0x40, 0x01,  // oid
+            b'p', b'u', b'b', 0,  // namespace
+            b't', b'1', 0,  // name
+            b'd',  // default replica identity
+            0, 2,  // 2 columns
+            1,  // key
+            b'i', b'd', 0,  // name
+            0, 0, 0, 23,  // type oid
+            0xff, 0xff, 0xff, 0xff,  // no type modifier
+            0,  // not key
+            b'v', 0,  // name
+            0, 0, 0x04, 0x13,  // type oid
+            0, 0, 0, 14,  // type modifier
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Relation(Relation {
+            oid: 0x4001,
+            namespace: "pub".into(),
+            name: "t1".into(),
+            replica_identity: ReplicaIdentity::Default,
+            columns: vec![
+                RelationColumn { is_key: true, name: "id".into(), type_oid: 23, type_modifier: -1 },
+                RelationColumn { is_key: false, name: "v".into(), type_oid: 1043, type_modifier: 14 },
+            ],
+        }), bytes);
+    }
+
+    #[test]
+    fn type_() {
+        let bytes = &[
+            b'Y',
+            0, 0, 0x40, 0x02,  // oid
+            b'p', b'u', b'b', 0,  // namespace
+            b'm', b'o', b'o', b'd', 0,  // name
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Type(Type {
+            oid: 0x4002,
+            namespace: "pub".into(),
+            name: "mood".into(),
+        }), bytes);
+    }
+
+    #[test]
+    fn insert() {
+        let bytes = &[
+            b'I',
+            0, 0, 0x40, 0x01,  // relation oid
+            b'N',  // new tuple
+            0, 3,  // 3 columns
+            b't', 0, 0, 0, 1, b'5',  // text
+            b'n',  // NULL
+            b'b', 0, 0, 0, 2, 0xbe, 0xef,  // binary
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Insert(Insert {
+            relation_oid: 0x4001,
+            new_tuple: TupleData { columns: vec![
+                TupleColumn::Value(Format::Text, Value::Bytes(Bytes(vec![b'5']))),
+                TupleColumn::Value(Format::Text, Value::Null),
+                TupleColumn::Value(Format::Binary, Value::Bytes(Bytes(vec![0xbe, 0xef]))),
+            ]},
+        }), bytes);
+    }
+
+    #[test]
+    fn update_without_old_tuple() {
+        let bytes = &[
+            b'U',
+            0, 0, 0x40, 0x01,  // relation oid
+            b'N',  // new tuple
+            0, 2,  // 2 columns
+            b't', 0, 0, 0, 1, b'5',  // text
+            b'u',  // unchanged TOAST
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Update(Update {
+            relation_oid: 0x4001,
+            old_tuple: None,
+            new_tuple: TupleData { columns: vec![
+                TupleColumn::Value(Format::Text, Value::Bytes(Bytes(vec![b'5']))),
+                TupleColumn::UnchangedToast,
+            ]},
+        }), bytes);
+    }
+
+    #[test]
+    fn update_with_key() {
+        let bytes = &[
+            b'U',
+            0, 0, 0x40, 0x01,  // relation oid
+            b'K',  // old key
+            0, 1,  // 1 column
+            b't', 0, 0, 0, 1, b'4',  // text
+            b'N',  // new tuple
+            0, 1,  // 1 column
+            b't', 0, 0, 0, 1, b'5',  // text
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Update(Update {
+            relation_oid: 0x4001,
+            old_tuple: Some(OldTuple::Key(TupleData { columns: vec![
+                TupleColumn::Value(Format::Text, Value::Bytes(Bytes(vec![b'4']))),
+            ]})),
+            new_tuple: TupleData { columns: vec![
+                TupleColumn::Value(Format::Text, Value::Bytes(Bytes(vec![b'5']))),
+            ]},
+        }), bytes);
+    }
+
+    #[test]
+    fn delete_with_full_old_tuple() {
+        let bytes = &[
+            b'D',
+            0, 0, 0x40, 0x01,  // relation oid
+            b'O',  // full old tuple
+            0, 2,  // 2 columns
+            b't', 0, 0, 0, 1, b'4',  // text
+            b'n',  // NULL
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Delete(Delete {
+            relation_oid: 0x4001,
+            old_tuple: OldTuple::Full(TupleData { columns: vec![
+                TupleColumn::Value(Format::Text, Value::Bytes(Bytes(vec![b'4']))),
+                TupleColumn::Value(Format::Text, Value::Null),
+            ]}),
+        }), bytes);
+    }
+
+    #[test]
+    fn truncate() {
+        let bytes = &[
+            b'T',
+            0, 0, 0, 2,  // 2 relations
+            1,  // cascade
+            0, 0, 0x40, 0x01,
+            0, 0, 0x40, 0x03,
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Truncate(Truncate {
+            cascade: true,
+            restart_identity: false,
+            relations_oids: vec![0x4001, 0x4003],
+        }), bytes);
+    }
+
+    #[test]
+    fn other_message() {
+        let bytes = &[
+            b'M',
+            1,  // transactional
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x48,  // LSN
+            b'p', 0,  // prefix
+            0, 0, 0, 1,  // content length
+            b'x',  // content
+        ];
+        assert_pgoutput_ok(PgOutputMsg::Other(b'M', Bytes(bytes[1..].to_vec())), bytes);
+        assert_pgoutput_ok(PgOutputMsg::Other(b'E', Bytes(vec![])), &[b'E']);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::msg::replication::Lsn;
+use crate::msg::util::decode::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrimaryKeepalive {
+    pub wal_end: Lsn,
+    pub clock: i64,  // microseconds since 2000-01-01 00:00:00 UTC
+    pub reply_requested: bool,
+}
+
+impl PrimaryKeepalive {
+    pub const TYPE_BYTE: u8 = b'k';
+}
+
+impl PartDecode for PrimaryKeepalive {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let wal_end = Lsn::decode(bytes)?;
+        let clock = bytes.take_u64()? as i64;
+        let reply_requested = bytes.take_u8()? != 0;
+        Ok(Self { wal_end, clock, reply_requested })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrimaryKeepalive;
+    use crate::msg::replication::{Lsn, WalSenderMsg};
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn reply_requested() {
+        let bytes = &[
+            b'k',
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x50,  // WAL end
+            0, 0x02, 0x9a, 0x3b, 0x1c, 0x5f, 0x2e, 0x10,  // clock
+            1,  // reply requested
+        ];
+        assert_decode_ok::<WalSenderMsg>(WalSenderMsg::PrimaryKeepalive(PrimaryKeepalive {
+            wal_end: Lsn(0x16_b374d850),
+            clock: 0x029a3b1c5f2e10,
+            reply_requested: true,
+        }), bytes);
+    }
+
+    #[test]
+    fn no_reply_requested() {
+        let bytes = &[
+            b'k',
+            0, 0, 0, 0, 0, 0, 0, 0,  // WAL end
+            0, 0, 0, 0, 0, 0, 0, 0,  // clock
+            0,  // no reply requested
+        ];
+        assert_decode_ok::<WalSenderMsg>(WalSenderMsg::PrimaryKeepalive(PrimaryKeepalive {
+            wal_end: Lsn(0),
+            clock: 0,
+            reply_requested: false,
+        }), bytes);
+    }
+}
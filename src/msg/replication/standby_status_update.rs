@@ -0,0 +1,52 @@
+use crate::msg::replication::Lsn;
+use crate::msg::util::decode::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StandbyStatusUpdate {
+    pub written: Lsn,
+    pub flushed: Lsn,
+    pub applied: Lsn,
+    pub clock: i64,  // microseconds since 2000-01-01 00:00:00 UTC
+    pub reply_requested: bool,
+}
+
+impl StandbyStatusUpdate {
+    pub const TYPE_BYTE: u8 = b'r';
+}
+
+impl PartDecode for StandbyStatusUpdate {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let written = Lsn::decode(bytes)?;
+        let flushed = Lsn::decode(bytes)?;
+        let applied = Lsn::decode(bytes)?;
+        let clock = bytes.take_u64()? as i64;
+        let reply_requested = bytes.take_u8()? != 0;
+        Ok(Self { written, flushed, applied, clock, reply_requested })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StandbyStatusUpdate;
+    use crate::msg::replication::{Lsn, WalReceiverMsg};
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn simple() {
+        let bytes = &[
+            b'r',
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x50,  // written
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x48,  // flushed
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x40,  // applied
+            0, 0x02, 0x9a, 0x3b, 0x1c, 0x5f, 0x2e, 0x10,  // clock
+            0,  // no reply requested
+        ];
+        assert_decode_ok(WalReceiverMsg::StandbyStatusUpdate(StandbyStatusUpdate {
+            written: Lsn(0x16_b374d850),
+            flushed: Lsn(0x16_b374d848),
+            applied: Lsn(0x16_b374d840),
+            clock: 0x029a3b1c5f2e10,
+            reply_requested: false,
+        }), bytes);
+    }
+}
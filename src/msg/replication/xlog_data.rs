@@ -0,0 +1,50 @@
+use crate::msg::parts::Bytes;
+use crate::msg::replication::Lsn;
+use crate::msg::util::decode::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct XLogData<Data = Bytes> {
+    pub start: Lsn,
+    pub wal_end: Lsn,
+    pub clock: i64,  // microseconds since 2000-01-01 00:00:00 UTC
+    pub data: Data,  // raw WAL for physical replication, output plugin message for logical one
+}
+
+impl<Data> XLogData<Data> {
+    pub const TYPE_BYTE: u8 = b'w';
+}
+
+impl<Data: PartDecode> PartDecode for XLogData<Data> {
+    fn decode(bytes: &mut BytesSource) -> DecodeResult<Self> {
+        let start = Lsn::decode(bytes)?;
+        let wal_end = Lsn::decode(bytes)?;
+        let clock = bytes.take_u64()? as i64;
+        let data = Data::decode(bytes)?;
+        Ok(Self { start, wal_end, clock, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XLogData;
+    use crate::msg::parts::Bytes;
+    use crate::msg::replication::{Lsn, WalSenderMsg};
+    use crate::msg::util::test::*;
+
+    #[test]
+    fn simple() {
+        let bytes = &[
+            b'w',
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x48,  // start
+            0, 0, 0, 0x16, 0xb3, 0x74, 0xd8, 0x50,  // WAL end
+            0, 0x02, 0x9a, 0x3b, 0x1c, 0x5f, 0x2e, 0x10,  // clock
+            1, 2, 3, 4, 5, 6, 7, 8,  // data
+        ];
+        assert_decode_ok(WalSenderMsg::XLogData(XLogData {
+            start: Lsn(0x16_b374d848),
+            wal_end: Lsn(0x16_b374d850),
+            clock: 0x029a3b1c5f2e10,
+            data: Bytes(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+        }), bytes);
+    }
+}
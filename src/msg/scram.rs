@@ -0,0 +1,332 @@
+use crate::msg::body::SaslInitialResponse;
+use crate::msg::parts::{Bytes, Text};
+use crate::msg::util::decode::{*, Problem::*};
+
+// https://www.postgresql.org/docs/current/sasl-authentication.html
+// https://tools.ietf.org/html/rfc5802#section-7
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mechanism {
+    ScramSha256,
+    ScramSha256Plus,
+}
+
+impl Mechanism {
+    pub fn of(initial_response: &SaslInitialResponse) -> Option<Self> {
+        [Self::ScramSha256, Self::ScramSha256Plus].iter().copied()
+            .find(|mechanism| mechanism.name().as_bytes() == initial_response.selected_mechanism.as_slice())
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ScramSha256 => "SCRAM-SHA-256",
+            Self::ScramSha256Plus => "SCRAM-SHA-256-PLUS",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelBinding {
+    NotSupportedByClient,  // n
+    NotSupportedByServer,  // y, i.e. client supports but thinks server doesn't
+    Required(Text),  // p=<cb-name>, e.g. tls-server-end-point
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gs2Header {
+    pub channel_binding: ChannelBinding,
+    pub authzid: Option<Text>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientFirst {
+    pub mechanism: Mechanism,
+    pub gs2_header: Gs2Header,
+    pub username: Text,  // PostgreSQL ignores it and takes the user from Startup
+    pub nonce: Text,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerFirst {
+    pub nonce: Text,
+    pub salt: Bytes,
+    pub iterations: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientFinal {
+    pub channel_binding: Bytes,  // GS2 header and channel binding data if any
+    pub nonce: Text,
+    pub proof: Bytes,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerFinal {
+    Error(Text),
+    Verifier(Bytes),
+}
+
+impl Gs2Header {
+    pub fn is_channel_binding_required(&self) -> bool {
+        matches!(self.channel_binding, ChannelBinding::Required(_))
+    }
+}
+
+impl ClientFirst {
+    pub fn parse(initial_response: &SaslInitialResponse) -> Option<DecodeResult<Self>> {
+        let mechanism = Mechanism::of(initial_response)?;
+        Some(match &initial_response.mechanism_data {
+            Some(data) => Self::parse_data(mechanism, data),
+            None => Err(Incorrect("SCRAM client-first-message is missing".into())),
+        })
+    }
+
+    fn parse_data(mechanism: Mechanism, data: &[u8]) -> DecodeResult<Self> {
+        let mut attrs = Attributes::new(data);
+        let channel_binding = match attrs.next()? {
+            b"n" => ChannelBinding::NotSupportedByClient,
+            b"y" => ChannelBinding::NotSupportedByServer,
+            [b'p', b'=', name @ ..] => ChannelBinding::Required(Text(name.to_vec())),
+            flag => return Err(Incorrect(format!("Unknown GS2 channel binding flag {:?}", String::from_utf8_lossy(flag)))),
+        };
+        let authzid = match attrs.next()? {
+            b"" => None,
+            _ => Some(attrs.value_of_last(b'a')?),
+        };
+        let gs2_header = Gs2Header { channel_binding, authzid: authzid.map(Text) };
+        let username = Text(attrs.take(b'n')?);
+        let nonce = Text(attrs.take(b'r')?);
+        Ok(Self { mechanism, gs2_header, username, nonce })
+    }
+}
+
+impl ServerFirst {
+    pub fn parse(challenge_data: &[u8]) -> DecodeResult<Self> {
+        let mut attrs = Attributes::new(challenge_data);
+        let nonce = Text(attrs.take(b'r')?);
+        let salt = Bytes(attrs.take_base64(b's')?);
+        let iterations = attrs.take(b'i')?;
+        let iterations = String::from_utf8_lossy(&iterations).parse()
+            .map_err(|_| Incorrect(format!("SCRAM iteration count {:?} is not a number", String::from_utf8_lossy(&iterations))))?;
+        Ok(Self { nonce, salt, iterations })
+    }
+}
+
+impl ClientFinal {
+    pub fn parse(mechanism_data: &[u8]) -> DecodeResult<Self> {
+        let mut attrs = Attributes::new(mechanism_data);
+        let channel_binding = Bytes(attrs.take_base64(b'c')?);
+        let nonce = Text(attrs.take(b'r')?);
+        attrs.skip_to_last();  // extensions if any
+        let proof = Bytes(attrs.take_base64(b'p')?);
+        Ok(Self { channel_binding, nonce, proof })
+    }
+}
+
+impl ServerFinal {
+    pub fn parse(additional_data: &[u8]) -> DecodeResult<Self> {
+        let mut attrs = Attributes::new(additional_data);
+        match attrs.next()? {
+            [b'e', b'=', error @ ..] => Ok(Self::Error(Text(error.to_vec()))),
+            [b'v', b'=', ..] => Ok(Self::Verifier(Bytes(attrs.value_of_last_base64(b'v')?))),
+            attr => Err(Incorrect(format!("SCRAM verifier or error expected but got {:?}", String::from_utf8_lossy(attr)))),
+        }
+    }
+}
+
+// Comma-separated attributes like "r=nonce,s=salt" which are mostly expected in the fixed order.
+struct Attributes<'a> {
+    rest: Option<&'a [u8]>,
+    last: &'a [u8],
+}
+
+impl<'a> Attributes<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { rest: Some(data), last: b"" }
+    }
+
+    fn next(&mut self) -> DecodeResult<&'a [u8]> {
+        let rest = self.rest.ok_or_else(|| Incorrect("SCRAM message is too short".into()))?;
+        let attr = match rest.iter().position(|&byte| byte == b',') {
+            Some(pos) => {
+                self.rest = Some(&rest[pos + 1 ..]);
+                &rest[.. pos]
+            },
+            None => {
+                self.rest = None;
+                rest
+            },
+        };
+        self.last = attr;
+        Ok(attr)
+    }
+
+    fn skip_to_last(&mut self) {
+        if let Some(rest) = self.rest {
+            let pos = rest.iter().rposition(|&byte| byte == b',').map_or(0, |pos| pos + 1);
+            self.rest = Some(&rest[pos ..]);
+        }
+    }
+
+    fn take(&mut self, name: u8) -> DecodeResult<Vec<u8>> {
+        self.next()?;
+        self.value_of_last(name)
+    }
+
+    fn take_base64(&mut self, name: u8) -> DecodeResult<Vec<u8>> {
+        self.next()?;
+        self.value_of_last_base64(name)
+    }
+
+    fn value_of_last(&self, name: u8) -> DecodeResult<Vec<u8>> {
+        match self.last {
+            [actual, b'=', value @ ..] if *actual == name =>
+                Ok(value.to_vec()),
+            [b'm', b'=', ..] =>
+                Err(Unknown(format!("Unknown mandatory SCRAM extension {:?}", String::from_utf8_lossy(self.last)))),
+            attr =>
+                Err(Incorrect(format!("SCRAM attribute {} expected but got {:?}", name as char, String::from_utf8_lossy(attr)))),
+        }
+    }
+
+    fn value_of_last_base64(&self, name: u8) -> DecodeResult<Vec<u8>> {
+        let value = self.value_of_last(name)?;
+        base64::decode(&value)
+            .map_err(|err| Incorrect(format!("SCRAM attribute {} is not base64: {}", name as char, err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelBinding, ClientFinal, ClientFirst, Gs2Header, Mechanism, ServerFinal, ServerFirst};
+    use crate::msg::body::SaslInitialResponse;
+    use crate::msg::parts::{Bytes, Text};
+    use crate::msg::util::decode::Problem::*;
+
+    fn initial_response(mechanism: &str, data: Option<&str>) -> SaslInitialResponse {
+        SaslInitialResponse {
+            selected_mechanism: Vec::from(mechanism),
+            mechanism_data: data.map(Vec::from),
+        }
+    }
+
+    fn text(s: &str) -> Text {
+        Text(Vec::from(s))
+    }
+
+    #[test]
+    fn not_scram() {
+        assert_eq!(None, ClientFirst::parse(&initial_response("OTP", Some("n,,n=,r=abc"))));
+    }
+
+    #[test]
+    fn client_first_without_channel_binding() {
+        let initial_response = initial_response("SCRAM-SHA-256", Some("n,,n=,r=rOprNGfwEbeRWgbNEkqO"));
+        assert_eq!(Some(Ok(ClientFirst {
+            mechanism: Mechanism::ScramSha256,
+            gs2_header: Gs2Header {
+                channel_binding: ChannelBinding::NotSupportedByClient,
+                authzid: None,
+            },
+            username: text(""),
+            nonce: text("rOprNGfwEbeRWgbNEkqO"),
+        })), ClientFirst::parse(&initial_response));
+    }
+
+    #[test]
+    fn client_first_with_channel_binding() {
+        let initial_response = initial_response("SCRAM-SHA-256-PLUS", Some("p=tls-server-end-point,a=admin,n=user,r=abc,x=ext"));
+        let client_first = ClientFirst::parse(&initial_response).unwrap().unwrap();
+        assert_eq!(Mechanism::ScramSha256Plus, client_first.mechanism);
+        assert_eq!(Gs2Header {
+            channel_binding: ChannelBinding::Required(text("tls-server-end-point")),
+            authzid: Some(text("admin")),
+        }, client_first.gs2_header);
+        assert!(client_first.gs2_header.is_channel_binding_required());
+        assert_eq!(text("user"), client_first.username);
+    }
+
+    #[test]
+    fn client_first_missing() {
+        let initial_response = initial_response("SCRAM-SHA-256", None);
+        assert_eq!(
+            Some(Err(Incorrect("SCRAM client-first-message is missing".into()))),
+            ClientFirst::parse(&initial_response),
+        );
+    }
+
+    #[test]
+    fn client_first_unknown_flag() {
+        let initial_response = initial_response("SCRAM-SHA-256", Some("x,,n=,r=abc"));
+        assert_eq!(
+            Some(Err(Incorrect("Unknown GS2 channel binding flag \"x\"".into()))),
+            ClientFirst::parse(&initial_response),
+        );
+    }
+
+    #[test]
+    fn client_first_mandatory_extension() {
+        let initial_response = initial_response("SCRAM-SHA-256", Some("y,,m=ext,n=,r=abc"));
+        assert_eq!(
+            Some(Err(Unknown("Unknown mandatory SCRAM extension \"m=ext\"".into()))),
+            ClientFirst::parse(&initial_response),
+        );
+    }
+
+    #[test]
+    fn server_first() {
+        assert_eq!(Ok(ServerFirst {
+            nonce: text("abcdef"),
+            salt: Bytes(vec![0x12, 0x34, 0x56]),
+            iterations: 4096,
+        }), ServerFirst::parse(b"r=abcdef,s=EjRW,i=4096"));
+    }
+
+    #[test]
+    fn server_first_too_short() {
+        assert_eq!(Err(Incorrect("SCRAM message is too short".into())), ServerFirst::parse(b"r=abcdef,s=EjRW"));
+    }
+
+    #[test]
+    fn server_first_incorrect_iterations() {
+        assert_eq!(
+            Err(Incorrect("SCRAM iteration count \"-1\" is not a number".into())),
+            ServerFirst::parse(b"r=abcdef,s=EjRW,i=-1"),
+        );
+    }
+
+    #[test]
+    fn server_first_incorrect_salt() {
+        assert_eq!(
+            Err(Incorrect("SCRAM attribute s is not base64: Invalid byte 33, offset 0.".into())),
+            ServerFirst::parse(b"r=abcdef,s=!,i=4096"),
+        );
+    }
+
+    #[test]
+    fn client_final() {
+        assert_eq!(Ok(ClientFinal {
+            channel_binding: Bytes(Vec::from("n,,")),
+            nonce: text("abcdef"),
+            proof: Bytes(vec![0x12, 0x34, 0x56]),
+        }), ClientFinal::parse(b"c=biws,r=abcdef,x=ext,p=EjRW"));
+    }
+
+    #[test]
+    fn client_final_without_proof() {
+        assert_eq!(
+            Err(Incorrect("SCRAM attribute p expected but got \"x=ext\"".into())),
+            ClientFinal::parse(b"c=biws,r=abcdef,x=ext"),
+        );
+    }
+
+    #[test]
+    fn server_final_verifier() {
+        assert_eq!(Ok(ServerFinal::Verifier(Bytes(vec![0x12, 0x34, 0x56]))), ServerFinal::parse(b"v=EjRW"));
+    }
+
+    #[test]
+    fn server_final_error() {
+        assert_eq!(Ok(ServerFinal::Error(text("invalid-proof"))), ServerFinal::parse(b"e=invalid-proof"));
+    }
+}
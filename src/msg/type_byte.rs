@@ -1,26 +1,92 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
-#[allow(non_camel_case_types)]
-#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive)]
+// The same byte may mean different messages depending on who sends it, e.g. 'D' is DataRow from backend
+// but Describe from frontend, so type bytes are decoded separately for each side.
+
+#[derive(Clone, Copy, Debug, IntoPrimitive, PartialEq, TryFromPrimitive)]
 #[repr(u8)]
-pub enum TypeByte {
+pub enum BackendTypeByte {
     Authentication = b'R',
     BackendKeyData = b'K',
-    Bind = b'B',
     BindComplete = b'2',
+    CloseComplete = b'3',
     CommandComplete = b'C',
+    CopyBothResponse = b'W',
+    CopyData = b'd',
+    CopyDone = b'c',
+    CopyInResponse = b'G',
+    CopyOutResponse = b'H',
     DataRow = b'D',
     EmptyQueryResponse = b'I',
-    Execute_or_ErrorResponse = b'E',
-    NoticeResponse = b'N',
-    GssResponse_or_Password_or_SaslResponses = b'p',
+    ErrorResponse = b'E',
+    FunctionCallResponse = b'V',
     NegotiateProtocolVersion = b'v',
-    ParameterStatus_or_Sync = b'S',
-    Parse = b'P',
+    NoData = b'n',
+    NoticeResponse = b'N',
+    NotificationResponse = b'A',
+    ParameterDescription = b't',
+    ParameterStatus = b'S',
     ParseComplete = b'1',
     PortalSuspended = b's',
-    Query = b'Q',
     ReadyForQuery = b'Z',
     RowDescription = b'T',
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, IntoPrimitive, PartialEq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum FrontendTypeByte {
+    Bind = b'B',
+    Close = b'C',
+    CopyData = b'd',
+    CopyDone = b'c',
+    CopyFail = b'f',
+    Describe = b'D',
+    Execute = b'E',
+    Flush = b'H',
+    FunctionCall = b'F',
+    GssResponse_or_Password_or_SaslResponses = b'p',  // which one is told by authentication state
+    Parse = b'P',
+    Query = b'Q',
+    Sync = b'S',
     Terminate = b'X',
 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeByte {
+    Backend(BackendTypeByte),
+    Frontend(FrontendTypeByte),
+}
+
+impl From<TypeByte> for u8 {
+    fn from(type_byte: TypeByte) -> u8 {
+        match type_byte {
+            TypeByte::Backend(backend) => backend.into(),
+            TypeByte::Frontend(frontend) => frontend.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackendTypeByte, FrontendTypeByte, TypeByte};
+    use ::std::convert::TryFrom;
+
+    #[test]
+    fn same_byte_differs_by_side() {
+        assert_eq!(Ok(BackendTypeByte::DataRow), BackendTypeByte::try_from(b'D'));
+        assert_eq!(Ok(FrontendTypeByte::Describe), FrontendTypeByte::try_from(b'D'));
+    }
+
+    #[test]
+    fn unknown_on_one_side() {
+        assert!(BackendTypeByte::try_from(b'f').is_err());
+        assert_eq!(Ok(FrontendTypeByte::CopyFail), FrontendTypeByte::try_from(b'f'));
+    }
+
+    #[test]
+    fn into_u8() {
+        assert_eq!(b'E', u8::from(TypeByte::Backend(BackendTypeByte::ErrorResponse)));
+        assert_eq!(b'E', u8::from(TypeByte::Frontend(FrontendTypeByte::Execute)));
+    }
+}
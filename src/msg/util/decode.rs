@@ -1,6 +1,5 @@
-use crate::msg::type_byte::TypeByte;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Problem {
     NeedMoreBytes(usize),
     NoNullByte,
@@ -15,7 +14,7 @@ pub trait PartDecode : Sized {
 }
 
 pub trait MsgDecode : Sized {
-    const TYPE_BYTE_OPT: Option<TypeByte>;
+    const TYPE_BYTE_OPT: Option<u8>;
 
     fn decode_body(bytes: &mut BytesSource) -> DecodeResult<Self>;
 }
@@ -46,6 +45,10 @@ impl<'a> BytesSource<'a> {
         self.take_bounded(4, |s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]))
     }
 
+    pub fn take_u64(&mut self) -> DecodeResult<u64> {
+        self.take_bounded(8, |s| u64::from_be_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]))
+    }
+
     pub fn take_slice(&mut self, result: &mut [u8]) -> DecodeResult<()> {
         self.take_bounded(result.len(), |s| result.copy_from_slice(s))
     }
@@ -131,6 +134,16 @@ mod tests {
         assert_eq!(Err(NeedMoreBytes(4)), bs.take_u32());
     }
 
+    #[test]
+    fn take_u64() {
+        let mut bs = BS::new(&[]);
+        assert_eq!(Err(NeedMoreBytes(8)), bs.take_u64());
+        let mut bs = BS::new(&[0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18, 0x29, 0x3a]);
+        assert_eq!(Ok(0xa1b2c3d4e5f60718), bs.take_u64());
+        assert_eq!(Err(NeedMoreBytes(6)), bs.take_u64());
+        assert_eq!(Err(NeedMoreBytes(8)), bs.take_u64());
+    }
+
     #[test]
     fn take_slice() {
         let mut res = [0; 3];
@@ -1,4 +1,4 @@
-use ::std::mem::size_of;
+use crate::msg::util::decode::MsgDecode;
 
 #[derive(Debug, PartialEq)]
 pub enum Problem {
@@ -7,40 +7,46 @@ pub enum Problem {
 
 pub type EncodeResult<Ok> = std::result::Result<Ok, Problem>;
 
+pub trait PartEncode {
+    fn encode(&self, bytes: &mut BytesTarget) -> EncodeResult<()>;
+}
+
+pub trait MsgEncode {
+    fn encode_body(&self, bytes: &mut BytesTarget) -> EncodeResult<()>;
+}
+
+// The type byte if any, the length and the body as they go on the wire.
+pub fn encode_msg<Msg: MsgDecode + MsgEncode>(msg: &Msg) -> EncodeResult<Vec<u8>> {
+    let mut measuring = BytesTarget::measuring();
+    msg.encode_body(&mut measuring)?;
+    let len = 4 + measuring.pos;
+    let mut bytes = vec![0; Msg::TYPE_BYTE_OPT.map_or(0, |_| 1) + len];
+    let mut bytes_target = BytesTarget::new(&mut bytes);
+    if let Some(type_byte) = Msg::TYPE_BYTE_OPT {
+        bytes_target.put_u8(type_byte)?;
+    }
+    bytes_target.put_u32(len as u32)?;
+    msg.encode_body(&mut bytes_target)?;
+    Ok(bytes)
+}
+
 pub struct BytesTarget<'a> {
     slice: &'a mut [u8],
     pos: usize,
-}
-
-macro_rules! put_bounded {
-    (
-        $typ: ident,
-        $self: ident,
-        $value: ident
-    ) => {{
-        const LEN: usize = size_of::<$typ>();
-        if $self.pos + LEN <= $self.slice.len() {
-            let bytes: [u8; LEN] = $value.to_be_bytes();
-            for i in 0..LEN {
-                $self.slice[$self.pos + i] = bytes[i];
-            }
-            $self.pos += LEN;
-            Ok(())
-        } else {
-            let left = $self.slice.len() - $self.pos;
-            $self.pos = $self.slice.len();
-            Err(Problem::OutOfSpace(LEN - left))
-        }
-    }};
+    measuring: bool,  // only pos is moved to know the length before encoding
 }
 
 impl<'a> BytesTarget<'a> {
     pub fn new(slice: &'a mut [u8]) -> Self {
-        Self { slice, pos: 0 }
+        Self { slice, pos: 0, measuring: false }
+    }
+
+    pub fn measuring() -> Self {
+        Self { slice: &mut [], pos: 0, measuring: true }
     }
 
     pub fn left(&self) -> usize {
-        self.slice.len() - self.pos
+        self.slice.len().saturating_sub(self.pos)
     }
 
     pub fn rest(&mut self) -> &mut [u8] {
@@ -48,17 +54,51 @@ impl<'a> BytesTarget<'a> {
     }
 
     pub fn put_u8(&mut self, value: u8) -> EncodeResult<()> {
-        put_bounded!(u8, self, value)
+        self.put_slice(&value.to_be_bytes())
+    }
+
+    pub fn put_u16(&mut self, value: u16) -> EncodeResult<()> {
+        self.put_slice(&value.to_be_bytes())
     }
 
     pub fn put_u32(&mut self, value: u32) -> EncodeResult<()> {
-        put_bounded!(u32, self, value)
+        self.put_slice(&value.to_be_bytes())
+    }
+
+    pub fn put_u64(&mut self, value: u64) -> EncodeResult<()> {
+        self.put_slice(&value.to_be_bytes())
+    }
+
+    pub fn put_slice(&mut self, value: &[u8]) -> EncodeResult<()> {
+        self.put_bounded(value.len(), |s| s.copy_from_slice(value))
+    }
+
+    pub fn put_with_null(&mut self, value: &[u8]) -> EncodeResult<()> {
+        self.put_slice(value)?;
+        self.put_u8(0)
+    }
+
+    fn put_bounded<Encode>(&mut self, needed: usize, encode: Encode) -> EncodeResult<()>
+    where Encode: FnOnce(&mut [u8]) {
+        if self.measuring {
+            self.pos += needed;
+            Ok(())
+        } else if self.pos + needed <= self.slice.len() {
+            encode(&mut self.slice[self.pos .. self.pos + needed]);
+            self.pos += needed;
+            Ok(())
+        } else {
+            let left = self.slice.len() - self.pos;
+            self.pos = self.slice.len();
+            Err(Problem::OutOfSpace(needed - left))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{BytesTarget as BT, Problem::*};
+    use super::{BytesTarget as BT, Problem::*, encode_msg};
+    use crate::msg::body::{Query, initial::Initial};
 
     #[test]
     fn left() {
@@ -103,4 +143,47 @@ mod tests {
         assert_eq!(Err(OutOfSpace(2)), bs.put_u32(0xe5f6a7b8));
         assert_eq!(Err(OutOfSpace(4)), bs.put_u32(0xc9d0e1f2));
     }
+
+    #[test]
+    fn put_u16() {
+        let slice = &mut [0; 3];
+        let mut bt = BT::new(slice);
+        assert_eq!(Ok(()), bt.put_u16(0xa1b2));
+        assert_eq!([0xa1, 0xb2, 0], *bt.slice);
+        assert_eq!(Err(OutOfSpace(1)), bt.put_u16(0xc3d4));
+    }
+
+    #[test]
+    fn put_u64() {
+        let slice = &mut [0; 8];
+        let mut bt = BT::new(slice);
+        assert_eq!(Ok(()), bt.put_u64(0xa1b2c3d4e5f60718));
+        assert_eq!([0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18], *bt.slice);
+        assert_eq!(Err(OutOfSpace(8)), bt.put_u64(0));
+    }
+
+    #[test]
+    fn put_slice() {
+        let slice = &mut [0; 4];
+        let mut bt = BT::new(slice);
+        assert_eq!(Ok(()), bt.put_slice(&[0xa1, 0xb2]));
+        assert_eq!(Ok(()), bt.put_with_null(&[0xc3]));
+        assert_eq!([0xa1, 0xb2, 0xc3, 0], *bt.slice);
+        assert_eq!(Err(OutOfSpace(2)), bt.put_slice(&[0xd4, 0xe5]));
+    }
+
+    #[test]
+    fn measuring() {
+        let mut bt = BT::measuring();
+        assert_eq!(Ok(()), bt.put_u32(0xa1b2c3d4));
+        assert_eq!(Ok(()), bt.put_with_null(&[0xe5, 0xf6]));
+        assert_eq!(7, bt.pos);
+        assert_eq!(0, bt.left());
+    }
+
+    #[test]
+    fn msg() {
+        assert_eq!(Ok(b"Q\0\0\0\x07ab\0".to_vec()), encode_msg(&Query(Vec::from("ab"))));
+        assert_eq!(Ok(b"\0\0\0\x08\x04\xd2\x16\x2f".to_vec()), encode_msg(&Initial::TLS));
+    }
 }
@@ -47,6 +47,19 @@ where
     Ok(ReadData { bytes, msg_result })
 }
 
+// Reads a message of any type without decoding the body, e.g. to pass an unknown message through.
+pub async fn read_raw<R>(stream: &mut R, type_byte: u8) -> Result<Vec<u8>, ReadError>
+where R: AsyncReadExt + Unpin {
+    use ReadError::*;
+    let specified_len = read_u32(stream).await.map_err(IoError)?;
+    let mut bytes = vec![0u8; 1 + specified_len as usize];
+    let mut bytes_target = BytesTarget::new(&mut bytes);
+    bytes_target.put_u8(type_byte).map_err(EncodeError)?;
+    bytes_target.put_u32(specified_len).map_err(EncodeError)?;
+    stream.read_exact(bytes_target.rest()).await.map_err(IoError)?;
+    Ok(bytes)
+}
+
 fn alloc_vec<Msg: MsgDecode>(specified_len: u32) -> Vec<u8> {
     let type_len = if Msg::TYPE_BYTE_OPT.is_some() { 1 } else { 0 };
     let full_len = type_len + specified_len;
@@ -55,7 +68,7 @@ fn alloc_vec<Msg: MsgDecode>(specified_len: u32) -> Vec<u8> {
 
 fn put_header<Msg: MsgDecode>(specified_len: u32, bytes_target: &mut BytesTarget) -> EncodeResult<()> {
     if let Some(type_byte) = Msg::TYPE_BYTE_OPT {
-        bytes_target.put_u8(type_byte.into())?;
+        bytes_target.put_u8(type_byte)?;
     }
     bytes_target.put_u32(specified_len)
 }
@@ -64,7 +77,6 @@ fn put_header<Msg: MsgDecode>(specified_len: u32, bytes_target: &mut BytesTarget
 mod tests {
     use super::*;
     use crate::msg::util::decode::{BytesSource, DecodeResult, MsgDecode, Problem as DecodeError};
-    use crate::msg::type_byte::TypeByte;
     use ::futures::executor::block_on;
     use ::std::fmt::Debug;
     use ::std::marker::PhantomData;
@@ -214,10 +226,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn raw() {
+        let bytes: &[u8] = &[0, 0, 0, 7, 1, 2, 3, 4];
+        let raw = block_on(read_raw(&mut &bytes[..], b'?')).unwrap();
+        assert_eq!(vec![b'?', 0, 0, 0, 7, 1, 2, 3], raw);
+    }
+
+    #[test]
+    fn raw_len_is_too_small() {
+        let bytes: &[u8] = &[0, 0, 0, 3];
+        match block_on(read_raw(&mut &bytes[..], b'?')) {
+            Err(ReadError::EncodeError(_)) => {},
+            _ => panic!(concat!("read_raw should be ", stringify!(Err(ReadError::EncodeError)))),
+        }
+    }
+
     fn assert_eq_bytes<T: Type>(expected: Vec<u8>, actual: Vec<u8>) {
         match T::TYPE_BYTE_OPT {
             Some(type_byte) => unsafe {
-                assert_eq!(u8::from(type_byte) as char, actual[0] as char);
+                assert_eq!(type_byte as char, actual[0] as char);
                 assert_eq!(String::from_utf8_unchecked(expected), String::from_utf8_unchecked(actual)[1..]);
             },
             None => unsafe {
@@ -260,7 +288,7 @@ mod tests {
     }
 
     impl<T: Type, B: Body> MsgDecode for TestMsg<T, B> {
-        const TYPE_BYTE_OPT: Option<TypeByte> = T::TYPE_BYTE_OPT;
+        const TYPE_BYTE_OPT: Option<u8> = T::TYPE_BYTE_OPT;
 
         fn decode_body(bytes_source: &mut BytesSource) -> DecodeResult<Self> {
             let taken = bytes_source.take_vec(B::EXPECTED_BYTES.len())?;
@@ -277,7 +305,7 @@ mod tests {
     }
 
     trait Type: Debug + PartialEq {
-        const TYPE_BYTE_OPT: Option<TypeByte>;
+        const TYPE_BYTE_OPT: Option<u8>;
     }
 
     trait Body: Debug + PartialEq {
@@ -302,13 +330,13 @@ mod tests {
     #[derive(Debug, PartialEq)]
     struct WithoutType();
     impl Type for WithoutType {
-        const TYPE_BYTE_OPT: Option<TypeByte> = None;
+        const TYPE_BYTE_OPT: Option<u8> = None;
     }
 
     #[derive(Debug, PartialEq)]
     struct HavingType();
     impl Type for HavingType {
-        const TYPE_BYTE_OPT: Option<TypeByte> = Some(TypeByte::RowDescription);
+        const TYPE_BYTE_OPT: Option<u8> = Some(b'T');
     }
 
     #[derive(Debug, PartialEq)]
@@ -1,4 +1,5 @@
 use crate::msg::util::decode::*;
+use crate::msg::util::encode::{BytesTarget, MsgEncode};
 
 use ::std::cmp::PartialEq;
 use ::std::fmt::Debug;
@@ -10,6 +11,25 @@ pub fn assert_decode_ok<Msg>(
     assert_decode(Ok(expected), bytes)
 }
 
+pub fn assert_codec_ok<Msg>(
+    expected: Msg,
+    bytes: &[u8],
+) where Msg: Debug + MsgDecode + MsgEncode + PartialEq {
+    assert_encode_ok(&expected, bytes);
+    assert_decode_ok(expected, bytes)
+}
+
+pub fn assert_encode_ok<Msg>(
+    msg: &Msg,
+    bytes: &[u8],
+) where Msg: MsgEncode {
+    let mut encoded = vec![0; bytes.len()];
+    let mut bytes_target = BytesTarget::new(&mut encoded);
+    assert_eq!(Ok(()), msg.encode_body(&mut bytes_target));
+    assert_eq!(0, bytes_target.left(), "left {} byte(s) not encoded", bytes_target.left());
+    assert_eq!(bytes, &encoded[..]);
+}
+
 pub fn assert_decode_err<Msg>(
     expected: Problem,
     bytes: &[u8],
@@ -0,0 +1,416 @@
+use crate::record::WriterThread;
+
+use ::std::fmt::{self, Debug, Formatter};
+use ::std::fs::File;
+use ::std::io::{self, Write};
+use ::std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use ::std::sync::{Arc, Mutex};
+use ::std::sync::atomic::{AtomicU32, Ordering};
+use ::std::time::{SystemTime, UNIX_EPOCH};
+
+// A pcapng file of raw IP packets which Wireshark dissects as PostgreSQL on port 5432, or on another one with Decode As.
+// Each connection is a TCP stream from the client to the target server: a handshake, packets carrying the bytes
+// written by the conveyor to either side, so replaced, dropped and injected messages show as the peers got them,
+// and FIN both ways once the connection is dropped.
+// The bytes are plaintext as TLS is terminated by postgread, so requests for TLS and GSSAPI encryption are left out
+// like the single-byte answers to them. Both addresses are IPv4 unless one of them is IPv6.
+// The file is written by a thread of its own, the packets are complete once the writer and its streams are dropped.
+pub struct PcapngWriter {
+    writer: WriterThread,
+    next_isn: AtomicU32,
+}
+
+// The TCP stream of a connection, clones write to the same stream.
+#[derive(Clone)]
+pub struct PcapngStream(Arc<Connection>);
+
+struct Connection {
+    writer: Arc<PcapngWriter>,
+    client: SocketAddr,
+    server: SocketAddr,
+    sequences: Mutex<Sequences>,
+}
+
+struct Sequences {
+    client_next: u32,  // sequence number of the next byte from the client
+    server_next: u32,
+    ip_id: u16,
+}
+
+#[derive(Clone, Copy)]
+enum Flow {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl PcapngWriter {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Self::new(File::create(path)?)
+    }
+
+    pub fn new(mut writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let mut header = vec![];
+        push_block(&mut header, SECTION_HEADER_BLOCK, |body| {
+            body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+            body.extend_from_slice(&1u16.to_le_bytes());  // major version
+            body.extend_from_slice(&0u16.to_le_bytes());  // minor version
+            body.extend_from_slice(&(-1i64).to_le_bytes());  // section length isn't told
+        });
+        push_block(&mut header, INTERFACE_DESCRIPTION_BLOCK, |body| {
+            body.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+            body.extend_from_slice(&0u16.to_le_bytes());  // reserved
+            body.extend_from_slice(&0u32.to_le_bytes());  // no snapshot length limit
+        });
+        writer.write_all(&header)?;
+        Ok(Self { writer: WriterThread::spawn(writer)?, next_isn: AtomicU32::new(0x1000_0000) })
+    }
+
+    // Writes the handshake of the stream from the client to the target server.
+    pub fn connection(self: &Arc<Self>, client: SocketAddr, server: SocketAddr) -> io::Result<PcapngStream> {
+        let client_isn = self.next_isn.fetch_add(ISN_STEP, Ordering::SeqCst);
+        let server_isn = client_isn.wrapping_add(ISN_STEP / 2);
+        let connection = Connection {
+            writer: self.clone(),
+            client,
+            server,
+            sequences: Mutex::new(Sequences { client_next: client_isn, server_next: server_isn, ip_id: 0 }),
+        };
+        let mut sequences = connection.sequences.lock().unwrap();
+        connection.write_control(&mut sequences, Flow::ClientToServer, SYN)?;
+        connection.write_control(&mut sequences, Flow::ServerToClient, SYN | ACK)?;
+        connection.write_control(&mut sequences, Flow::ClientToServer, ACK)?;
+        drop(sequences);
+        Ok(PcapngStream(Arc::new(connection)))
+    }
+
+    fn write(&self, packets: &[Vec<u8>]) -> io::Result<()> {
+        self.writer.send_with(|| {
+            // taken in order of sending so timestamps grow along the file
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_micros() as u64);
+            let mut blocks = vec![];
+            for packet in packets {
+                push_block(&mut blocks, ENHANCED_PACKET_BLOCK, |body| {
+                    body.extend_from_slice(&0u32.to_le_bytes());  // interface
+                    body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+                    body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+                    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());  // captured
+                    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());  // original
+                    body.extend_from_slice(packet);
+                });
+            }
+            blocks
+        })
+    }
+}
+
+impl PcapngStream {
+    // Bytes written to the target server, requests for encryption are left out.
+    pub fn sent_to_server(&self, bytes: &[u8]) -> io::Result<()> {
+        if is_encryption_request(bytes) {
+            return Ok(());
+        }
+        self.write(Flow::ClientToServer, bytes)
+    }
+
+    // Bytes written to the client.
+    pub fn sent_to_client(&self, bytes: &[u8]) -> io::Result<()> {
+        self.write(Flow::ServerToClient, bytes)
+    }
+
+    fn write(&self, flow: Flow, bytes: &[u8]) -> io::Result<()> {
+        let connection = &self.0;
+        let mut sequences = connection.sequences.lock().unwrap();
+        let packets: Vec<_> = bytes.chunks(MAX_SEGMENT)
+            .map(|segment| connection.packet(&mut sequences, flow, PSH | ACK, segment))
+            .collect();
+        connection.writer.write(&packets)
+    }
+}
+
+impl Connection {
+    fn write_control(&self, sequences: &mut Sequences, flow: Flow, flags: u8) -> io::Result<()> {
+        let packet = self.packet(sequences, flow, flags, &[]);
+        self.writer.write(&[packet])
+    }
+
+    // Builds the IP packet and advances the sequence number of the sending side.
+    fn packet(&self, sequences: &mut Sequences, flow: Flow, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let (source, destination, seq, ack) = match flow {
+            Flow::ClientToServer => (self.client, self.server, sequences.client_next, sequences.server_next),
+            Flow::ServerToClient => (self.server, self.client, sequences.server_next, sequences.client_next),
+        };
+        let ack = if flags & ACK != 0 { ack } else { 0 };
+        // SYN and FIN take a sequence number like a byte does
+        let advance = payload.len() as u32 + if flags & (SYN | FIN) != 0 { 1 } else { 0 };
+        match flow {
+            Flow::ClientToServer => sequences.client_next = seq.wrapping_add(advance),
+            Flow::ServerToClient => sequences.server_next = seq.wrapping_add(advance),
+        }
+        sequences.ip_id = sequences.ip_id.wrapping_add(1);
+        let mut tcp = Vec::with_capacity(TCP_HEADER_LEN + payload.len());
+        tcp.extend_from_slice(&source.port().to_be_bytes());
+        tcp.extend_from_slice(&destination.port().to_be_bytes());
+        tcp.extend_from_slice(&seq.to_be_bytes());
+        tcp.extend_from_slice(&ack.to_be_bytes());
+        tcp.push((TCP_HEADER_LEN as u8 / 4) << 4);
+        tcp.push(flags);
+        tcp.extend_from_slice(&u16::MAX.to_be_bytes());  // window
+        tcp.extend_from_slice(&[0, 0, 0, 0]);  // checksum and urgent pointer
+        tcp.extend_from_slice(payload);
+        ip_packet(source.ip(), destination.ip(), sequences.ip_id, tcp)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut sequences = self.sequences.lock().unwrap();
+        let _ = self.write_control(&mut sequences, Flow::ClientToServer, FIN | ACK)
+            .and_then(|()| self.write_control(&mut sequences, Flow::ServerToClient, FIN | ACK))
+            .and_then(|()| self.write_control(&mut sequences, Flow::ClientToServer, ACK));
+    }
+}
+
+// Wraps the TCP segment whose checksum is left zero into an IP packet and fills the checksums.
+fn ip_packet(source: IpAddr, destination: IpAddr, ip_id: u16, mut tcp: Vec<u8>) -> Vec<u8> {
+    let tcp_len = tcp.len() as u16;
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            let mut pseudo_header = vec![];
+            pseudo_header.extend_from_slice(&source.octets());
+            pseudo_header.extend_from_slice(&destination.octets());
+            pseudo_header.extend_from_slice(&[0, TCP_PROTOCOL]);
+            pseudo_header.extend_from_slice(&tcp_len.to_be_bytes());
+            fill_tcp_checksum(&pseudo_header, &mut tcp);
+            let mut packet = Vec::with_capacity(IPV4_HEADER_LEN + tcp.len());
+            packet.push(0x45);  // version 4, header of 5 words
+            packet.push(0);  // type of service
+            packet.extend_from_slice(&(IPV4_HEADER_LEN as u16 + tcp_len).to_be_bytes());
+            packet.extend_from_slice(&ip_id.to_be_bytes());
+            packet.extend_from_slice(&0x4000u16.to_be_bytes());  // don't fragment
+            packet.push(TTL);
+            packet.push(TCP_PROTOCOL);
+            packet.extend_from_slice(&[0, 0]);  // header checksum
+            packet.extend_from_slice(&source.octets());
+            packet.extend_from_slice(&destination.octets());
+            let checksum = internet_checksum(&[&packet]);
+            packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+            packet.extend_from_slice(&tcp);
+            packet
+        },
+        (source, destination) => {
+            let (source, destination) = (to_ipv6(source), to_ipv6(destination));
+            let mut pseudo_header = vec![];
+            pseudo_header.extend_from_slice(&source.octets());
+            pseudo_header.extend_from_slice(&destination.octets());
+            pseudo_header.extend_from_slice(&u32::from(tcp_len).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, TCP_PROTOCOL]);
+            fill_tcp_checksum(&pseudo_header, &mut tcp);
+            let mut packet = Vec::with_capacity(IPV6_HEADER_LEN + tcp.len());
+            packet.extend_from_slice(&0x6000_0000u32.to_be_bytes());  // version 6, no traffic class and flow label
+            packet.extend_from_slice(&tcp_len.to_be_bytes());
+            packet.push(TCP_PROTOCOL);
+            packet.push(TTL);
+            packet.extend_from_slice(&source.octets());
+            packet.extend_from_slice(&destination.octets());
+            packet.extend_from_slice(&tcp);
+            packet
+        },
+    }
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn fill_tcp_checksum(pseudo_header: &[u8], tcp: &mut [u8]) {
+    let checksum = internet_checksum(&[pseudo_header, tcp]);
+    tcp[16..18].copy_from_slice(&checksum.to_be_bytes());
+}
+
+// RFC 1071, the parts but the last one are of even lengths.
+fn internet_checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum = 0u32;
+    for part in parts {
+        for pair in part.chunks(2) {
+            let word = u16::from_be_bytes([pair[0], pair.get(1).cloned().unwrap_or(0)]);
+            sum = sum.wrapping_add(u32::from(word));
+        }
+    }
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn is_encryption_request(bytes: &[u8]) -> bool {
+    bytes.len() == 8 && matches!(&bytes[4..], [0x04, 0xD2, 0x16, 0x2F] | [0x04, 0xD2, 0x16, 0x30])  // 1234.5679, 1234.5680
+}
+
+// Blocks are padded to 32 bits and have the total length both before and after the body.
+fn push_block(target: &mut Vec<u8>, block_type: u32, body: impl FnOnce(&mut Vec<u8>)) {
+    let mut block = vec![];
+    block.extend_from_slice(&block_type.to_le_bytes());
+    block.extend_from_slice(&[0; 4]);
+    body(&mut block);
+    while block.len() % 4 != 0 {
+        block.push(0);
+    }
+    let total_len = (block.len() + 4) as u32;
+    block[4..8].copy_from_slice(&total_len.to_le_bytes());
+    block.extend_from_slice(&total_len.to_le_bytes());
+    target.extend(block);
+}
+
+impl Debug for PcapngWriter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("PcapngWriter").finish()
+    }
+}
+
+impl Debug for PcapngStream {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("PcapngStream")
+            .field("client", &self.0.client)
+            .field("server", &self.0.server)
+            .finish()
+    }
+}
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const LINKTYPE_RAW: u16 = 101;  // packets begin with an IPv4 or IPv6 header
+
+const IPV4_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const TCP_HEADER_LEN: usize = 20;
+const TCP_PROTOCOL: u8 = 6;
+const TTL: u8 = 64;
+const MAX_SEGMENT: usize = 65535 - IPV4_HEADER_LEN - TCP_HEADER_LEN;
+const ISN_STEP: u32 = 0x0100_0000;
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+#[cfg(test)]
+mod tests {
+    use super::{ACK, FIN, PSH, PcapngWriter, SYN, internet_checksum};
+
+    use ::std::convert::TryInto;
+    use ::std::io::{self, Write};
+    use ::std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBytes(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBytes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn u32_le(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes(bytes[..4].try_into().unwrap())
+    }
+
+    fn u32_be(bytes: &[u8]) -> u32 {
+        u32::from_be_bytes(bytes[..4].try_into().unwrap())
+    }
+
+    // Returns the blocks as (type, body).
+    fn blocks(mut bytes: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        let mut blocks = vec![];
+        while !bytes.is_empty() {
+            let total_len = u32_le(&bytes[4..]) as usize;
+            assert_eq!(total_len as u32, u32_le(&bytes[total_len - 4..]));
+            blocks.push((u32_le(bytes), bytes[8..total_len - 4].to_vec()));
+            bytes = &bytes[total_len..];
+        }
+        blocks
+    }
+
+    fn packets(blocks: &[(u32, Vec<u8>)]) -> Vec<Vec<u8>> {
+        blocks[2..].iter()
+            .map(|(block_type, body)| {
+                assert_eq!(6, *block_type);
+                let len = u32_le(&body[12..]) as usize;
+                body[20..20 + len].to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tcp_stream() {
+        let file = SharedBytes::default();
+        let writer = Arc::new(PcapngWriter::new(file.clone()).unwrap());
+        let stream = writer.connection("10.0.0.1:40000".parse().unwrap(), "10.0.0.2:5432".parse().unwrap()).unwrap();
+        stream.sent_to_server(&[0, 0, 0, 8, 0x04, 0xD2, 0x16, 0x2F]).unwrap();
+        stream.sent_to_server(b"\0\0\0\x0a\0\x03\0\0\0\0").unwrap();
+        stream.sent_to_client(b"Z\0\0\0\x05I").unwrap();
+        stream.sent_to_server(b"X\0\0\0\x04").unwrap();
+        drop((stream, writer));
+        let bytes = file.0.lock().unwrap().clone();
+
+        let blocks = blocks(&bytes);
+        assert_eq!(0x0A0D0D0A, blocks[0].0);
+        assert_eq!(0x1A2B3C4D, u32_le(&blocks[0].1));
+        assert_eq!((1, 101), (blocks[1].0, u16::from_le_bytes([blocks[1].1[0], blocks[1].1[1]])));
+        let packets = packets(&blocks);
+        // (from client, flags, seq, ack, payload) with sequence numbers relative to the initial ones
+        let client_isn = u32_be(&packets[0][24..]);
+        let server_isn = u32_be(&packets[1][24..]);
+        let segments: Vec<_> = packets.iter()
+            .map(|packet| {
+                assert_eq!(0x45, packet[0]);
+                assert_eq!(packet.len(), u16::from_be_bytes([packet[2], packet[3]]) as usize);
+                assert_eq!(0, internet_checksum(&[&packet[..20]]));
+                let mut pseudo_header = packet[12..20].to_vec();
+                pseudo_header.extend_from_slice(&[0, 6, 0, (packet.len() - 20) as u8]);
+                assert_eq!(0, internet_checksum(&[&pseudo_header, &packet[20..]]));
+                let from_client = packet[12..16] == [10, 0, 0, 1];
+                let (isn, peer_isn) = if from_client { (client_isn, server_isn) } else { (server_isn, client_isn) };
+                let ack = match u32_be(&packet[28..]) {
+                    0 => 0,
+                    ack => ack.wrapping_sub(peer_isn),
+                };
+                (from_client, packet[33], u32_be(&packet[24..]).wrapping_sub(isn), ack, packet[40..].to_vec())
+            })
+            .collect();
+        assert_eq!(vec![
+            (true, SYN, 0, 0, vec![]),
+            (false, SYN | ACK, 0, 1, vec![]),
+            (true, ACK, 1, 1, vec![]),
+            (true, PSH | ACK, 1, 1, b"\0\0\0\x0a\0\x03\0\0\0\0".to_vec()),
+            (false, PSH | ACK, 1, 11, b"Z\0\0\0\x05I".to_vec()),
+            (true, PSH | ACK, 11, 7, b"X\0\0\0\x04".to_vec()),
+            (true, FIN | ACK, 16, 7, vec![]),
+            (false, FIN | ACK, 7, 17, vec![]),
+            (true, ACK, 17, 8, vec![]),
+        ], segments);
+    }
+
+    #[test]
+    fn ipv6() {
+        let file = SharedBytes::default();
+        let writer = Arc::new(PcapngWriter::new(file.clone()).unwrap());
+        let stream = writer.connection("[::1]:40000".parse().unwrap(), "127.0.0.1:5432".parse().unwrap()).unwrap();
+        stream.sent_to_server(b"X\0\0\0\x04").unwrap();
+        drop((stream, writer));
+        let bytes = file.0.lock().unwrap().clone();
+        let packet = &packets(&blocks(&bytes))[3];
+        assert_eq!(0x60, packet[0]);
+        assert_eq!(20 + 5, u16::from_be_bytes([packet[4], packet[5]]));
+        assert_eq!(b"X\0\0\0\x04", &packet[60..]);
+        assert_eq!(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 127, 0, 0, 1], &packet[24..40]);
+    }
+}
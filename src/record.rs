@@ -0,0 +1,311 @@
+use crate::msg::util::decode::MsgDecode;
+use crate::msg::util::read::{ReadError, ReadResult, read_msg};
+
+use ::futures::executor::block_on;
+use ::futures::io::Cursor;
+use ::std::convert::TryFrom;
+use ::std::fmt::{self, Debug, Formatter};
+use ::std::fs::File;
+use ::std::io::{self, BufReader, BufWriter, Read, Write};
+use ::std::sync::{Arc, Mutex};
+use ::std::sync::mpsc::{self, Receiver, Sender};
+use ::std::thread::{self, JoinHandle};
+use ::std::time::{Duration, Instant};
+
+// A capture file is MAGIC followed by records of
+//   connection id: u64, direction: u8, microseconds since the recorder was created: u64, length: u32, bytes
+// in network byte order. The bytes are a message as read by the conveyor: the type byte, the length and the body,
+// so read_msg decodes them. Initial messages have no type byte thus have a direction of their own.
+// Relayed bytes and single-byte answers to TLS and GSSAPI requests aren't messages and aren't recorded.
+// The file is written by a thread of its own, the records are complete once the recorder is dropped.
+pub struct Recorder {
+    writer: WriterThread,
+    started: Instant,
+}
+
+// Writes chunks on a thread of its own so async tasks don't wait for the file, and flushes whenever no chunk is waiting.
+// Dropping it waits for the chunks sent before.
+pub(crate) struct WriterThread {
+    sender: Mutex<Option<Sender<Vec<u8>>>>,  // None once dropping
+    thread: Option<JoinHandle<()>>,
+    failure: Arc<Mutex<Option<io::Error>>>,  // why the thread has stopped
+}
+
+// The recorder bound to a connection, each connection records through its own one.
+#[derive(Clone)]
+pub struct Recording {
+    recorder: Arc<Recorder>,
+    connection_id: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    FromBackend,
+    FromFrontend,
+    FromFrontendInitial,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub connection_id: u64,
+    pub direction: Direction,
+    pub timestamp: Duration,  // since the recorder was created
+    pub bytes: Vec<u8>,
+}
+
+pub struct CaptureReader<R> {
+    reader: R,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Self::new(File::create(path)?)
+    }
+
+    pub fn new(mut writer: impl Write + Send + 'static) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Self { writer: WriterThread::spawn(writer)?, started: Instant::now() })
+    }
+
+    pub fn connection(self: &Arc<Self>, connection_id: u64) -> Recording {
+        Recording { recorder: self.clone(), connection_id }
+    }
+
+    fn write(&self, connection_id: u64, direction: Direction, bytes: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let mut record = Vec::with_capacity(HEADER_LEN + bytes.len());
+        record.extend_from_slice(&connection_id.to_be_bytes());
+        record.push(direction.into());
+        record.extend_from_slice(&[0; 8]);  // timestamp
+        record.extend_from_slice(&len.to_be_bytes());
+        record.extend_from_slice(bytes);
+        self.writer.send_with(|| {
+            // taken in order of sending so timestamps grow along the file
+            let timestamp = self.started.elapsed().as_micros() as u64;
+            record[9..17].copy_from_slice(&timestamp.to_be_bytes());
+            record
+        })
+    }
+}
+
+impl WriterThread {
+    pub(crate) fn spawn(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let failure = Arc::new(Mutex::new(None));
+        let thread_failure = failure.clone();
+        let thread = thread::Builder::new().name("postgread-writer".into()).spawn(move || {
+            if let Err(err) = write_received(BufWriter::new(writer), receiver) {
+                *thread_failure.lock().unwrap() = Some(err);
+            }
+        })?;
+        Ok(Self { sender: Mutex::new(Some(sender)), thread: Some(thread), failure })
+    }
+
+    // The chunk is made under the lock, so chunks are written in the order they are made.
+    pub(crate) fn send_with(&self, make_chunk: impl FnOnce() -> Vec<u8>) -> io::Result<()> {
+        let sender = self.sender.lock().unwrap();
+        match sender.as_ref().map(|sender| sender.send(make_chunk())) {
+            Some(Ok(())) => Ok(()),
+            _ => Err(self.failure.lock().unwrap().take()
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "writer thread has stopped"))),
+        }
+    }
+}
+
+impl Drop for WriterThread {
+    fn drop(&mut self) {
+        self.sender.lock().unwrap().take();  // the thread ends after the chunks sent before
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn write_received(mut writer: impl Write, receiver: Receiver<Vec<u8>>) -> io::Result<()> {
+    while let Ok(chunk) = receiver.recv() {
+        writer.write_all(&chunk)?;
+        while let Ok(chunk) = receiver.try_recv() {
+            writer.write_all(&chunk)?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+impl Recording {
+    pub fn record(&self, direction: Direction, bytes: &[u8]) -> io::Result<()> {
+        self.recorder.write(self.connection_id, direction, bytes)
+    }
+}
+
+impl From<Direction> for u8 {
+    fn from(direction: Direction) -> u8 {
+        match direction {
+            Direction::FromBackend => b'B',
+            Direction::FromFrontend => b'F',
+            Direction::FromFrontendInitial => b'I',
+        }
+    }
+}
+
+impl Direction {
+    fn decode(byte: u8) -> io::Result<Self> {
+        match byte {
+            b'B' => Ok(Self::FromBackend),
+            b'F' => Ok(Self::FromFrontend),
+            b'I' => Ok(Self::FromFrontendInitial),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown direction {}", byte))),
+        }
+    }
+}
+
+impl Record {
+    pub fn type_byte(&self) -> Option<u8> {
+        match self.direction {
+            Direction::FromFrontendInitial => None,
+            _ => self.bytes.first().cloned(),
+        }
+    }
+
+    // Decodes the message as the conveyor has done, the caller picks Msg by the type byte and the direction.
+    pub fn decode<Msg: MsgDecode>(&self) -> ReadResult<Msg> {
+        if Msg::TYPE_BYTE_OPT != self.type_byte() {
+            return Err(ReadError::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("type byte {:?} isn't of the message", self.type_byte()),
+            )))
+        }
+        let after_type_byte = if Msg::TYPE_BYTE_OPT.is_some() { 1 } else { 0 };
+        let mut cursor = Cursor::new(&self.bytes[after_type_byte..]);
+        block_on(read_msg(&mut cursor))
+    }
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(path: &str) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a postgread capture"))
+        }
+        Ok(Self { reader })
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut header = [0u8; HEADER_LEN];
+        let mut filled = 0;
+        while filled < HEADER_LEN {
+            match self.reader.read(&mut header[filled..])? {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => filled += n,
+            }
+        }
+        let mut u64_bytes = [0u8; 8];
+        u64_bytes.copy_from_slice(&header[0..8]);
+        let connection_id = u64::from_be_bytes(u64_bytes);
+        let direction = Direction::decode(header[8])?;
+        u64_bytes.copy_from_slice(&header[9..17]);
+        let timestamp = Duration::from_micros(u64::from_be_bytes(u64_bytes));
+        let mut u32_bytes = [0u8; 4];
+        u32_bytes.copy_from_slice(&header[17..21]);
+        let mut bytes = vec![0u8; u32::from_be_bytes(u32_bytes) as usize];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(Some(Record { connection_id, direction, timestamp, bytes }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+impl Debug for Recorder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+impl Debug for Recording {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Recording")
+            .field("connection_id", &self.connection_id)
+            .finish()
+    }
+}
+
+const MAGIC: &[u8] = b"PGREAD\0\x01";  // the last byte is the format version
+const HEADER_LEN: usize = 8 + 1 + 8 + 4;
+
+#[cfg(test)]
+mod tests {
+    use super::{CaptureReader, Direction, Recorder};
+    use crate::msg::body::{Query, ReadyForQuery};
+    use crate::msg::body::initial::{Initial, Startup, StartupParam, Version};
+    use crate::msg::body::ready_for_query::Status;
+    use crate::msg::util::encode::encode_msg;
+
+    use ::std::io::{self, Write};
+    use ::std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBytes(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBytes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recorded_and_read() {
+        let startup = Initial::Startup(Startup {
+            version: Version::V3_0,
+            params: vec![StartupParam::new(b"user".to_vec(), b"alice".to_vec())],
+        });
+        let query = Query(b"select 1".to_vec());
+        let ready = ReadyForQuery { status: Status::Idle };
+        let file = SharedBytes::default();
+        let recorder = Arc::new(Recorder::new(file.clone()).unwrap());
+        let (first, second) = (recorder.connection(1), recorder.connection(2));
+        first.record(Direction::FromFrontendInitial, &encode_msg(&startup).unwrap()).unwrap();
+        second.record(Direction::FromFrontend, &encode_msg(&query).unwrap()).unwrap();
+        first.record(Direction::FromBackend, &encode_msg(&ready).unwrap()).unwrap();
+        drop((first, second, recorder));
+        let bytes = file.0.lock().unwrap().clone();
+
+        let records: Vec<_> = CaptureReader::new(&bytes[..]).unwrap().collect::<Result<_, _>>().unwrap();
+        let sessions: Vec<_> = records.iter().map(|record| (record.connection_id, record.direction, record.type_byte())).collect();
+        assert_eq!(vec![
+            (1, Direction::FromFrontendInitial, None),
+            (2, Direction::FromFrontend, Some(b'Q')),
+            (1, Direction::FromBackend, Some(b'Z')),
+        ], sessions);
+        assert!(records.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        assert_eq!(Ok(startup), records[0].decode::<Initial>().unwrap().msg_result);
+        assert_eq!(Ok(query), records[1].decode::<Query>().unwrap().msg_result);
+        assert_eq!(Ok(ready), records[2].decode::<ReadyForQuery>().unwrap().msg_result);
+        assert!(records[2].decode::<Query>().is_err());
+
+        let cut = &bytes[..bytes.len() - 1];
+        let read: Vec<_> = CaptureReader::new(cut).unwrap().collect();
+        assert_eq!(3, read.len());
+        assert_eq!(io::ErrorKind::UnexpectedEof, read[2].as_ref().unwrap_err().kind());
+        assert!(CaptureReader::new(&b"PGREAD\0\x02"[..]).is_err());
+    }
+}
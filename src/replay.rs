@@ -0,0 +1,633 @@
+use crate::auth::credentials::Credentials;
+use crate::convey::{BackendMsg, ConveyError, ConveyOptions, ConveyResult, Message, convey};
+use crate::msg::body::{CommandComplete, DataRow, ErrorResponse, Parse, Password, Query, ReadyForQuery, RowDescription, Terminate};
+use crate::msg::parts::Value;
+use crate::msg::util::decode::MsgDecode;
+use crate::msg::util::encode::encode_msg;
+use crate::record::{Direction, Record};
+use crate::tls::interface::{TlsChannelBinding, TlsProvider, TlsServer};
+use crate::tls::native::{NativeTlsClient, new_tls_connector};
+
+use ::async_std::net::TcpStream;
+use ::async_std::task;
+use ::async_trait::async_trait;
+use ::futures::channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded};
+use ::futures::future::{Either, Future, select};
+use ::futures::io::{AsyncRead, AsyncWrite};
+use ::futures::pin_mut;
+use ::futures::stream::Stream;
+use ::futures::task::{Context, Poll};
+use ::serde::Serialize;
+use ::std::collections::{HashMap, VecDeque};
+use ::std::fmt::{self, Display, Formatter};
+use ::std::io;
+use ::std::net::SocketAddr;
+use ::std::pin::Pin;
+use ::std::sync::{Arc, Mutex};
+use ::std::time::{Duration, Instant};
+
+// Connections of a capture are replayed one after another through the conveyor, so TLS and authentication
+// are negotiated with the target server anew. A recorded frontend message is sent at its recorded time since
+// the connection started multiplied by time_scale (0 sends at once) but never before backend has sent as many
+// ReadyForQuery as it had when the message was recorded.
+// Backend responses are compared segment by segment where a segment ends with ReadyForQuery: RowDescription
+// by column names and types, DataRow by values, CommandComplete by tag and ErrorResponse by SQLSTATE.
+// A connection whose backend doesn't end a segment within segment_timeout since frontend sent its last message,
+// or was due to send it, stops with a timeout error.
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    pub time_scale: f64,
+    pub segment_timeout: Duration,
+    // Answer backend's challenge like the conveyor does, recorded password messages aren't replayed then.
+    // Without credentials they are, which only passes cleartext password authentication.
+    pub backend_credentials: Option<Arc<Credentials>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub connections: Vec<ConnectionReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionReport {
+    pub connection_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,  // why replaying stopped before the recorded end
+    pub differences: Vec<Difference>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Difference {
+    pub segment: usize,  // the number of ReadyForQuery before it
+    pub statements: Vec<String>,  // sent by frontend within the segment
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Item {
+    RowDescription(Vec<(Vec<u8>, u32)>),
+    DataRow(Vec<Value>),
+    CommandComplete(Vec<u8>),
+    ErrorResponse(Option<Vec<u8>>),
+}
+
+#[derive(Debug, Default)]
+struct Segment {
+    statements: Vec<String>,
+    items: Vec<Item>,
+}
+
+struct Chunk {
+    at: Duration,  // since the connection started
+    ready_before: usize,  // ReadyForQuery which backend had sent before frontend sent it
+    bytes: Vec<u8>,
+}
+
+// What the recorded frontend sent and what the recorded backend answered.
+struct Script {
+    chunks: VecDeque<Chunk>,
+    expected: Vec<Segment>,
+}
+
+// The frontend conveyed to the target server, it reads the script and ignores what it is written.
+struct ReplayedFrontend {
+    chunks: VecDeque<Chunk>,
+    sent_of_chunk: usize,
+    ready_seen: usize,
+    ready_receiver: UnboundedReceiver<()>,
+    started: Instant,
+    time_scale: f64,
+    delay: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    recorded_ready: usize,  // ReadyForQuery which the recorded backend sent
+    watch: Arc<Mutex<Watch>>,
+}
+
+// Whether backend owes frontend a ReadyForQuery and since when, watched for the segment timeout.
+struct Watch {
+    ready: usize,  // ReadyForQuery which backend has sent
+    awaited: usize,  // ReadyForQuery which backend owes for the chunks sent
+    since: Instant,  // the last ReadyForQuery or chunk, or when the next chunk is due if later
+}
+
+// Lets the conveyor switch the replayed frontend to TLS while it's neither encrypted nor decrypted.
+// Only the leg to the target server is really encrypted then.
+struct NoTlsServer;
+
+pub async fn replay<Records>(records: Records, target: SocketAddr, options: &ReplayOptions) -> io::Result<Report>
+where Records: IntoIterator<Item = io::Result<Record>> {
+    let mut connections: Vec<(u64, Vec<Record>)> = vec![];
+    let mut indexes = HashMap::new();
+    for record in records {
+        let record = record?;
+        let index = *indexes.entry(record.connection_id).or_insert_with(|| {
+            connections.push((record.connection_id, vec![]));
+            connections.len() - 1
+        });
+        connections[index].1.push(record);
+    }
+    let mut report = Report::default();
+    for (connection_id, records) in connections {
+        let replay_passwords = options.backend_credentials.is_none();
+        let (differences, error) = match Script::new(&records, replay_passwords) {
+            Ok(script) => match TcpStream::connect(target).await {
+                Ok(backend) => {
+                    let (actual, result) = replay_connection(&script, backend, options).await;
+                    (compare(&script.expected, &actual), result.err().map(|err| format!("{:?}", err)))
+                },
+                // the other connections are replayed and reported anyway
+                Err(err) => (vec![], Some(format!("could not connect to target server: {:?}", err))),
+            },
+            Err(err) => (vec![], Some(err)),
+        };
+        report.connections.push(ConnectionReport { connection_id, error, differences });
+    }
+    Ok(report)
+}
+
+impl Report {
+    pub fn differs(&self) -> bool {
+        self.connections.iter().any(|connection| connection.error.is_some() || !connection.differences.is_empty())
+    }
+}
+
+async fn replay_connection(script: &Script, backend: TcpStream, options: &ReplayOptions) -> (Vec<Vec<Item>>, ConveyResult<()>) {
+    let (ready_sender, ready_receiver) = unbounded();
+    let watch = Arc::new(Mutex::new(Watch { ready: 0, awaited: 0, since: Instant::now() }));
+    let frontend = ReplayedFrontend {
+        chunks: script.chunks.iter()
+            .map(|chunk| Chunk { at: chunk.at, ready_before: chunk.ready_before, bytes: chunk.bytes.clone() })
+            .collect(),
+        sent_of_chunk: 0,
+        ready_seen: 0,
+        ready_receiver,
+        started: Instant::now(),
+        time_scale: options.time_scale,
+        delay: None,
+        recorded_ready: script.expected.len() - 1,
+        watch: watch.clone(),
+    };
+    let convey_options = ConveyOptions {
+        lenient: true,
+        backend_credentials: options.backend_credentials.clone(),
+        ..Default::default()
+    };
+    let actual = Mutex::new(vec![vec![]]);
+    let connector = new_tls_connector();
+    let backend_tls_client = NativeTlsClient { connector: &connector, hostname: "localhost" };
+    let result = {
+        let conveying = convey(frontend, backend, NoTlsServer, backend_tls_client, convey_options, |msg| {
+            collect(msg, &actual, &watch, &ready_sender)
+        });
+        let watching = watch_segments(&watch, options.segment_timeout);
+        pin_mut!(conveying, watching);
+        match select(conveying, watching).await {
+            Either::Left((result, _)) => result,
+            Either::Right(((), _)) => Err(ConveyError::IoError(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("backend hasn't ended the segment within {:?}", options.segment_timeout),
+            ))),
+        }
+    };
+    (actual.into_inner().unwrap(), result)
+}
+
+// Returns once backend owes a ReadyForQuery for longer than the timeout.
+async fn watch_segments(watch: &Mutex<Watch>, timeout: Duration) {
+    loop {
+        let (waiting, deadline) = {
+            let watch = watch.lock().unwrap();
+            (watch.ready < watch.awaited, watch.since + timeout)
+        };
+        let now = Instant::now();
+        if waiting && now >= deadline {
+            return
+        }
+        task::sleep(if waiting { deadline - now } else { timeout }).await;
+    }
+}
+
+fn collect(msg: Message, actual: &Mutex<Vec<Vec<Item>>>, watch: &Mutex<Watch>, ready_sender: &UnboundedSender<()>) {
+    let mut actual = actual.lock().unwrap();
+    match msg {
+        Message::Backend(BackendMsg::ReadyForQuery(_)) => {
+            actual.push(vec![]);
+            let mut watch = watch.lock().unwrap();
+            watch.ready += 1;
+            watch.since = watch.since.max(Instant::now());
+            // the frontend is gone when conveying has stopped
+            let _ = ready_sender.unbounded_send(());
+        },
+        Message::Backend(msg) => if let Some(item) = Item::from_backend(&msg) {
+            actual.last_mut().unwrap().push(item);
+        },
+        _ => {},
+    }
+}
+
+// A segment missing on one side differs only if the other side has something in it.
+fn compare(expected: &[Segment], actual: &[Vec<Item>]) -> Vec<Difference> {
+    let no_items = vec![];
+    (0..expected.len().max(actual.len()))
+        .filter_map(|segment| {
+            let expected_segment = expected.get(segment);
+            let expected_items = expected_segment.map_or(&no_items, |expected| &expected.items);
+            let actual_items = actual.get(segment).unwrap_or(&no_items);
+            if expected_items == actual_items {
+                return None
+            }
+            Some(Difference {
+                segment,
+                statements: expected_segment.map_or(vec![], |expected| expected.statements.clone()),
+                expected: expected_items.iter().map(Item::to_string).collect(),
+                actual: actual_items.iter().map(Item::to_string).collect(),
+            })
+        })
+        .collect()
+}
+
+impl Script {
+    fn new(records: &[Record], replay_passwords: bool) -> Result<Self, String> {
+        let started = records.first().map_or(Duration::default(), |record| record.timestamp);
+        let mut chunks = VecDeque::new();
+        let mut expected = vec![Segment::default()];
+        for record in records {
+            let segment = expected.last_mut().unwrap();
+            match (record.direction, record.type_byte()) {
+                (Direction::FromBackend, Some(ReadyForQuery::TYPE_BYTE)) => expected.push(Segment::default()),
+                (Direction::FromBackend, _) => segment.items.extend(Item::from_record(record)?),
+                (Direction::FromFrontend, Some(Password::TYPE_BYTE)) if !replay_passwords => {},
+                (_, type_byte) => {
+                    match type_byte {
+                        Some(Query::TYPE_BYTE) => segment.statements.push(lossy(&decode::<Query>(record)?.0)),
+                        Some(Parse::TYPE_BYTE) => segment.statements.push(lossy(&decode::<Parse>(record)?.query.0)),
+                        _ => {},
+                    }
+                    let at = record.timestamp.checked_sub(started).unwrap_or_default();
+                    chunks.push_back(Chunk { at, ready_before: expected.len() - 1, bytes: record.bytes.clone() });
+                },
+            }
+        }
+        // a recording cut before Terminate is finished as if it were sent after all answers
+        if let Some(last) = chunks.back() {
+            if last.bytes.first() != Some(&Terminate::TYPE_BYTE) {
+                let bytes = encode_msg(&Terminate {}).map_err(|err| format!("{:?}", err))?;
+                chunks.push_back(Chunk { at: last.at, ready_before: expected.len() - 1, bytes });
+            }
+        }
+        Ok(Self { chunks, expected })
+    }
+}
+
+impl Item {
+    fn from_backend(msg: &BackendMsg) -> Option<Self> {
+        match msg {
+            BackendMsg::RowDescription(msg) => Some(Self::row_description(msg)),
+            BackendMsg::DataRow(msg) => Some(Self::DataRow(msg.columns.clone())),
+            BackendMsg::CommandComplete(msg) => Some(Self::CommandComplete(msg.tag.clone())),
+            BackendMsg::ErrorResponse(msg) => Some(Self::ErrorResponse(msg.0.code.clone())),
+            _ => None,
+        }
+    }
+
+    fn from_record(record: &Record) -> Result<Option<Self>, String> {
+        Ok(match record.type_byte() {
+            Some(RowDescription::TYPE_BYTE) => Some(Self::row_description(&decode(record)?)),
+            Some(DataRow::TYPE_BYTE) => Some(Self::DataRow(decode::<DataRow>(record)?.columns)),
+            Some(CommandComplete::TYPE_BYTE) => Some(Self::CommandComplete(decode::<CommandComplete>(record)?.tag)),
+            Some(ErrorResponse::TYPE_BYTE) => Some(Self::ErrorResponse(decode::<ErrorResponse>(record)?.0.code)),
+            _ => None,
+        })
+    }
+
+    fn row_description(msg: &RowDescription) -> Self {
+        Self::RowDescription(msg.fields.iter().map(|field| (field.name.clone(), field.type_oid)).collect())
+    }
+}
+
+fn decode<Msg: MsgDecode>(record: &Record) -> Result<Msg, String> {
+    let undecodable = |err| format!("recorded {:?} is undecodable: {}", record.type_byte().map(char::from), err);
+    let read_data = record.decode::<Msg>().map_err(|err| undecodable(format!("{:?}", err)))?;
+    read_data.msg_result.map_err(|err| undecodable(format!("{:?}", err)))
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::RowDescription(fields) => {
+                let fields: Vec<_> = fields.iter().map(|(name, type_oid)| format!("{}:{}", lossy(name), type_oid)).collect();
+                write!(f, "RowDescription {}", fields.join(", "))
+            },
+            Self::DataRow(columns) => {
+                let columns: Vec<_> = columns.iter().map(|column| match column {
+                    Value::Null => "NULL".to_string(),
+                    Value::Bytes(bytes) => format!("{:?}", lossy(&bytes.0)),
+                }).collect();
+                write!(f, "DataRow {}", columns.join(", "))
+            },
+            Self::CommandComplete(tag) => write!(f, "CommandComplete {}", lossy(tag)),
+            Self::ErrorResponse(code) => write!(f, "ErrorResponse {}", code.as_deref().map_or("without SQLSTATE".into(), lossy)),
+        }
+    }
+}
+
+impl ReplayedFrontend {
+    // Ready when the chunk may be sent, Pending while backend hasn't answered enough or it's too early.
+    fn poll_chunk_due(&mut self, cx: &mut Context) -> Poll<Option<()>> {
+        let (at, ready_before) = match self.chunks.front() {
+            Some(chunk) => (chunk.at, chunk.ready_before),
+            None => return Poll::Ready(None),
+        };
+        while self.ready_seen < ready_before {
+            match Pin::new(&mut self.ready_receiver).poll_next(cx) {
+                Poll::Ready(Some(())) => self.ready_seen += 1,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        if self.delay.is_none() {
+            let due = at.mul_f64(self.time_scale);
+            let elapsed = self.started.elapsed();
+            if elapsed >= due {
+                return Poll::Ready(Some(()))
+            }
+            self.delay = Some(Box::pin(task::sleep(due - elapsed)));
+        }
+        match self.delay.as_mut().map(|delay| delay.as_mut().poll(cx)) {
+            Some(Poll::Pending) => Poll::Pending,
+            _ => {
+                self.delay = None;
+                Poll::Ready(Some(()))
+            },
+        }
+    }
+}
+
+impl AsyncRead for ReplayedFrontend {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if self.sent_of_chunk == 0 {
+            match self.poll_chunk_due(cx) {
+                Poll::Ready(Some(())) => {},
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let this = &mut *self;
+        let chunk = this.chunks.front().expect("the due chunk is there");
+        let rest = &chunk.bytes[this.sent_of_chunk..];
+        let len = rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        this.sent_of_chunk += len;
+        if this.sent_of_chunk == chunk.bytes.len() {
+            // backend ends the segment of the chunk unless the recording was cut before it did
+            let awaited = (chunk.ready_before + 1).min(this.recorded_ready);
+            this.chunks.pop_front();
+            this.sent_of_chunk = 0;
+            let now = Instant::now();
+            let next_due = this.chunks.front().map_or(now, |next| this.started + next.at.mul_f64(this.time_scale));
+            let mut watch = this.watch.lock().unwrap();
+            watch.awaited = awaited;
+            watch.since = now.max(next_due);
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for ReplayedFrontend {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl TlsChannelBinding for ReplayedFrontend {
+    fn tls_server_end_point(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+impl<Plain> TlsProvider<Plain> for NoTlsServer
+where Plain: Send + Unpin {
+    type Tls = Plain;
+    type Error = io::Error;
+}
+
+#[async_trait]
+impl<Plain> TlsServer<Plain> for NoTlsServer
+where Plain: Send + Unpin {
+    async fn accept(&self, plain: Plain) -> Result<Plain, io::Error>
+    where Plain: 'async_trait {
+        Ok(plain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Difference, NoTlsServer, ReplayOptions, replay};
+    use crate::mock::{self, fixture::Fixture};
+    use crate::msg::body::{CommandComplete, DataRow, ErrorResponse, Query, ReadyForQuery, RowDescription, Terminate};
+    use crate::msg::body::error_and_notice_responses::ErrorOrNoticeFields;
+    use crate::msg::body::initial::{Initial, Startup, StartupParam, Version};
+    use crate::msg::body::ready_for_query::Status;
+    use crate::msg::body::row_description::Field;
+    use crate::msg::parts::{Bytes, Format, Value};
+    use crate::msg::util::decode::MsgDecode;
+    use crate::msg::util::encode::{MsgEncode, encode_msg};
+    use crate::record::{Direction, Record};
+
+    use ::async_std::net::{TcpListener, TcpStream};
+    use ::async_std::task;
+    use ::std::time::Duration;
+
+    const FIXTURE: &str = r#"
+        queries:
+          - pattern: (?i)^select id, name from users
+            columns: [{name: id, type_oid: 23}, {name: name}]
+            rows: [[1, alice], [2, null]]
+          - pattern: (?i)^drop
+            error: {code: "42501", message: permission denied}
+    "#;
+
+    fn record(direction: Direction, millis: u64, msg: &(impl MsgDecode + MsgEncode)) -> Record {
+        Record { connection_id: 7, direction, timestamp: Duration::from_millis(millis), bytes: encode_msg(msg).unwrap() }
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Bytes(Bytes(s.as_bytes().to_vec()))
+    }
+
+    fn users(second_type_oid: u32) -> RowDescription {
+        let field = |name: &[u8], type_oid| Field {
+            name: name.to_vec(),
+            column_oid: 0,
+            column_attr_num: 0,
+            type_oid,
+            type_size: -1,
+            type_modifier: -1,
+            format: Format::Text,
+        };
+        RowDescription { fields: vec![field(b"id", 23), field(b"name", second_type_oid)] }
+    }
+
+    #[test]
+    fn differences_against_mock() {
+        use Direction::*;
+        let ready = ReadyForQuery { status: Status::Idle };
+        let records = vec![
+            record(FromFrontendInitial, 100, &Initial::Startup(Startup {
+                version: Version::V3_0,
+                params: vec![StartupParam::new(b"user".to_vec(), b"alice".to_vec())],
+            })),
+            record(FromBackend, 101, &ready),
+            record(FromFrontend, 110, &Query(b"select id, name from users".to_vec())),
+            record(FromBackend, 111, &users(1043)),
+            record(FromBackend, 111, &DataRow { columns: vec![text("1"), text("alice")] }),
+            record(FromBackend, 111, &DataRow { columns: vec![text("2"), Value::Null] }),
+            record(FromBackend, 111, &CommandComplete { tag: b"SELECT 2".to_vec() }),
+            record(FromBackend, 111, &ready),
+            record(FromFrontend, 120, &Query(b"drop table users".to_vec())),
+            record(FromBackend, 121, &CommandComplete { tag: b"DROP TABLE".to_vec() }),
+            record(FromBackend, 121, &ready),
+            record(FromFrontend, 130, &Query(b"select id, name from users".to_vec())),
+            record(FromBackend, 131, &users(25)),
+            record(FromBackend, 131, &DataRow { columns: vec![text("1"), text("alice")] }),
+            record(FromBackend, 131, &DataRow { columns: vec![text("2"), Value::Null] }),
+            record(FromBackend, 131, &CommandComplete { tag: b"SELECT 2".to_vec() }),
+            record(FromBackend, 131, &ready),
+            record(FromFrontend, 140, &Terminate {}),
+        ];
+        let report = task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let target = listener.local_addr().unwrap();
+            let mock_server = task::spawn(async move {
+                let fixture = Fixture::parse(FIXTURE).unwrap();
+                let (client, _) = listener.accept().await.unwrap();
+                mock::serve(client, NoTlsServer, &fixture, |_| {}).await
+            });
+            let options = ReplayOptions { time_scale: 0.0, segment_timeout: Duration::from_secs(10), backend_credentials: None };
+            let report = replay(records.into_iter().map(Ok), target, &options).await.unwrap();
+            assert_ok!(mock_server.await);
+            report
+        });
+        assert!(report.differs());
+        assert_eq!(1, report.connections.len());
+        assert_eq!(7, report.connections[0].connection_id);
+        assert_eq!(None, report.connections[0].error);
+        assert_eq!(vec![
+            Difference {
+                segment: 1,
+                statements: vec!["select id, name from users".into()],
+                expected: vec![
+                    "RowDescription id:23, name:1043".into(),
+                    r#"DataRow "1", "alice""#.into(),
+                    r#"DataRow "2", NULL"#.into(),
+                    "CommandComplete SELECT 2".into(),
+                ],
+                actual: vec![
+                    "RowDescription id:23, name:25".into(),
+                    r#"DataRow "1", "alice""#.into(),
+                    r#"DataRow "2", NULL"#.into(),
+                    "CommandComplete SELECT 2".into(),
+                ],
+            },
+            Difference {
+                segment: 2,
+                statements: vec!["drop table users".into()],
+                expected: vec!["CommandComplete DROP TABLE".into()],
+                actual: vec!["ErrorResponse 42501".into()],
+            },
+        ], report.connections[0].differences);
+        let yaml = serde_yaml::to_string(&report).unwrap();
+        assert!(yaml.contains("connection_id: 7"), "{}", yaml);
+        assert!(!yaml.contains("error:"), "{}", yaml);
+    }
+
+    #[test]
+    fn cut_recording_and_closed_server() {
+        let records = vec![
+            record(Direction::FromFrontendInitial, 0, &Initial::Startup(Startup {
+                version: Version::V3_0,
+                params: vec![StartupParam::new(b"user".to_vec(), b"alice".to_vec())],
+            })),
+            record(Direction::FromBackend, 1, &ReadyForQuery { status: Status::Idle }),
+            record(Direction::FromFrontend, 2, &Query(b"select 1".to_vec())),
+            record(Direction::FromBackend, 3, &ErrorResponse(ErrorOrNoticeFields {
+                code: Some(b"42P01".to_vec()),
+                ..Default::default()
+            })),
+        ];
+        let report = task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let target = listener.local_addr().unwrap();
+            let closing_server = task::spawn(async move {
+                let _: TcpStream = listener.accept().await.unwrap().0;
+            });
+            let options = ReplayOptions { time_scale: 1.0, segment_timeout: Duration::from_secs(10), backend_credentials: None };
+            let report = replay(records.into_iter().map(Ok), target, &options).await.unwrap();
+            closing_server.await;
+            report
+        });
+        assert!(report.differs());
+        assert!(report.connections[0].error.is_some());
+        assert_eq!(1, report.connections[0].differences.len());
+        assert_eq!(vec!["ErrorResponse 42P01".to_string()], report.connections[0].differences[0].expected);
+    }
+
+    #[test]
+    fn silent_server() {
+        let records = vec![
+            record(Direction::FromFrontendInitial, 0, &Initial::Startup(Startup {
+                version: Version::V3_0,
+                params: vec![StartupParam::new(b"user".to_vec(), b"alice".to_vec())],
+            })),
+            record(Direction::FromBackend, 1, &ReadyForQuery { status: Status::Idle }),
+            record(Direction::FromFrontend, 2, &Terminate {}),
+        ];
+        let report = task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let target = listener.local_addr().unwrap();
+            let silent_server = task::spawn(async move {
+                let (client, _) = listener.accept().await.unwrap();
+                task::sleep(Duration::from_secs(5)).await;
+                drop(client);
+            });
+            let options = ReplayOptions { time_scale: 0.0, segment_timeout: Duration::from_millis(100), backend_credentials: None };
+            let report = replay(records.into_iter().map(Ok), target, &options).await.unwrap();
+            drop(silent_server);
+            report
+        });
+        assert!(report.differs());
+        let error = report.connections[0].error.as_deref().unwrap();
+        assert!(error.contains("TimedOut"), "{}", error);
+    }
+
+    #[test]
+    fn unreachable_server() {
+        let records = vec![
+            Record { connection_id: 7, ..record(Direction::FromFrontend, 0, &Terminate {}) },
+            Record { connection_id: 8, ..record(Direction::FromFrontend, 0, &Terminate {}) },
+        ];
+        let report = task::block_on(async {
+            let target = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+            let options = ReplayOptions { time_scale: 0.0, segment_timeout: Duration::from_secs(10), backend_credentials: None };
+            replay(records.into_iter().map(Ok), target, &options).await.unwrap()
+        });
+        assert!(report.differs());
+        assert_eq!(vec![7, 8], report.connections.iter().map(|connection| connection.connection_id).collect::<Vec<_>>());
+        for connection in &report.connections {
+            let error = connection.error.as_deref().unwrap();
+            assert!(error.starts_with("could not connect to target server"), "{}", error);
+        }
+    }
+}
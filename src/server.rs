@@ -1,15 +1,23 @@
-use crate::convey::{Message, convey};
-use crate::tls::native::{NativeTlsServer, NativeTlsClient};
+use crate::auth::credentials::Credentials;
+use crate::auth::secrets::Secrets;
+use crate::convey::{ConveyOptions, Message, convey};
+use crate::firewall::Rules;
+use crate::mock::{self, fixture::Fixture};
+use crate::pcapng::PcapngWriter;
+use crate::record::{CaptureReader, Recorder};
+use crate::replay::{self, ReplayOptions, Report};
+use crate::tls::native::{NativeTlsServer, NativeTlsClient, new_tls_connector};
 
 use ::async_std::net::{TcpListener, TcpStream};
 use ::async_std::stream::StreamExt;
 use ::async_std::task;
-use ::async_native_tls::{TlsAcceptor, TlsConnector};
+use ::async_native_tls::TlsAcceptor;
 use ::std::fs;
 use ::std::io;
 use ::std::net::{IpAddr, SocketAddr};
 use ::std::sync::Arc;
 use ::std::sync::atomic::{AtomicUsize, Ordering};
+use ::std::time::Duration;
 use ::chrono::{Local, SecondsFormat};
 use ::structopt::StructOpt;
 
@@ -23,7 +31,7 @@ pub struct Config {
     pub listen_port: u16,
 
     #[structopt(long = "target-host")]
-    pub target_host: String,
+    pub target_host: Option<String>,  // required unless mock_fixture_file is given
 
     #[structopt(long = "target-port", default_value = "5432")]
     pub target_port: u16,
@@ -33,19 +41,67 @@ pub struct Config {
 
     #[structopt(long = "cert-p12-password", default_value = "")]
     pub cert_p12_password: String,
+
+    #[structopt(long = "lenient")]
+    pub lenient: bool,
+
+    #[structopt(long = "relay-fallback")]
+    pub relay_fallback: bool,
+
+    #[structopt(long = "scram-secrets-file")]
+    pub scram_secrets_file: Option<String>,
+
+    #[structopt(long = "auth-file")]
+    pub auth_file: Option<String>,
+
+    #[structopt(long = "backend-credentials-file")]
+    pub backend_credentials_file: Option<String>,
+
+    #[structopt(long = "firewall-rules-file")]
+    pub firewall_rules_file: Option<String>,
+
+    #[structopt(long = "read-only")]
+    pub read_only: bool,
+
+    #[structopt(long = "record-file")]
+    pub record_file: Option<String>,
+
+    // Write the decrypted traffic of each connection as a TCP stream for Wireshark.
+    #[structopt(long = "pcapng-file")]
+    pub pcapng_file: Option<String>,
+
+    // Answer clients from this fixture instead of conveying to the target server.
+    #[structopt(long = "mock-fixture-file")]
+    pub mock_fixture_file: Option<String>,
+
+    // Replay this capture against the target server and report how its answers differ instead of listening.
+    #[structopt(long = "replay-file")]
+    pub replay_file: Option<String>,
+
+    // Multiplies the recorded pauses between messages while replaying, 0 sends them without pauses.
+    #[structopt(long = "replay-time-scale", default_value = "1")]
+    pub replay_time_scale: f64,
+
+    // Seconds to wait for the target server to end a segment with ReadyForQuery while replaying.
+    #[structopt(long = "replay-segment-timeout", default_value = "30")]
+    pub replay_segment_timeout: u64,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_client<Callback>(
     target_host: String,
     target_port: u16,
     tls_acceptor: TlsAcceptor,
+    mut options: ConveyOptions,
+    pcapng: Option<Arc<PcapngWriter>>,
     client_id: usize,
     client: TcpStream,
     callback: Arc<Callback>,
 ) -> io::Result<()>
 where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
     let listen_port = client.local_addr().map(|addr| addr.port()).unwrap_or(0);
-    println!("postgread[:{}] #{} is new connection from {:?}", listen_port, client_id, client.peer_addr().unwrap());
+    let client_endpoint = client.peer_addr()?;
+    println!("postgread[:{}] #{} is new connection from {:?}", listen_port, client_id, client_endpoint);
     let target_ip = target_host.parse()
         .map_err(|err| io::Error::new(io::ErrorKind::NotConnected, err))?;
     let server_endpoint = SocketAddr::new(target_ip, target_port);
@@ -53,9 +109,13 @@ where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
         match TcpStream::connect(&server_endpoint).await {
             Ok(server) => {
                 println!("{} postgread[:{}] #{} connected to target server {}", format_now(), listen_port, client_id, server.local_addr().unwrap());
+                match pcapng.map(|pcapng| pcapng.connection(client_endpoint, server_endpoint)).transpose() {
+                    Ok(stream) => options.pcapng = stream,
+                    Err(err) => println!("{} postgread[:{}] #{} could not capture to pcapng: {:?}", format_now(), listen_port, client_id, err),
+                }
                 let frontend_tls_server = NativeTlsServer(&tls_acceptor);
                 let backend_tls_client = NativeTlsClient { connector: &new_tls_connector(), hostname: "localhost" };
-                let result = convey(client, server, frontend_tls_server, backend_tls_client, &*callback).await;
+                let result = convey(client, server, frontend_tls_server, backend_tls_client, options, &*callback).await;
                 println!("{} postgread[:{}] #{} stopped conveying with {:?}", format_now(), listen_port, client_id, result);
             },
             Err(err) => {
@@ -66,6 +126,24 @@ where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
     Ok(())
 }
 
+async fn handle_mock_client<Callback>(
+    tls_acceptor: TlsAcceptor,
+    fixture: Arc<Fixture>,
+    client_id: usize,
+    client: TcpStream,
+    callback: Arc<Callback>,
+) -> io::Result<()>
+where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
+    let listen_port = client.local_addr().map(|addr| addr.port()).unwrap_or(0);
+    println!("postgread[:{}] #{} is new connection from {:?}", listen_port, client_id, client.peer_addr().unwrap());
+    task::spawn(async move {
+        let frontend_tls_server = NativeTlsServer(&tls_acceptor);
+        let result = mock::serve(client, frontend_tls_server, &fixture, &*callback).await;
+        println!("{} postgread[:{}] #{} stopped mocking with {:?}", format_now(), listen_port, client_id, result);
+    });
+    Ok(())
+}
+
 fn tls_error_to_io_error(tls_error: native_tls::Error) -> io::Error {
     io::Error::new(io::ErrorKind::Other, tls_error.to_string())
 }
@@ -78,10 +156,6 @@ fn new_tls_acceptor(config: &Config) -> io::Result<TlsAcceptor> {
     tls_acceptor_impl.map(TlsAcceptor::from).map_err(tls_error_to_io_error)
 }
 
-fn new_tls_connector() -> TlsConnector {
-    TlsConnector::new().danger_accept_invalid_certs(true)  // TODO make it configurable
-}
-
 pub async fn listen(config: Config) -> io::Result<Server> {
     let tls_acceptor = new_tls_acceptor(&config)?;
     let socket = SocketAddr::new(config.listen_addr, config.listen_port);
@@ -104,8 +178,34 @@ impl Server {
 pub async fn loop_accepting<Callback>(server: Server, callback: Arc<Callback>) -> io::Result<()>
 where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
     let Server { tls_acceptor, tcp_listener, config } = server;
-    let target_host = config.target_host;
+    let mock_fixture = config.mock_fixture_file.as_deref().map(Fixture::load).transpose()?.map(Arc::new);
+    let target_host = match (config.target_host, &mock_fixture) {
+        (Some(target_host), _) => target_host,
+        (None, Some(_)) => String::new(),  // never connected in mock mode
+        (None, None) => return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--target-host is required unless --mock-fixture-file is given",
+        )),
+    };
     let target_port = config.target_port;
+    let scram_secrets = config.scram_secrets_file.as_deref().map(Secrets::load).transpose()?.map(Arc::new);
+    let local_auth = config.auth_file.as_deref().map(Secrets::load).transpose()?.map(Arc::new);
+    let backend_credentials = config.backend_credentials_file.as_deref().map(Credentials::load).transpose()?.map(Arc::new);
+    let firewall = config.firewall_rules_file.as_deref().map(Rules::load).transpose()?.map(Arc::new);
+    let read_only = if config.read_only { Some(Arc::new(Rules::read_only())) } else { None };
+    let options = ConveyOptions {
+        lenient: config.lenient,
+        relay_fallback: config.relay_fallback,
+        scram_secrets,
+        local_auth,
+        backend_credentials,
+        firewall,
+        read_only,
+        recording: None,
+        pcapng: None,
+    };
+    let recorder = config.record_file.as_deref().map(Recorder::create).transpose()?.map(Arc::new);
+    let pcapng = config.pcapng_file.as_deref().map(PcapngWriter::create).transpose()?.map(Arc::new);
     let mut incoming = tcp_listener.incoming();
     let next_client_id = Arc::new(AtomicUsize::new(1));
     while let Some(stream) = incoming.next().await {
@@ -114,10 +214,19 @@ where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
         let next_client_id = next_client_id.clone();
         let target_host = target_host.clone();
         let callback = callback.clone();
+        let mut options = options.clone();
+        let recorder = recorder.clone();
+        let pcapng = pcapng.clone();
+        let mock_fixture = mock_fixture.clone();
         task::spawn(async move {
             let client_id = next_client_id.fetch_add(1, Ordering::SeqCst);
+            options.recording = recorder.map(|recorder| recorder.connection(client_id as u64));
             let local_port = stream.local_addr().map(|addr| addr.port()).unwrap_or(0);
-            handle_client(target_host, target_port, tls_acceptor, client_id, stream, callback).await.unwrap_or_else(|err| {
+            let handled = match mock_fixture {
+                Some(fixture) => handle_mock_client(tls_acceptor, fixture, client_id, stream, callback).await,
+                None => handle_client(target_host, target_port, tls_acceptor, options, pcapng, client_id, stream, callback).await,
+            };
+            handled.unwrap_or_else(|err| {
                 println!("{} postgread[:{}] #{} could not be handled: {:?}", format_now(), local_port, client_id, err)
             });
         });
@@ -125,6 +234,23 @@ where Callback: for<'a> Fn(Message<'a>) + Send + Sync + 'static {
     Ok(())
 }
 
+pub async fn replay_capture(config: &Config) -> io::Result<Report> {
+    let invalid_input = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message.to_string());
+    let replay_file = config.replay_file.as_deref().ok_or_else(|| invalid_input("--replay-file is required"))?;
+    let target_host = config.target_host.as_deref().ok_or_else(|| invalid_input("--target-host is required to replay"))?;
+    if !(config.replay_time_scale >= 0.0 && config.replay_time_scale.is_finite()) {
+        return Err(invalid_input("--replay-time-scale must be a non-negative number"))
+    }
+    let target_ip = target_host.parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::NotConnected, err))?;
+    let options = ReplayOptions {
+        time_scale: config.replay_time_scale,
+        segment_timeout: Duration::from_secs(config.replay_segment_timeout),
+        backend_credentials: config.backend_credentials_file.as_deref().map(Credentials::load).transpose()?.map(Arc::new),
+    };
+    replay::replay(CaptureReader::open(replay_file)?, SocketAddr::new(target_ip, config.target_port), &options).await
+}
+
 fn format_now() -> String {
     Local::now().to_rfc3339_opts(SecondsFormat::Secs, true)
 }
\ No newline at end of file
@@ -22,3 +22,9 @@ where Plain: Send + Unpin {
     async fn accept(&self, plain: Plain) -> Result<Self::Tls, Self::Error>
     where Plain: 'async_trait;
 }
+
+pub trait TlsChannelBinding {
+    // Hash of the server certificate for tls-server-end-point channel binding (RFC 5929),
+    // None if the certificate's signature algorithm doesn't define the hash.
+    fn tls_server_end_point(&self) -> Option<Vec<u8>>;
+}
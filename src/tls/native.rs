@@ -11,6 +11,10 @@ pub struct NativeTlsClient<'a> {
 
 pub struct NativeTlsServer<'a>(pub &'a TlsAcceptor);
 
+pub fn new_tls_connector() -> TlsConnector {
+    TlsConnector::new().danger_accept_invalid_certs(true)  // TODO make it configurable
+}
+
 impl<'a, Plain> TlsProvider<Plain> for NativeTlsClient<'a>
 where Plain: Send + Unpin {
     type Tls = TlsStream<Plain>;
@@ -40,3 +44,11 @@ where Plain: AsyncRead + AsyncWrite + Send + Unpin {
         self.0.accept(plain).await
     }
 }
+
+impl<S> TlsChannelBinding for TlsStream<S>
+where S: AsyncRead + AsyncWrite + Unpin {
+    fn tls_server_end_point(&self) -> Option<Vec<u8>> {
+        // the stream knows whether it's a server or a client so the certificate is either own or peer's
+        TlsStream::tls_server_end_point(self).ok().flatten()
+    }
+}
@@ -150,10 +150,23 @@ async fn start_server(pg_server_port: u16) -> Result<Server, String> {
     let config = server::Config {
         listen_addr: Ipv4Addr::LOCALHOST.into(),
         listen_port: 0,
-        target_host: Ipv4Addr::LOCALHOST.to_string(),
+        target_host: Some(Ipv4Addr::LOCALHOST.to_string()),
         target_port: pg_server_port,
         cert_p12_file: concat!(env!("CARGO_MANIFEST_DIR"), "/try/cert.p12").to_owned(),
         cert_p12_password: "".to_owned(),
+        lenient: false,
+        relay_fallback: false,
+        scram_secrets_file: None,
+        auth_file: None,
+        backend_credentials_file: None,
+        firewall_rules_file: None,
+        read_only: false,
+        record_file: None,
+        pcapng_file: None,
+        mock_fixture_file: None,
+        replay_file: None,
+        replay_time_scale: 1.0,
+        replay_segment_timeout: 30,
     };
     server::listen(config).await.map_err(|e| e.to_string())
 }